@@ -0,0 +1,34 @@
+//! Lexes generated sources of doubling size and reports the throughput, which
+//! should stay roughly constant if the lexer is linear in the input size.
+//!
+//! Run with `cargo bench --bench lexer`.
+
+use std::time::Instant;
+
+use tcc::diagnostics::Diagnostics;
+use tcc::lexxer;
+use tcc::source_map::SourceMap;
+
+const FUNCTION : &str = "int main()
+{
+    // Comments are skipped by the scanner too.
+    return (1 + 22) * -333 / ~4444 - !55555; /* trailing */
+}
+";
+
+fn main()
+{
+    println!("{:>12} {:>12} {:>12} {:>10}", "bytes", "tokens", "time (ms)", "MB/s");
+    for shift in 10..17
+    {
+        let source = FUNCTION.repeat(1 << shift);
+
+        let start = Instant::now();
+        let mut source_map = SourceMap::new();
+        let tokens = lexxer::lex_str("bench.c", &source, &mut source_map, &mut Diagnostics::new());
+        let elapsed = start.elapsed().as_secs_f64();
+
+        println!("{:>12} {:>12} {:>12.2} {:>10.1}", source.len(), tokens.len(), elapsed * 1000.0,
+                    source.len() as f64 / elapsed / 1_000_000.0);
+    }
+}
@@ -1,52 +1,1621 @@
+use std::collections::HashMap;
+
 use crate::ast::*;
+use crate::symbol_table::VarId;
+use crate::types::Type;
+
+
+/// State shared by the whole program while its assembly is generated.
+#[derive(Default)]
+struct Context
+{
+    /// Contents of every string literal, labelled `.LC<index>` in `.rodata`.
+    string_literals : Vec<Vec<u8>>,
+    /// Stack slot of every local variable of the current function, as an
+    /// offset from `%ebp`.
+    variables : HashMap<VarId, i32>,
+    /// Bytes of the current frame in use by the variables in scope.
+    stack_size : i32,
+    /// Largest `stack_size` reached in the current function, which is what
+    /// its frame has to reserve.
+    frame_size : i32,
+    /// Bytes pushed below the frame at the current point of the code, by
+    /// spilled operands and call arguments.
+    temporaries : i32,
+    /// Whether the current function calls any function, and so has to keep
+    /// the stack aligned.
+    makes_calls : bool,
+    /// Whether the current function returns a struct or union, to the
+    /// memory its caller passes the address of.
+    returns_record : bool,
+    /// Number of `.L<n>` jump labels handed out so far.
+    label_count : usize,
+    /// Where `break` and `continue` jump to in the innermost enclosing loop
+    /// or switch.
+    break_labels : Vec<String>,
+    continue_labels : Vec<String>,
+    /// Labels of every switch around the statement being generated,
+    /// innermost last.
+    switches : Vec<SwitchLabels>,
+    /// Assembly label of every `goto` label of the current function.
+    goto_labels : HashMap<String, String>,
+    /// Targets of every switch jump table, labelled `.LT<index>` in `.rodata`.
+    jump_tables : Vec<Vec<String>>,
+    /// Label of every variable with static storage.
+    static_labels : HashMap<VarId, String>,
+    /// Every variable with static storage, in the order they are emitted.
+    static_variables : Vec<StaticVariable>,
+}
+
+/// A variable with static storage, from every declaration of it.
+struct StaticVariable
+{
+    label : String,
+    ty : Type,
+    /// The value of every scalar its initializer gives, with its offset in
+    /// bytes and its type.
+    init : Option<Vec<(usize, Type, StaticValue)>>,
+    /// Whether any declaration of it is a definition, rather than `extern`.
+    defined : bool,
+    internal : bool,
+}
+
+/// The initial value of a scalar in static storage.
+enum StaticValue
+{
+    Integer(i64),
+    /// The address at the label, moved by an offset in bytes.
+    Address(String, i64),
+}
+
+struct SwitchLabels
+{
+    cases : HashMap<i64, String>,
+    default : String,
+}
+
+/// Where a variable lives.
+#[derive(Debug,Clone)]
+enum Location
+{
+    /// A slot at this offset from %ebp.
+    Frame(i32),
+    /// Static storage at this label.
+    Static(String),
+    /// Memory at the address in this register.
+    Address(&'static str),
+    /// Memory at the address kept in the frame slot at this offset, which
+    /// `prepare` loads into %ecx.
+    Indirect(i32),
+}
 
+impl Location
+{
+    /// The memory operand `offset` bytes into the object.
+    fn at(&self, offset : i32) -> String
+    {
+        match self
+        {
+            Location::Frame(slot) => return format!("{}(%ebp)", slot + offset),
+            Location::Static(label) if offset == 0 => return label.clone(),
+            Location::Static(label) => return format!("{}+{}", label, offset),
+            Location::Address(register) if offset == 0 => return format!("({})", register),
+            Location::Address(register) => return format!("{}({})", offset, register),
+            Location::Indirect(_) => return Location::Address("%ecx").at(offset),
+        };
+    }
+
+    /// Code that has to run before the operands of `at` can be used.
+    fn prepare(&self) -> String
+    {
+        match self
+        {
+            Location::Indirect(slot) => return format!("movl {}(%ebp), %ecx\n", slot),
+            _ => return String::new(),
+        };
+    }
+}
+
+impl Context
+{
+    /// Gives the variable `id` a slot in the frame, aligned as its type.
+    fn allocate(&mut self, id : VarId, ty : &Type) -> Location
+    {
+        let (size, align) = (ty.size() as i32, ty.align() as i32);
+        self.stack_size = (self.stack_size + size + align - 1) / align * align;
+        self.frame_size = self.frame_size.max(self.stack_size);
+        self.variables.insert(id, -self.stack_size);
+        return Location::Frame(-self.stack_size);
+    }
+
+    /// Reserves a 4 byte slot in the frame for a value the code of an
+    /// expression has to keep, until `stack_size` is restored.
+    fn temporary_slot(&mut self) -> i32
+    {
+        self.stack_size = (self.stack_size + 7) / 4 * 4;
+        self.frame_size = self.frame_size.max(self.stack_size);
+        return -self.stack_size;
+    }
+
+    /// Reserves a slot in the frame for an object of type `ty` that a call
+    /// returns, until `stack_size` is restored.
+    fn temporary_object(&mut self, ty : &Type) -> i32
+    {
+        let (size, align) = (ty.size() as i32, ty.align() as i32);
+        self.stack_size = (self.stack_size + size + align - 1) / align * align;
+        self.frame_size = self.frame_size.max(self.stack_size);
+        return -self.stack_size;
+    }
+
+    fn location(&self, id : VarId) -> Location
+    {
+        match self.variables.get(&id)
+        {
+            Some(offset) => return Location::Frame(*offset),
+            None => return Location::Static(self.static_labels[&id].clone()),
+        };
+    }
+
+    /// Records a declaration of a variable with static storage, merging it
+    /// with earlier declarations of the same variable.
+    fn declare_static(&mut self, id : VarId, label : String, declaration : &Declaration)
+    {
+        // The label is known before the initializer, which may refer to it.
+        let previous = self.static_labels.get(&id).cloned();
+        if previous.is_none()
+        {
+            self.static_labels.insert(id, label.clone());
+        }
+        let init = declaration.init.as_ref().map(|init| self.static_init(&declaration.ty, init));
+        let defined = declaration.storage != Some(StorageClass::Extern) || init.is_some();
+        let internal = declaration.storage == Some(StorageClass::Static);
+        if let Some(previous) = previous
+        {
+            let variable = self.static_variables.iter_mut().find(|variable| variable.label == previous).unwrap();
+            variable.init = variable.init.take().or(init);
+            variable.defined |= defined;
+            variable.internal |= internal;
+            return;
+        }
+        self.static_variables.push(StaticVariable{label, ty: declaration.ty.clone(), init, defined, internal});
+    }
+
+    fn static_init(&mut self, ty : &Type, init : &Initializer) -> Vec<(usize, Type, StaticValue)>
+    {
+        match init
+        {
+            Initializer::Single(value) => return vec!((0, ty.clone(), self.static_value(value))),
+            Initializer::List(values) => return values.iter().map(|(offset, value)| (*offset, value.ty(), self.static_value(value))).collect(),
+        };
+    }
+
+    fn static_value(&mut self, value : &Expression) -> StaticValue
+    {
+        if let Some(value) = value.constant_value()
+        {
+            return StaticValue::Integer(value);
+        }
+        match value.address_constant()
+        {
+            Some((Factor::Variable(variable, _), offset)) => return StaticValue::Address(self.static_labels[&variable.id].clone(), offset),
+            Some((Factor::StringLiteral(bytes, _), offset)) => return StaticValue::Address(self.string_label(bytes), offset),
+            _ => unreachable!("static storage is only initialized with constants"),
+        };
+    }
+
+    fn new_label(&mut self) -> String
+    {
+        self.label_count += 1;
+        return format!(".L{}", self.label_count - 1);
+    }
+
+    fn goto_label(&mut self, name : &str) -> String
+    {
+        if !self.goto_labels.contains_key(name)
+        {
+            let label = self.new_label();
+            self.goto_labels.insert(name.to_string(), label);
+        }
+        return self.goto_labels[name].clone();
+    }
+
+    fn jump_table_label(&mut self, targets : Vec<String>) -> String
+    {
+        self.jump_tables.push(targets);
+        return format!(".LT{}", self.jump_tables.len() - 1);
+    }
+
+    fn string_label(&mut self, bytes : &[u8]) -> String
+    {
+        self.string_literals.push(bytes.to_vec());
+        return format!(".LC{}", self.string_literals.len() - 1);
+    }
+}
 
 pub fn generate_asm(program_ast : &Program) -> String
 {
-    match program_ast
+    let mut context = Context::default();
+    // Functions can refer to globals declared after them, so every label is
+    // known before any code is generated.
+    for declaration in &program_ast.declarations
+    {
+        if let ExternalDeclaration::Variable(declaration) = declaration
+        {
+            context.declare_static(declaration.id, declaration.name.clone(), declaration);
+        }
+    }
+    let mut asm = String::new();
+    for declaration in &program_ast.declarations
+    {
+        if let ExternalDeclaration::Function(func_decl) = declaration
+        {
+            asm += &function_asm(func_decl, &mut context);
+        }
+    }
+    return format!("{}{}{}", asm, data_asm(&context), rodata_asm(&context));
+}
+
+/// Emits every variable with static storage: initialized ones in `.data`,
+/// zero initialized ones in `.bss` and tentative definitions as common
+/// symbols, which the linker merges with definitions in other files.
+fn data_asm(context : &Context) -> String
+{
+    let mut asm = String::new();
+    for variable in &context.static_variables
+    {
+        if !variable.defined
+        {
+            continue;
+        }
+        let label = &variable.label;
+        let (size, align) = (variable.ty.size(), variable.ty.align());
+        let visibility = if variable.internal { format!(".local {}\n", label) } else { format!(".globl {}\n", label) };
+        match &variable.init
+        {
+            Some(values) if values.iter().all(|(_, _, value)| matches!(value, StaticValue::Integer(0))) => {
+                asm += &format!("{}.bss\n.align {}\n{}:\n.zero {}\n", visibility, align, label, size);
+            },
+            Some(values) => asm += &format!("{}.data\n.align {}\n{}:\n{}", visibility, align, label, values_asm(values, size)),
+            None => asm += &format!("{}.comm {},{},{}\n", visibility, label, size, align),
+        };
+    }
+    return asm;
+}
+
+// Lays out the initial values of a variable of `size` bytes, with zeros
+// wherever there is no value.
+fn values_asm(values : &[(usize, Type, StaticValue)], size : usize) -> String
+{
+    let mut asm = String::new();
+    let mut position = 0;
+    for (offset, ty, value) in values
+    {
+        if *offset > position
+        {
+            asm += &format!(".zero {}\n", offset - position);
+        }
+        match value
+        {
+            StaticValue::Integer(value) => asm += &format!("{} {}\n", data_directive(ty.size()), value),
+            StaticValue::Address(label, 0) => asm += &format!(".long {}\n", label),
+            StaticValue::Address(label, offset) => asm += &format!(".long {}{:+}\n", label, offset),
+        };
+        position = offset + ty.size();
+    }
+    if size > position
+    {
+        asm += &format!(".zero {}\n", size - position);
+    }
+    return asm;
+}
+
+fn data_directive(size : usize) -> &'static str
+{
+    match size
+    {
+        1 => return ".byte",
+        2 => return ".value",
+        8 => return ".quad",
+        _ => return ".long",
+    };
+}
+
+fn rodata_asm(context : &Context) -> String
+{
+    if context.string_literals.is_empty() && context.jump_tables.is_empty()
+    {
+        return String::new();
+    }
+    let mut asm = String::from(".section .rodata\n");
+    for (index, targets) in context.jump_tables.iter().enumerate()
+    {
+        asm += &format!(".align 4\n.LT{}:\n", index);
+        for target in targets
+        {
+            asm += &format!(".long {}\n", target);
+        }
+    }
+    for (index, bytes) in context.string_literals.iter().enumerate()
+    {
+        asm += &format!(".LC{}:\n.string \"{}\"\n", index, escape_string(bytes));
+    }
+    return asm;
+}
+
+/// Spells `bytes` for a GNU as `.string` directive, which adds the final NUL.
+fn escape_string(bytes : &[u8]) -> String
+{
+    let mut escaped = String::new();
+    for byte in bytes
+    {
+        match byte
+        {
+            b'"' => escaped += "\\\"",
+            b'\\' => escaped += "\\\\",
+            0x20..=0x7e => escaped.push(*byte as char),
+            _ => escaped += &format!("\\{:03o}", byte),
+        }
+    }
+    return escaped;
+}
+
+const EPILOGUE : &str = "movl %ebp, %esp\npop %ebp\nret\n";
+
+// Like gcc, a function returning a struct or union gives back the address
+// its caller passed for it and pops it.
+fn epilogue_asm(context : &Context) -> String
+{
+    if context.returns_record
+    {
+        return format!("movl {}(%ebp), %eax\nmovl %ebp, %esp\npop %ebp\nret $4\n", FIRST_PARAMETER_OFFSET);
+    }
+    return String::from(EPILOGUE);
+}
+
+/// Offset from %ebp of the first parameter, above the saved %ebp and the
+/// return address.
+const FIRST_PARAMETER_OFFSET : i32 = 8;
+
+fn function_asm(func_decl : &FunctionDecl, context : &mut Context) -> String
+{
+    // A prototype generates nothing.
+    let Some(items) = &func_decl.body else { return String::new() };
+    context.variables.clear();
+    context.goto_labels.clear();
+    context.stack_size = 0;
+    context.frame_size = 0;
+    context.temporaries = 0;
+    context.makes_calls = false;
+    context.returns_record = func_decl.return_type.is_record();
+    // cdecl passes the arguments on the stack, the first one lowest, each
+    // taking a multiple of 4 bytes, after the address to return a struct or
+    // union to.
+    let mut offset = FIRST_PARAMETER_OFFSET;
+    if context.returns_record
+    {
+        offset += 4;
+    }
+    for param in &func_decl.params
+    {
+        if let Some(id) = param.id
+        {
+            context.variables.insert(id, offset);
+        }
+        offset += stack_size_of(&param.ty);
+    }
+    let mut body = block_asm(items, context);
+
+    // Falling off the end of `main` returns 0 (C99 5.1.2.2.3). For any other
+    // function the value is undefined, but control must still get back to
+    // the caller.
+    if func_decl.name == "main"
+    {
+        body += "movl $0, %eax\n";
+    }
+    body += &epilogue_asm(context);
+
+    // The frame size is only known once every variable has a slot. The
+    // caller left %esp 16 byte aligned before pushing the return address, and
+    // %ebp is pushed too, so a frame of 8 bytes modulo 16 keeps calls made
+    // from this function aligned.
+    let mut frame_size = context.frame_size;
+    if context.makes_calls
+    {
+        frame_size += (24 - frame_size % 16) % 16;
+    }
+    let mut prologue = String::from("push %ebp\nmovl %esp, %ebp\n");
+    if frame_size > 0
     {
-        Program::Declaration(func_decl) => return function_asm(&func_decl),
+        prologue += &format!("subl ${}, %esp\n", frame_size);
+    }
+    let visibility = match func_decl.storage
+    {
+        Some(StorageClass::Static) => String::new(),
+        _ => format!(".globl {}\n", func_decl.name),
     };
+    return format!("{}{}:\n{}{}", visibility, &func_decl.name, prologue, body);
 }
 
-fn function_asm(func_decl : &FunctionDecl) -> String
+// Slots of the variables declared in a block are released at its end, so
+// sibling blocks share the same part of the frame.
+fn block_asm(items : &[BlockItem], context : &mut Context) -> String
 {
-    return format!(".globl {}\n{}:\n{}", &func_decl.name,&func_decl.name, statement_asm(&func_decl.body));
+    let stack_size = context.stack_size;
+    let asm = items.iter().map(|item| block_item_asm(item, context)).collect();
+    context.stack_size = stack_size;
+    return asm;
+}
+
+fn block_item_asm(item : &BlockItem, context : &mut Context) -> String
+{
+    match item
+    {
+        BlockItem::Statement(statement) => return statement_asm(statement, context),
+        BlockItem::Declaration(declaration) => return declaration_asm(declaration, context),
+        BlockItem::TypeDeclaration(_) => return String::new(),
+    };
 }
 
-fn statement_asm(statement : &Statement) -> String
+fn declaration_asm(declaration : &Declaration, context : &mut Context) -> String
+{
+    match declaration.storage
+    {
+        // Refers to a global, which may not be declared at file scope.
+        Some(StorageClass::Extern) => {
+            context.declare_static(declaration.id, declaration.name.clone(), declaration);
+            return String::new();
+        },
+        // Initialized once, before the program runs. The id keeps the labels of
+        // statics with the same name apart.
+        Some(StorageClass::Static) => {
+            let label = format!("{}.{}", declaration.name, declaration.id.0);
+            context.declare_static(declaration.id, label, declaration);
+            return String::new();
+        },
+        Some(StorageClass::Typedef) => unreachable!("typedefs declare no variables"),
+        None => {},
+    };
+    let location = context.allocate(declaration.id, &declaration.ty);
+    match &declaration.init
+    {
+        Some(Initializer::Single(init)) => return format!("{}{}", expression_asm(init, context), store_asm(&declaration.ty, &location)),
+        // What the list leaves out is zero.
+        Some(Initializer::List(values)) => {
+            let Location::Frame(slot) = location else { unreachable!("local variables are in the frame") };
+            let mut asm = zero_asm(slot, declaration.ty.size());
+            for (offset, value) in values
+            {
+                asm += &format!("{}{}", expression_asm(value, context), store_asm(&value.ty(), &Location::Frame(slot + *offset as i32)));
+            }
+            return asm;
+        },
+        None => return String::new(),
+    };
+}
+
+/// Fewest words worth clearing or copying with a `rep` string instruction
+/// rather than one by one.
+const REP_MIN_WORDS : usize = 8;
+
+// Clears `size` bytes of the frame from the slot at `slot` on. `rep stosl`
+// stores %eax to %ecx words from the address in %edi, which the caller of
+// the function expects to be kept.
+fn zero_asm(slot : i32, size : usize) -> String
+{
+    let words = size / 4;
+    let mut asm = if words >= REP_MIN_WORDS
+    {
+        format!("push %edi\nleal {}(%ebp), %edi\nmovl ${}, %ecx\nxorl %eax, %eax\nrep stosl\npop %edi\n", slot, words)
+    }
+    else
+    {
+        (0..words).map(|word| format!("movl $0, {}(%ebp)\n", slot + 4 * word as i32)).collect()
+    };
+    for byte in words * 4..size
+    {
+        asm += &format!("movb $0, {}(%ebp)\n", slot + byte as i32);
+    }
+    return asm;
+}
+
+fn statement_asm(statement : &Statement, context : &mut Context) -> String
 {
     match statement
     {
-        Statement::Return(exp) => return format!("{}\nret\n", expression_asm(&exp)),
+        Statement::Return(Some(exp), _) if exp.ty().is_record() => {
+            let store = store_asm(&exp.ty(), &Location::Indirect(FIRST_PARAMETER_OFFSET));
+            return format!("{}{}{}", expression_asm(exp, context), store, epilogue_asm(context));
+        },
+        Statement::Return(Some(exp), _) => return format!("{}{}", expression_asm(exp, context), epilogue_asm(context)),
+        Statement::Return(None, _) => return epilogue_asm(context),
+        Statement::Expression(exp, _) => return expression_asm(exp, context),
+        Statement::Compound(items, _) => return block_asm(items, context),
+        Statement::If(condition, then, otherwise, _) => {
+            let then_asm = statement_asm(then, context);
+            let otherwise_asm = otherwise.as_ref().map(|statement| statement_asm(statement, context));
+            return branch_asm(condition_asm(condition, context), then_asm, otherwise_asm, context);
+        },
+        Statement::While(condition, body, _) => {
+            let start = context.new_label();
+            let end = context.new_label();
+            let body_asm = loop_body_asm(body, &end, &start, context);
+            return format!("{}:\n{}cmpl $0, %eax\nje {}\n{}jmp {}\n{}:\n",
+                            start, condition_asm(condition, context), end, body_asm, start, end);
+        },
+        Statement::DoWhile(body, condition, _) => {
+            let start = context.new_label();
+            let next = context.new_label();
+            let end = context.new_label();
+            let body_asm = loop_body_asm(body, &end, &next, context);
+            return format!("{}:\n{}{}:\n{}cmpl $0, %eax\njne {}\n{}:\n",
+                            start, body_asm, next, condition_asm(condition, context), start, end);
+        },
+        Statement::For(init, condition, step, body, _) => return for_asm(init.as_ref(), condition.as_ref(), step.as_ref(), body, context),
+        Statement::Break(_) => return format!("jmp {}\n", context.break_labels.last().unwrap()),
+        Statement::Continue(_) => return format!("jmp {}\n", context.continue_labels.last().unwrap()),
+        Statement::Null(_) => return String::new(),
+        Statement::Switch(value, body, cases, _) => return switch_asm(value, body, cases, context),
+        Statement::Case(value, statement, _) => {
+            let label = context.switches.last().unwrap().cases[value].clone();
+            return format!("{}:\n{}", label, statement_asm(statement, context));
+        },
+        Statement::Default(statement, _) => {
+            let label = context.switches.last().unwrap().default.clone();
+            return format!("{}:\n{}", label, statement_asm(statement, context));
+        },
+        // Every variable has a fixed slot in the frame and %esp does not move
+        // between statements, so jumping into or out of a block needs no stack
+        // adjustment. Variables of the block jumped into just hold whatever
+        // their slot holds, as C allows.
+        Statement::Labeled(name, statement, _) => return format!("{}:\n{}", context.goto_label(name), statement_asm(statement, context)),
+        Statement::Goto(name, _) => return format!("jmp {}\n", context.goto_label(name)),
     };
 }
 
-fn expression_asm(expression : &Expression) -> String
+fn for_asm(init : Option<&ForInit>, condition : Option<&Expression>, step : Option<&Expression>, body : &Statement,
+            context : &mut Context) -> String
+{
+    // A variable declared by the loop is released after it, like in a block.
+    let stack_size = context.stack_size;
+    let init_asm = match init
+    {
+        Some(ForInit::Declaration(declarations)) => declarations.iter().map(|declaration| declaration_asm(declaration, context)).collect(),
+        Some(ForInit::Expression(expression)) => expression_asm(expression, context),
+        None => String::new(),
+    };
+    let start = context.new_label();
+    let next = context.new_label();
+    let end = context.new_label();
+    // Without a condition the loop only ends through `break` or `return`.
+    let condition_asm = condition.map_or(String::new(),
+                            |condition| format!("{}cmpl $0, %eax\nje {}\n", condition_asm(condition, context), end));
+    let body_asm = loop_body_asm(body, &end, &next, context);
+    let step_asm = step.map_or(String::new(), |step| expression_asm(step, context));
+    context.stack_size = stack_size;
+    return format!("{}{}:\n{}{}{}:\n{}jmp {}\n{}:\n", init_asm, start, condition_asm, body_asm, next, step_asm, start, end);
+}
+
+// Generates a loop body in which `break` jumps to `end` and `continue` to
+// `next`.
+fn loop_body_asm(body : &Statement, end : &str, next : &str, context : &mut Context) -> String
+{
+    context.break_labels.push(end.to_string());
+    context.continue_labels.push(next.to_string());
+    let asm = statement_asm(body, context);
+    context.break_labels.pop();
+    context.continue_labels.pop();
+    return asm;
+}
+
+fn switch_asm(value : &Expression, body : &Statement, cases : &SwitchCases, context : &mut Context) -> String
+{
+    let end = context.new_label();
+    let labels : HashMap<i64, String> = cases.values.iter().map(|(value, _)| (*value, context.new_label())).collect();
+    let default = if cases.default.is_some() { context.new_label() } else { end.clone() };
+
+    // Case values are already converted to the type of the controlling
+    // expression, so they are ordered as unsigned numbers when it is
+    // unsigned.
+    let mut targets : Vec<(i64, String)> = labels.iter().map(|(value, label)| (*value, label.clone())).collect();
+    targets.sort();
+    let dispatch = if cases.ty.size() == 8
+    {
+        long_dispatch_asm(&targets, &default, context)
+    }
+    else
+    {
+        dispatch_asm(&targets, &default, cases.ty.is_unsigned(), context)
+    };
+
+    context.switches.push(SwitchLabels{cases: labels, default});
+    context.break_labels.push(end.clone());
+    let body_asm = statement_asm(body, context);
+    context.break_labels.pop();
+    context.switches.pop();
+    return format!("{}{}{}{}:\n", expression_asm(value, context), dispatch, body_asm, end);
+}
+
+/// Fewest cases worth a jump table.
+const JUMP_TABLE_MIN_CASES : usize = 4;
+
+// Jumps from the value in %eax to the label of its case in `targets`, which
+// are sorted by value, or to `default`. Dense cases index a jump table, where
+// at least a third of the entries must be real cases; sparse ones are found
+// by binary search.
+fn dispatch_asm(targets : &[(i64, String)], default : &str, unsigned : bool, context : &mut Context) -> String
+{
+    if targets.len() >= JUMP_TABLE_MIN_CASES
+    {
+        let min = targets[0].0;
+        let range = targets[targets.len() - 1].0 - min + 1;
+        if range <= 3 * targets.len() as i64
+        {
+            let mut table = vec!(default.to_string(); range as usize);
+            for (value, label) in targets
+            {
+                table[(value - min) as usize] = label.clone();
+            }
+            let table_label = context.jump_table_label(table);
+            // After subtracting the smallest value, values below it wrap
+            // around to large unsigned numbers, so one unsigned comparison
+            // catches values on both sides of the table.
+            return format!("subl ${}, %eax\ncmpl ${}, %eax\nja {}\njmp *{}(,%eax,4)\n",
+                            min as i32, range - 1, default, table_label);
+        }
+    }
+    return search_asm(targets, default, unsigned, context);
+}
+
+fn search_asm(targets : &[(i64, String)], default : &str, unsigned : bool, context : &mut Context) -> String
+{
+    if targets.len() <= 3
+    {
+        let mut asm : String = targets.iter().map(|(value, label)| format!("cmpl ${}, %eax\nje {}\n", *value as i32, label)).collect();
+        asm += &format!("jmp {}\n", default);
+        return asm;
+    }
+    let middle = targets.len() / 2;
+    let (value, label) = &targets[middle];
+    let lower = context.new_label();
+    let below = if unsigned { "jb" } else { "jl" };
+    let upper_asm = search_asm(&targets[middle + 1..], default, unsigned, context);
+    let lower_asm = search_asm(&targets[..middle], default, unsigned, context);
+    return format!("cmpl ${}, %eax\nje {}\n{} {}\n{}{}:\n{}", *value as i32, label, below, lower, upper_asm, lower, lower_asm);
+}
+
+// Jumps from the 64 bit value in %edx:%eax to its case, comparing one case
+// after the other.
+fn long_dispatch_asm(targets : &[(i64, String)], default : &str, context : &mut Context) -> String
+{
+    let mut asm = String::new();
+    for (value, label) in targets
+    {
+        let next = context.new_label();
+        asm += &format!("cmpl ${}, %edx\njne {}\ncmpl ${}, %eax\nje {}\n{}:\n", (value >> 32) as i32, next, *value as i32, label, next);
+    }
+    return asm + &format!("jmp {}\n", default);
+}
+
+fn expression_asm(expression : &Expression, context : &mut Context) -> String
 {
     match expression
     {
-        // Expression::Constant(const_str) => return format!("{}", constant_asm(&const_str)),
-        // Expression::UnOp(oper, exp) => return format!("{}\n{}",expression_asm(exp), unioperator_asm(oper)),
-        Expression::BinOp(oper, exp,expr) => return "".to_string(),
-        _ => return "".to_string(), //TODO:REMOVE
+        Expression::Assign(target, value, _) => {
+            let stack_size = context.stack_size;
+            let (place_asm, location) = place_asm(target, context);
+            let asm = format!("{}{}{}", place_asm, expression_asm(value, context), store_asm(&target.ty(), &location));
+            context.stack_size = stack_size;
+            return asm;
+        },
+        // The target is both read and written through its location, so it is
+        // not evaluated twice.
+        Expression::CompoundAssign(oper, target, value, _) => {
+            let stack_size = context.stack_size;
+            let (place_asm, location) = place_asm(target, context);
+            let target_type = target.ty();
+            let (ty, _) = oper.operand_types(&target_type, &value.ty());
+            let load_asm = format!("{}{}", load_asm(&target_type, &location), conversion_asm(&target_type, &ty));
+            let operation_asm = if ty.size() == 8
+            {
+                long_binop_asm(*oper, &ty, &|_| load_asm.clone(), value, context)
+            }
+            else
+            {
+                let rhs_asm = format!("{}{}", spilled_asm(value, context), scale_asm(&ty));
+                format!("{}{}", load_asm, binary_asm(*oper, &rhs_asm, is_unsigned(&ty)))
+            };
+            context.stack_size = stack_size;
+            return format!("{}{}{}{}", place_asm, operation_asm, conversion_asm(&ty, &target_type), store_asm(&target_type, &location));
+        },
+        Expression::Comma(lhs, rhs, _) => return format!("{}{}", expression_asm(lhs, context), expression_asm(rhs, context)),
+        Expression::BinOp(oper, lhs, rhs, _, _) => return binop_asm(*oper, lhs, rhs, context),
+        Expression::Conditional(condition, then, otherwise, _) => {
+            let condition_asm = condition_asm(condition, context);
+            let then_asm = expression_asm(then, context);
+            let otherwise_asm = expression_asm(otherwise, context);
+            return branch_asm(condition_asm, then_asm, Some(otherwise_asm), context);
+        },
+        // An array used as a value is the address of its first element.
+        Expression::Convert(_, array) if array.ty().is_array() => {
+            let Expression::Factor(array) = array.as_ref() else { unreachable!("only factors are arrays") };
+            return address_asm(array, context);
+        },
+        Expression::Convert(ty, _) if expression.constant_value().is_some() => return constant_asm(expression.constant_value().unwrap(), ty),
+        Expression::Convert(ty, expression) => return format!("{}{}", expression_asm(expression, context), conversion_asm(&expression.ty(), ty)),
+        Expression::Factor(factor) => return factor_asm(factor, context),
+    };
+}
+
+// Leaves a value in %eax that is zero exactly when the expression is, for a
+// `cmpl $0, %eax`.
+fn condition_asm(expression : &Expression, context : &mut Context) -> String
+{
+    return format!("{}{}", expression_asm(expression, context), truth_asm(&expression.ty()));
+}
+
+// Folds the high half of a 64 bit value into %eax, so that %eax is zero
+// exactly when the value is.
+fn truth_asm(ty : &Type) -> &'static str
+{
+    if ty.size() == 8
+    {
+        return "orl %edx, %eax\n";
+    }
+    return "";
+}
+
+// Runs `then_asm` when the condition left a non-zero value in %eax and
+// `otherwise_asm`, if any, when it left 0.
+fn branch_asm(condition_asm : String, then_asm : String, otherwise_asm : Option<String>, context : &mut Context) -> String
+{
+    let end = context.new_label();
+    match otherwise_asm
+    {
+        Some(otherwise_asm) => {
+            let otherwise = context.new_label();
+            return format!("{}cmpl $0, %eax\nje {}\n{}jmp {}\n{}:\n{}{}:\n",
+                            condition_asm, otherwise, then_asm, end, otherwise, otherwise_asm, end);
+        },
+        None => return format!("{}cmpl $0, %eax\nje {}\n{}{}:\n", condition_asm, end, then_asm, end),
     };
 }
 
-fn unioperator_asm(oper : &UniOperator) -> String
+// Values narrower than 32 bits are kept extended to all of %eax, as their
+// type's signedness says, so they can be used as `int`s. 64 bit values are
+// kept in %edx:%eax. A struct or union is too big for registers, its value is
+// its address.
+fn load_asm(ty : &Type, location : &Location) -> String
 {
+    if ty.is_record()
+    {
+        if let Location::Address("%eax") = location
+        {
+            return String::new();
+        }
+        return format!("{}leal {}, %eax\n", location.prepare(), location.at(0));
+    }
+    let load = match (ty.size(), ty.is_unsigned())
+    {
+        // A `void` value is never used.
+        (0, _) => String::new(),
+        (1, true) => format!("movzbl {}, %eax\n", location.at(0)),
+        (1, false) => format!("movsbl {}, %eax\n", location.at(0)),
+        (2, true) => format!("movzwl {}, %eax\n", location.at(0)),
+        (2, false) => format!("movswl {}, %eax\n", location.at(0)),
+        // The high half first, in case the address is in %eax.
+        (8, _) => format!("movl {}, %edx\nmovl {}, %eax\n", location.at(4), location.at(0)),
+        _ => format!("movl {}, %eax\n", location.at(0)),
+    };
+    return format!("{}{}", location.prepare(), load);
+}
+
+fn store_asm(ty : &Type, location : &Location) -> String
+{
+    if ty.is_record()
+    {
+        return format!("{}{}", location.prepare(), copy_asm(ty.size(), location));
+    }
+    let store = match ty.size()
+    {
+        1 => format!("movb %al, {}\n", location.at(0)),
+        2 => format!("movw %ax, {}\n", location.at(0)),
+        8 => format!("movl %eax, {}\nmovl %edx, {}\n", location.at(0), location.at(4)),
+        _ => format!("movl %eax, {}\n", location.at(0)),
+    };
+    return format!("{}{}", location.prepare(), store);
+}
+
+// Copies the struct or union of `size` bytes at the address in %eax to
+// `location`, whose `prepare` has run, keeping %eax. Big ones are copied by
+// `rep movsl`, which moves %ecx words from the address in %esi to the one in
+// %edi, registers the caller of the function expects to be kept.
+fn copy_asm(size : usize, location : &Location) -> String
+{
+    let words = size / 4;
+    if words >= REP_MIN_WORDS
+    {
+        let mut asm = format!("leal {}, %edx\npush %esi\npush %edi\nmovl %edx, %edi\nmovl %eax, %esi\nmovl ${}, %ecx\nrep movsl\n",
+                                location.at(0), words);
+        if size % 4 >= 2
+        {
+            asm += "movsw\n";
+        }
+        if size % 2 == 1
+        {
+            asm += "movsb\n";
+        }
+        return asm + "pop %edi\npop %esi\n";
+    }
+    let mut asm = String::new();
+    let mut offset = 0;
+    while offset < size
+    {
+        let (mov, register, width) = match size - offset
+        {
+            4.. => ("movl", "%edx", 4),
+            2 | 3 => ("movw", "%dx", 2),
+            _ => ("movb", "%dl", 1),
+        };
+        let source = Location::Address("%eax").at(offset as i32);
+        asm += &format!("{} {}, {}\n{} {}, {}\n", mov, source, register, mov, register, location.at(offset as i32));
+        offset += width;
+    }
+    return asm;
+}
+
+// The location of the object `target` designates when it is known without
+// running any code, as for a variable or a member of one.
+fn fixed_location(target : &Factor, context : &Context) -> Option<Location>
+{
+    match target
+    {
+        Factor::Variable(variable, _) => return Some(context.location(variable.id)),
+        Factor::Expr(expression, _) => match expression.as_ref()
+        {
+            Expression::Factor(target) => return fixed_location(target, context),
+            _ => return None,
+        },
+        Factor::Member(object, _, offset, _, _) => match fixed_location(object, context)?
+        {
+            Location::Frame(slot) => return Some(Location::Frame(slot + *offset as i32)),
+            Location::Static(label) if *offset == 0 => return Some(Location::Static(label)),
+            Location::Static(label) => return Some(Location::Static(format!("{}+{}", label, offset))),
+            Location::Address(_) | Location::Indirect(_) => return None,
+        },
+        _ => return None,
+    };
+}
+
+// Finds the object the lvalue `target` designates. A variable or a member of
+// one is at a fixed location, anything else at an address that is computed
+// first and kept in a frame slot, since the code storing a value may need
+// every register. The caller releases the slot by restoring `stack_size`.
+fn place_asm(target : &Factor, context : &mut Context) -> (String, Location)
+{
+    if let Some(location) = fixed_location(target, context)
+    {
+        return (String::new(), location);
+    }
+    let asm = address_asm(target, context);
+    let slot = context.temporary_slot();
+    return (format!("{}movl %eax, {}(%ebp)\n", asm, slot), Location::Indirect(slot));
+}
+
+// Computes the address of the lvalue `target` into %eax.
+fn address_asm(target : &Factor, context : &mut Context) -> String
+{
+    match fixed_location(target, context)
+    {
+        Some(Location::Frame(offset)) => return format!("leal {}(%ebp), %eax\n", offset),
+        Some(Location::Static(label)) => return format!("movl ${}, %eax\n", label),
+        Some(Location::Address(_) | Location::Indirect(_)) => unreachable!("variables are in the frame or in static storage"),
+        None => {},
+    };
+    match target
+    {
+        Factor::Expr(expression, _) => {
+            let Expression::Factor(target) = expression.as_ref() else { unreachable!("only factors are lvalues") };
+            return address_asm(target, context);
+        },
+        Factor::StringLiteral(bytes, _) => return format!("movl ${}, %eax\n", context.string_label(bytes)),
+        // `&*pointer` is just the pointer.
+        Factor::UnOp(UniOperator::Dereference, pointer, _) => return factor_asm(pointer, context),
+        // The value of a struct or union is its address, even when a call
+        // returns it.
+        Factor::Member(object, _, 0, _, _) => return factor_asm(object, context),
+        Factor::Member(object, _, offset, _, _) => return format!("{}addl ${}, %eax\n", factor_asm(object, context), offset),
+        _ => unreachable!("only lvalues have an address"),
+    };
+}
+
+// Converts the value of type `from` in %eax or %edx:%eax to `to`.
+fn conversion_asm(from : &Type, to : &Type) -> String
+{
+    if *to == Type::Void || from == to
+    {
+        return String::new();
+    }
+    if *to == Type::Bool
+    {
+        return format!("{}cmpl $0, %eax\nmovl $0, %eax\nsetne %al\n", truth_asm(from));
+    }
+    match to.size()
+    {
+        8 if from.size() == 8 => return String::new(),
+        8 if from.is_unsigned() => return String::from("xorl %edx, %edx\n"),
+        8 => return String::from("cltd\n"),
+        // Narrower values are already extended to 32 bits, and wider ones
+        // just lose their high half.
+        4 => return String::new(),
+        _ if from.fits_in(to) => return String::new(),
+        _ => return extension_asm(to),
+    };
+}
+
+// Extends the value of a type narrower than 32 bits from %al or %ax to all
+// of %eax.
+fn extension_asm(ty : &Type) -> String
+{
+    match (ty.size(), ty.is_unsigned())
+    {
+        (1, true) => return String::from("movzbl %al, %eax\n"),
+        (1, false) => return String::from("movsbl %al, %eax\n"),
+        (2, true) => return String::from("movzwl %ax, %eax\n"),
+        (2, false) => return String::from("movswl %ax, %eax\n"),
+        _ => return String::new(),
+    };
+}
+
+fn factor_asm(factor : &Factor, context : &mut Context) -> String
+{
+    match factor
+    {
+        Factor::Constant(_, _) | Factor::SizeOf(_, _) => return constant_asm(factor.constant_value().unwrap(), &factor.ty()),
+        Factor::StringLiteral(..) => return address_asm(factor, context),
+        Factor::Variable(variable, _) => return load_asm(&variable.ty, &context.location(variable.id)),
+        Factor::Member(_, _, _, ty, _) => match fixed_location(factor, context)
+        {
+            Some(location) => return load_asm(ty, &location),
+            None => return format!("{}{}", address_asm(factor, context), load_asm(ty, &Location::Address("%eax"))),
+        },
+        Factor::UnOp(UniOperator::AddressOf, operand, _) => return address_asm(operand, context),
+        Factor::UnOp(UniOperator::Dereference, operand, _) => {
+            return format!("{}{}", factor_asm(operand, context), load_asm(&factor.ty(), &Location::Address("%eax")));
+        },
+        Factor::UnOp(oper, operand, _) => return format!("{}{}", factor_asm(operand, context), unioperator_asm(oper, &operand.ty())),
+        Factor::Expr(expression, _) => return expression_asm(expression, context),
+        Factor::Call(name, args, ty, _) => {
+            // Only the low bits of a narrow result are defined, as in gcc.
+            let extension = if ty.size() < 4 && !ty.is_record() { extension_asm(ty) } else { String::new() };
+            return format!("{}{}", call_asm(name, args, ty, context), extension);
+        },
+        Factor::Cast(ty, operand, _) => return format!("{}{}", factor_asm(operand, context), conversion_asm(&operand.ty(), ty)),
+        Factor::IncDec(oper, target, _) => {
+            let stack_size = context.stack_size;
+            let (place_asm, location) = place_asm(target, context);
+            context.stack_size = stack_size;
+            // Nothing runs between the update and the load, so an address can
+            // stay in %ecx.
+            let place_asm = format!("{}{}", place_asm, location.prepare());
+            let location = if let Location::Indirect(_) = location { Location::Address("%ecx") } else { location };
+            let ty = target.ty();
+            let increment = matches!(oper, IncDecOperator::PreIncrement | IncDecOperator::PostIncrement);
+            let update = match (&ty, ty.size())
+            {
+                // A `_Bool` becomes 1 when incremented, and flips when decremented.
+                (Type::Bool, _) if increment => format!("movb $1, {}\n", location.at(0)),
+                (Type::Bool, _) => format!("xorb $1, {}\n", location.at(0)),
+                (Type::Pointer(pointee), _) => format!("{} ${}, {}\n", if increment { "addl" } else { "subl" }, element_size(pointee), location.at(0)),
+                (_, 8) if increment => format!("addl $1, {}\nadcl $0, {}\n", location.at(0), location.at(4)),
+                (_, 8) => format!("subl $1, {}\nsbbl $0, {}\n", location.at(0), location.at(4)),
+                (_, size) => format!("{}{} {}\n", if increment { "inc" } else { "dec" }, size_suffix(size), location.at(0)),
+            };
+            let load = load_asm(&ty, &location);
+            match oper
+            {
+                IncDecOperator::PreIncrement | IncDecOperator::PreDecrement => return format!("{}{}{}", place_asm, update, load),
+                IncDecOperator::PostIncrement | IncDecOperator::PostDecrement => return format!("{}{}{}", place_asm, load, update),
+            };
+        },
+    };
+}
+
+fn size_suffix(size : usize) -> &'static str
+{
+    match size
+    {
+        1 => return "b",
+        2 => return "w",
+        _ => return "l",
+    };
+}
+
+fn binop_asm(oper : BiOperator, lhs : &Expression, rhs : &Expression, context : &mut Context) -> String
+{
+    let lhs_type = lhs.ty();
     match oper
     {
+        // The right operand is skipped once the left one decides the result.
+        // Both paths reach the end label with the flags of a comparison
+        // against 0, so `setne` turns either into 0 or 1.
+        BiOperator::LogicalAnd | BiOperator::LogicalOr => {
+            let lhs_asm = condition_asm(lhs, context);
+            let jump = if oper == BiOperator::LogicalAnd { "je" } else { "jne" };
+            let end = context.new_label();
+            return format!("{}cmpl $0, %eax\n{} {}\n{}cmpl $0, %eax\n{}:\nmovl $0, %eax\nsetne %al\n",
+                            lhs_asm, jump, end, condition_asm(rhs, context), end);
+        },
+        _ if lhs_type.is_pointer() || rhs.ty().is_pointer() => return pointer_binop_asm(oper, lhs, rhs, context),
+        // The operands have been converted to the type of the operation,
+        // except for shift counts.
+        _ if lhs_type.size() == 8 => return long_binop_asm(oper, &lhs_type, &|context| expression_asm(lhs, context), rhs, context),
+        _ => {
+            let lhs_asm = expression_asm(lhs, context);
+            return format!("{}{}", lhs_asm, binary_asm(oper, &spilled_asm(rhs, context), lhs_type.is_unsigned()));
+        },
+    };
+}
+
+// An integer added to or subtracted from a pointer counts values of the type
+// it points to, as does the difference of two pointers. Addresses compare as
+// unsigned numbers.
+fn pointer_binop_asm(oper : BiOperator, lhs : &Expression, rhs : &Expression, context : &mut Context) -> String
+{
+    let (lhs_type, rhs_type) = (lhs.ty(), rhs.ty());
+    let mut lhs_asm = expression_asm(lhs, context);
+    let mut rhs_asm = spilled_asm(rhs, context);
+    match (lhs_type.is_pointer(), rhs_type.is_pointer())
+    {
+        (true, false) => rhs_asm += &scale_asm(&lhs_type),
+        (false, true) => lhs_asm += &scale_asm(&rhs_type),
+        _ => {},
+    };
+    let asm = format!("{}{}", lhs_asm, binary_asm(oper, &rhs_asm, true));
+    match (oper, lhs_type.pointee(), rhs_type.is_pointer())
+    {
+        (BiOperator::Subtraction, Some(pointee), true) => return format!("{}{}", asm, unscale_asm(element_size(pointee))),
+        _ => return asm,
+    };
+}
+
+/// Bytes between consecutive values a pointer to `pointee` can point to.
+/// Like gcc, arithmetic on a `void *` moves it byte by byte.
+fn element_size(pointee : &Type) -> i32
+{
+    return pointee.size().max(1) as i32;
+}
+
+// Multiplies the integer in %eax, which is added to a pointer of type
+// `pointer`, by the size of the values the pointer moves over. Nothing is
+// done when `pointer` is an integer.
+fn scale_asm(pointer : &Type) -> String
+{
+    match pointer.pointee().map(element_size)
+    {
+        Some(1) | None => return String::new(),
+        Some(size) => return format!("imull ${}, %eax\n", size),
+    };
+}
+
+// Divides the difference of two addresses in %eax by the size of the values
+// they point to, which it is a multiple of.
+fn unscale_asm(size : i32) -> String
+{
+    match size
+    {
+        1 => return String::new(),
+        _ if size.count_ones() == 1 => return format!("sarl ${}, %eax\n", size.trailing_zeros()),
+        _ => return format!("movl ${}, %ecx\ncltd\nidivl %ecx\n", size),
+    };
+}
+
+// Whether comparisons, divisions and right shifts of values of the type treat
+// them as unsigned.
+fn is_unsigned(ty : &Type) -> bool
+{
+    return ty.is_unsigned() || ty.is_pointer();
+}
+
+// Generates the right operand of `binary_asm`, which runs while the left one
+// is pushed on the stack.
+fn spilled_asm(expression : &Expression, context : &mut Context) -> String
+{
+    context.temporaries += 4;
+    let asm = expression_asm(expression, context);
+    context.temporaries -= 4;
+    return asm;
+}
+
+/// Bytes a value of type `ty` takes as an argument on the stack.
+fn stack_size_of(ty : &Type) -> i32
+{
+    return (ty.size() as i32 + 3) / 4 * 4;
+}
+
+// Pushes the value in %eax, or %edx:%eax, as an argument. A struct or union
+// is copied to the stack from its address.
+fn push_asm(ty : &Type) -> String
+{
+    if ty.is_record()
+    {
+        return format!("subl ${}, %esp\n{}", stack_size_of(ty), copy_asm(ty.size(), &Location::Address("%esp")));
+    }
+    if ty.size() == 8
+    {
+        return String::from("push %edx\npush %eax\n");
+    }
+    return String::from("push %eax\n");
+}
+
+// Calls follow cdecl: the arguments are pushed from last to first, the
+// result comes back in %eax, or %edx:%eax for 64 bit types, and the caller
+// pops the arguments. A struct or union is returned to a temporary object
+// in the frame, whose address is pushed last. %ecx and %edx may be
+// clobbered, but nothing is kept in them across an expression.
+fn call_asm(name : &str, args : &[Expression], ty : &Type, context : &mut Context) -> String
+{
+    context.makes_calls = true;
+    let result = if ty.is_record() { Some(context.temporary_object(ty)) } else { None };
+    // %esp has to be 16 byte aligned at the call. The frame is sized so it is
+    // aligned between statements, which leaves the spilled operands and the
+    // arguments to pad for.
+    let args_size : i32 = args.iter().map(|arg| stack_size_of(&arg.ty())).sum();
+    let result_size = if result.is_some() { 4 } else { 0 };
+    let padding = (16 - (context.temporaries + args_size + result_size) % 16) % 16;
+    let mut asm = String::new();
+    if padding > 0
+    {
+        asm += &format!("subl ${}, %esp\n", padding);
+    }
+    let temporaries = context.temporaries;
+    context.temporaries += padding;
+    for arg in args.iter().rev()
+    {
+        asm += &format!("{}{}", expression_asm(arg, context), push_asm(&arg.ty()));
+        context.temporaries += stack_size_of(&arg.ty());
+    }
+    context.temporaries = temporaries;
+    // The function pops the address of the result itself.
+    if let Some(slot) = result
+    {
+        asm += &format!("leal {}(%ebp), %eax\npush %eax\n", slot);
+    }
+    asm += &format!("call {}\n", name);
+    if padding + args_size > 0
+    {
+        asm += &format!("addl ${}, %esp\n", padding + args_size);
+    }
+    return asm;
+}
+
+// The left operand is already in %eax. It is spilled to the stack while the
+// right operand is evaluated, then the right operand goes to %ecx and the
+// left one back to %eax. `unsigned` picks unsigned division, comparisons and
+// a logical rather than an arithmetic right shift.
+fn binary_asm(oper : BiOperator, rhs_asm : &str, unsigned : bool) -> String
+{
+    let asm = format!("push %eax\n{}movl %eax, %ecx\npop %eax\n", rhs_asm);
+    match oper
+    {
+        BiOperator::Addition => return format!("{}addl %ecx, %eax\n", asm),
+        BiOperator::Subtraction => return format!("{}subl %ecx, %eax\n", asm),
+        BiOperator::Multiplication => return format!("{}imull %ecx, %eax\n", asm),
+        BiOperator::Division if unsigned => return format!("{}xorl %edx, %edx\ndivl %ecx\n", asm),
+        BiOperator::Division => return format!("{}cltd\nidivl %ecx\n", asm),
+        BiOperator::Modulo if unsigned => return format!("{}xorl %edx, %edx\ndivl %ecx\nmovl %edx, %eax\n", asm),
+        BiOperator::Modulo => return format!("{}cltd\nidivl %ecx\nmovl %edx, %eax\n", asm),
+        BiOperator::BitwiseAnd => return format!("{}andl %ecx, %eax\n", asm),
+        BiOperator::BitwiseOr => return format!("{}orl %ecx, %eax\n", asm),
+        BiOperator::BitwiseXor => return format!("{}xorl %ecx, %eax\n", asm),
+        // The shift count has to be in %cl.
+        BiOperator::ShiftLeft => return format!("{}shll %cl, %eax\n", asm),
+        BiOperator::ShiftRight if unsigned => return format!("{}shrl %cl, %eax\n", asm),
+        BiOperator::ShiftRight => return format!("{}sarl %cl, %eax\n", asm),
+        BiOperator::LessThan => return comparison_asm(&asm, if unsigned { "setb" } else { "setl" }),
+        BiOperator::GreaterThan => return comparison_asm(&asm, if unsigned { "seta" } else { "setg" }),
+        BiOperator::LessThanOrEqual => return comparison_asm(&asm, if unsigned { "setbe" } else { "setle" }),
+        BiOperator::GreaterThanOrEqual => return comparison_asm(&asm, if unsigned { "setae" } else { "setge" }),
+        BiOperator::Equal => return comparison_asm(&asm, "sete"),
+        BiOperator::NotEqual => return comparison_asm(&asm, "setne"),
+        BiOperator::LogicalAnd | BiOperator::LogicalOr => unreachable!("logical operators short-circuit in binop_asm"),
+    };
+}
+
+fn comparison_asm(operands_asm : &str, set : &str) -> String
+{
+    return format!("{}cmpl %ecx, %eax\nmovl $0, %eax\n{} %al\n", operands_asm, set);
+}
+
+// Operations on 64 bit values in %edx:%eax. The right operand is evaluated
+// first, which C allows, and pushed: it then sits at (%esp) and 4(%esp)
+// while `lhs_asm` leaves the left one in %edx:%eax, already in the order of
+// the arguments of the libgcc helpers used for division.
+fn long_binop_asm(oper : BiOperator, ty : &Type, lhs_asm : &dyn Fn(&mut Context) -> String, rhs : &Expression,
+                    context : &mut Context) -> String
+{
+    let unsigned = ty.is_unsigned();
+    if let BiOperator::ShiftLeft | BiOperator::ShiftRight = oper
+    {
+        // The count is an `int`. The double shifts only use its low 5 bits,
+        // so counts of 32 or more move one half into the other.
+        let rhs_asm = spilled_asm(rhs, context);
+        context.temporaries += 4;
+        let lhs_asm = lhs_asm(context);
+        context.temporaries -= 4;
+        let done = context.new_label();
+        let shift_asm = match oper
+        {
+            BiOperator::ShiftLeft => format!("shldl %cl, %eax, %edx\nshll %cl, %eax\ntestb $32, %cl\nje {}\nmovl %eax, %edx\nxorl %eax, %eax\n", done),
+            _ if unsigned => format!("shrdl %cl, %edx, %eax\nshrl %cl, %edx\ntestb $32, %cl\nje {}\nmovl %edx, %eax\nxorl %edx, %edx\n", done),
+            _ => format!("shrdl %cl, %edx, %eax\nsarl %cl, %edx\ntestb $32, %cl\nje {}\nmovl %edx, %eax\nsarl $31, %edx\n", done),
+        };
+        return format!("{}push %eax\n{}pop %ecx\n{}{}:\n", rhs_asm, lhs_asm, shift_asm, done);
+    }
+
+    let helper = match oper
+    {
+        BiOperator::Division => Some(if unsigned { "__udivdi3" } else { "__divdi3" }),
+        BiOperator::Modulo => Some(if unsigned { "__umoddi3" } else { "__moddi3" }),
+        _ => None,
+    };
+    // Calling a helper pushes the left operand as well, and the stack has to
+    // be aligned then.
+    let padding = if helper.is_some() { (16 - context.temporaries % 16) % 16 } else { 0 };
+    context.temporaries += padding;
+    let rhs_asm = expression_asm(rhs, context);
+    context.temporaries += 8;
+    let lhs_asm = lhs_asm(context);
+    context.temporaries -= 8 + padding;
+    let mut asm = String::new();
+    if padding > 0
+    {
+        asm += &format!("subl ${}, %esp\n", padding);
+    }
+    asm += &format!("{}push %edx\npush %eax\n{}", rhs_asm, lhs_asm);
+    let operation_asm = match oper
+    {
+        BiOperator::Addition => String::from("addl (%esp), %eax\nadcl 4(%esp), %edx\n"),
+        BiOperator::Subtraction => String::from("subl (%esp), %eax\nsbbl 4(%esp), %edx\n"),
+        // The low halves multiply to a full 64 bit product; the cross
+        // products only add to its high half.
+        BiOperator::Multiplication => String::from("movl %edx, %ecx\nimull (%esp), %ecx\nmovl 4(%esp), %edx\nimull %eax, %edx\n\
+                                                    addl %edx, %ecx\nmull (%esp)\naddl %ecx, %edx\n"),
+        BiOperator::Division | BiOperator::Modulo => {
+            context.makes_calls = true;
+            format!("push %edx\npush %eax\ncall {}\naddl $8, %esp\n", helper.unwrap())
+        },
+        BiOperator::BitwiseAnd => String::from("andl (%esp), %eax\nandl 4(%esp), %edx\n"),
+        BiOperator::BitwiseOr => String::from("orl (%esp), %eax\norl 4(%esp), %edx\n"),
+        BiOperator::BitwiseXor => String::from("xorl (%esp), %eax\nxorl 4(%esp), %edx\n"),
+        BiOperator::Equal => String::from("xorl (%esp), %eax\nxorl 4(%esp), %edx\norl %edx, %eax\nmovl $0, %eax\nsete %al\n"),
+        BiOperator::NotEqual => String::from("xorl (%esp), %eax\nxorl 4(%esp), %edx\norl %edx, %eax\nmovl $0, %eax\nsetne %al\n"),
+        BiOperator::LessThan => long_comparison_asm(if unsigned { "setb" } else { "setl" }, "setb", context),
+        BiOperator::GreaterThan => long_comparison_asm(if unsigned { "seta" } else { "setg" }, "seta", context),
+        BiOperator::LessThanOrEqual => long_comparison_asm(if unsigned { "setbe" } else { "setle" }, "setbe", context),
+        BiOperator::GreaterThanOrEqual => long_comparison_asm(if unsigned { "setae" } else { "setge" }, "setae", context),
+        BiOperator::ShiftLeft | BiOperator::ShiftRight | BiOperator::LogicalAnd | BiOperator::LogicalOr => unreachable!(),
+    };
+    return format!("{}{}addl ${}, %esp\n", asm, operation_asm, 8 + padding);
+}
+
+// Compares the high halves with the signedness of the type, and only when
+// they are equal the low halves, always as unsigned numbers.
+fn long_comparison_asm(high_set : &str, low_set : &str, context : &mut Context) -> String
+{
+    let high = context.new_label();
+    let end = context.new_label();
+    return format!("cmpl 4(%esp), %edx\njne {}\ncmpl (%esp), %eax\nmovl $0, %eax\n{} %al\njmp {}\n{}:\nmovl $0, %eax\n{} %al\n{}:\n",
+                    high, low_set, end, high, high_set, end);
+}
+
+// The operand has the type `ty`, and the result its promoted type.
+fn unioperator_asm(oper : &UniOperator, ty : &Type) -> String
+{
+    let long = ty.size() == 8;
+    match oper
+    {
+        UniOperator::Negation if long => return String::from("negl %eax\nadcl $0, %edx\nnegl %edx\n"),
         UniOperator::Negation => return String::from("neg %eax\n"),
+        UniOperator::BitwiseComplement if long => return String::from("notl %eax\nnotl %edx\n"),
         UniOperator::BitwiseComplement => String::from("not %eax\n"),
-        UniOperator::LogicalNegation => return String::from("cmpl $0, %eax\nmovl $0, %eax\nsete %al\n"),
+        UniOperator::LogicalNegation => return format!("{}cmpl $0, %eax\nmovl $0, %eax\nsete %al\n", truth_asm(ty)),
+        UniOperator::Dereference | UniOperator::AddressOf => unreachable!("pointer operators are handled by factor_asm"),
     }
 }
 
-fn constant_asm(constant : &Constant) -> String
+fn constant_asm(value : i64, ty : &Type) -> String
 {
-    match constant
+    if ty.size() == 8
     {
-        Constant::Integer(string) => return format!("movl ${}, %eax",string),
-    };
+        return format!("movl ${}, %eax\nmovl ${}, %edx\n", value as u32, (value >> 32) as u32);
+    }
+    return format!("movl ${}, %eax\n", value as u32);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+    use crate::lexxer::lex_str;
+    use crate::literal::parse_integer;
+    use crate::source_map::{SourceMap, Span};
+
+    fn int_expression(value : &str) -> Expression
+    {
+        return Expression::Factor(Factor::Constant(Constant::Integer(parse_integer(value).unwrap()), Span::default()));
+    }
+
+    fn binop(oper : BiOperator, lhs : Expression, rhs : Expression) -> Expression
+    {
+        return Expression::operation(oper, lhs, rhs, Span::default());
+    }
+
+    fn compile(source : &str) -> String
+    {
+        let mut diagnostics = Diagnostics::new();
+        let mut tokens = lex_str("test.c", source, &mut SourceMap::new(), &mut diagnostics);
+        let program = Program::new(&mut tokens, &mut diagnostics).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        return generate_asm(&program);
+    }
+
+    #[test]
+    fn division_asm()
+    {
+        let expression = binop(BiOperator::Division, int_expression("6"), int_expression("3"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()),
+                    "movl $6, %eax\npush %eax\nmovl $3, %eax\nmovl %eax, %ecx\npop %eax\ncltd\nidivl %ecx\n");
+    }
+
+    #[test]
+    fn subtraction_is_left_associative()
+    {
+        let expression = binop(BiOperator::Subtraction,
+                                binop(BiOperator::Subtraction, int_expression("1"), int_expression("2")),
+                                int_expression("3"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()),
+                    "movl $1, %eax\n\
+                    push %eax\nmovl $2, %eax\nmovl %eax, %ecx\npop %eax\nsubl %ecx, %eax\n\
+                    push %eax\nmovl $3, %eax\nmovl %eax, %ecx\npop %eax\nsubl %ecx, %eax\n");
+    }
+
+    #[test]
+    fn modulo_and_shifts_asm()
+    {
+        let operands = "movl $7, %eax\npush %eax\nmovl $2, %eax\nmovl %eax, %ecx\npop %eax\n";
+        let expression = binop(BiOperator::Modulo, int_expression("7"), int_expression("2"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()), format!("{}cltd\nidivl %ecx\nmovl %edx, %eax\n", operands));
+        let expression = binop(BiOperator::ShiftRight, int_expression("7"), int_expression("2"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()), format!("{}sarl %cl, %eax\n", operands));
+        let expression = binop(BiOperator::ShiftRight, int_expression("7u"), int_expression("2"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()), format!("{}shrl %cl, %eax\n", operands));
+    }
+
+    #[test]
+    fn compound_assignment_reads_and_writes_slot()
+    {
+        let mut context = Context::default();
+        let variable = Variable{name: String::from("a"), id: VarId(0), ty: Type::Int};
+        context.allocate(variable.id, &variable.ty);
+        let expression = Expression::CompoundAssign(BiOperator::Subtraction, Box::new(Factor::Variable(variable.clone(), Span::default())), Box::new(int_expression("2")), Span::default());
+        assert_eq!(expression_asm(&expression, &mut context),
+                    "movl -4(%ebp), %eax\npush %eax\nmovl $2, %eax\nmovl %eax, %ecx\npop %eax\nsubl %ecx, %eax\nmovl %eax, -4(%ebp)\n");
+        let expression = Expression::Factor(Factor::IncDec(IncDecOperator::PostIncrement, Box::new(Factor::Variable(variable, Span::default())), Span::default()));
+        assert_eq!(expression_asm(&expression, &mut context), "movl -4(%ebp), %eax\nincl -4(%ebp)\n");
+    }
+
+    #[test]
+    fn globals_go_in_data_bss_and_common()
+    {
+        let asm = compile("int a = 3; static int b; int b2 = 0; extern int c; static int d = 0; int e; \
+                          static int f() { static int s = 2; return a + s; }");
+        assert!(asm.starts_with("f:\npush %ebp\nmovl %esp, %ebp\nmovl a, %eax\npush %eax\nmovl s.6, %eax\n"), "{}", asm);
+        assert!(asm.ends_with(".globl a\n.data\n.align 4\na:\n.long 3\n\
+                                .local b\n.comm b,4,4\n\
+                                .globl b2\n.bss\n.align 4\nb2:\n.zero 4\n\
+                                .local d\n.bss\n.align 4\nd:\n.zero 4\n\
+                                .globl e\n.comm e,4,4\n\
+                                .local s.6\n.data\n.align 4\ns.6:\n.long 2\n"), "{}", asm);
+    }
+
+    #[test]
+    fn arrays_are_laid_out_with_their_initializers()
+    {
+        let asm = compile("char *s = \"ab\" + 1; short a[4] = {1, -2}; int z[3] = {0}; int *p = &z[2]; \
+                          int main() { char b[6] = {1}; int c[8] = {0}; return b[0]; }");
+        assert!(asm.starts_with(".globl main\nmain:\npush %ebp\nmovl %esp, %ebp\nsubl $40, %esp\n\
+                                movl $0, -6(%ebp)\nmovb $0, -2(%ebp)\nmovb $0, -1(%ebp)\nmovl $1, %eax\nmovb %al, -6(%ebp)\n\
+                                push %edi\nleal -40(%ebp), %edi\nmovl $8, %ecx\nxorl %eax, %eax\nrep stosl\npop %edi\n\
+                                movl $0, %eax\nmovl %eax, -40(%ebp)\n\
+                                leal -6(%ebp), %eax\n"), "{}", asm);
+        assert!(asm.ends_with(".globl s\n.data\n.align 4\ns:\n.long .LC0+1\n\
+                                .globl a\n.data\n.align 2\na:\n.value 1\n.value -2\n.zero 4\n\
+                                .globl z\n.bss\n.align 4\nz:\n.zero 12\n\
+                                .globl p\n.data\n.align 4\np:\n.long z+8\n\
+                                .section .rodata\n.LC0:\n.string \"ab\"\n"), "{}", asm);
+    }
+
+    #[test]
+    fn structs_are_copied_and_returned_through_memory()
+    {
+        let asm = compile("struct s { int a; short b; }; struct s get(struct s v) { return v; } \
+                          int main() { struct s x; struct s y; y = get(x); return y.b; }");
+        // The address to return to comes before the parameters, and is popped
+        // by the function.
+        assert!(asm.starts_with(".globl get\nget:\npush %ebp\nmovl %esp, %ebp\nleal 12(%ebp), %eax\n\
+                                movl 8(%ebp), %ecx\nmovl (%eax), %edx\nmovl %edx, (%ecx)\nmovl 4(%eax), %edx\nmovl %edx, 4(%ecx)\n\
+                                movl 8(%ebp), %eax\nmovl %ebp, %esp\npop %ebp\nret $4\n"), "{}", asm);
+        assert!(asm.contains("subl $24, %esp\nsubl $4, %esp\nleal -8(%ebp), %eax\nsubl $8, %esp\n\
+                              movl (%eax), %edx\nmovl %edx, (%esp)\nmovl 4(%eax), %edx\nmovl %edx, 4(%esp)\n\
+                              leal -24(%ebp), %eax\npush %eax\ncall get\naddl $12, %esp\n\
+                              movl (%eax), %edx\nmovl %edx, -16(%ebp)\nmovl 4(%eax), %edx\nmovl %edx, -12(%ebp)\n\
+                              movswl -12(%ebp), %eax\n"), "{}", asm);
+    }
+
+    #[test]
+    fn big_structs_are_copied_with_rep_movsl()
+    {
+        let asm = compile("struct big { int v[8]; char c; }; struct big g; \
+                          int main() { struct big *p = &g; *p = g; return p->c; }");
+        assert!(asm.contains("movl -4(%ebp), %eax\nmovl %eax, -8(%ebp)\nleal g, %eax\nmovl -8(%ebp), %ecx\n\
+                              leal (%ecx), %edx\npush %esi\npush %edi\nmovl %edx, %edi\nmovl %eax, %esi\nmovl $9, %ecx\nrep movsl\n\
+                              pop %edi\npop %esi\nmovl -4(%ebp), %eax\naddl $32, %eax\nmovsbl (%eax), %eax\n"), "{}", asm);
+        assert!(asm.contains(".comm g,36,4\n"), "{}", asm);
+    }
+
+    #[test]
+    fn long_operator_chains_compile()
+    {
+        // Every operator knows its type, so a chain is not walked again for
+        // each link of it.
+        let terms = vec!("x"; 50).join(" + ");
+        let source = format!("int main() {{ int x = {}; int *p = &x; return {} + *(p{}); }}", vec!("1"; 50).join(" + "), terms, " + 0".repeat(50));
+        let asm = compile(&source);
+        assert_eq!(asm.matches("movl $1, %eax\n").count(), 50, "{}", asm);
+    }
+
+    #[test]
+    fn narrow_and_wide_values_use_sized_moves()
+    {
+        assert_eq!(compile("int main() { unsigned char c = 200; short s = c; long long l = s; return l + c; }"),
+                    ".globl main\nmain:\npush %ebp\nmovl %esp, %ebp\nsubl $16, %esp\n\
+                    movl $200, %eax\nmovb %al, -1(%ebp)\n\
+                    movzbl -1(%ebp), %eax\nmovw %ax, -4(%ebp)\n\
+                    movswl -4(%ebp), %eax\ncltd\nmovl %eax, -16(%ebp)\nmovl %edx, -12(%ebp)\n\
+                    movzbl -1(%ebp), %eax\nxorl %edx, %edx\npush %edx\npush %eax\n\
+                    movl -12(%ebp), %edx\nmovl -16(%ebp), %eax\naddl (%esp), %eax\nadcl 4(%esp), %edx\naddl $8, %esp\n\
+                    movl %ebp, %esp\npop %ebp\nret\nmovl $0, %eax\nmovl %ebp, %esp\npop %ebp\nret\n");
+    }
+
+    #[test]
+    fn pointers_are_scaled_and_dereferenced()
+    {
+        assert_eq!(compile("int main() { int a = 1; int *p = &a; *p = 2; return *(p + 1) - *p; }"),
+                    ".globl main\nmain:\npush %ebp\nmovl %esp, %ebp\nsubl $12, %esp\n\
+                    movl $1, %eax\nmovl %eax, -4(%ebp)\n\
+                    leal -4(%ebp), %eax\nmovl %eax, -8(%ebp)\n\
+                    movl -8(%ebp), %eax\nmovl %eax, -12(%ebp)\nmovl $2, %eax\nmovl -12(%ebp), %ecx\nmovl %eax, (%ecx)\n\
+                    movl -8(%ebp), %eax\npush %eax\nmovl $1, %eax\nimull $4, %eax\nmovl %eax, %ecx\npop %eax\naddl %ecx, %eax\n\
+                    movl (%eax), %eax\npush %eax\nmovl -8(%ebp), %eax\nmovl (%eax), %eax\nmovl %eax, %ecx\npop %eax\nsubl %ecx, %eax\n\
+                    movl %ebp, %esp\npop %ebp\nret\nmovl $0, %eax\nmovl %ebp, %esp\npop %ebp\nret\n");
+    }
+
+    #[test]
+    fn calls_keep_stack_aligned()
+    {
+        assert_eq!(compile("int f(int a, int b) { return b; } int main() { return 1 + f(2, 3); }"),
+                    ".globl f\nf:\npush %ebp\nmovl %esp, %ebp\nmovl 12(%ebp), %eax\nmovl %ebp, %esp\npop %ebp\nret\n\
+                    movl %ebp, %esp\npop %ebp\nret\n\
+                    .globl main\nmain:\npush %ebp\nmovl %esp, %ebp\nsubl $8, %esp\n\
+                    movl $1, %eax\npush %eax\n\
+                    subl $4, %esp\nmovl $3, %eax\npush %eax\nmovl $2, %eax\npush %eax\ncall f\naddl $12, %esp\n\
+                    movl %eax, %ecx\npop %eax\naddl %ecx, %eax\n\
+                    movl %ebp, %esp\npop %ebp\nret\nmovl $0, %eax\nmovl %ebp, %esp\npop %ebp\nret\n");
+    }
+
+    #[test]
+    fn shift_counts_are_counted_when_aligning_calls()
+    {
+        // The count of a 64 bit shift is pushed before the left operand calls `f`.
+        let asm = compile("long long f(int a); int main() { int n = 2; return f(1) << n; }");
+        assert!(asm.contains("movl -4(%ebp), %eax\npush %eax\nsubl $8, %esp\nmovl $1, %eax\npush %eax\ncall f\naddl $12, %esp\npop %ecx\n"),
+                "{}", asm);
+    }
+
+    #[test]
+    fn comparison_asm()
+    {
+        let expression = binop(BiOperator::LessThanOrEqual, int_expression("1"), int_expression("2"));
+        assert_eq!(expression_asm(&expression, &mut Context::default()),
+                    "movl $1, %eax\npush %eax\nmovl $2, %eax\nmovl %eax, %ecx\npop %eax\n\
+                    cmpl %ecx, %eax\nmovl $0, %eax\nsetle %al\n");
+        let expression = binop(BiOperator::LessThanOrEqual, int_expression("1u"), int_expression("2u"));
+        assert!(expression_asm(&expression, &mut Context::default()).ends_with("setbe %al\n"));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit()
+    {
+        let mut context = Context::default();
+        let and = binop(BiOperator::LogicalAnd, int_expression("0"), int_expression("2"));
+        let or = binop(BiOperator::LogicalOr, int_expression("1"), int_expression("0"));
+        assert_eq!(expression_asm(&and, &mut context),
+                    "movl $0, %eax\ncmpl $0, %eax\nje .L0\nmovl $2, %eax\ncmpl $0, %eax\n.L0:\nmovl $0, %eax\nsetne %al\n");
+        assert_eq!(expression_asm(&or, &mut context),
+                    "movl $1, %eax\ncmpl $0, %eax\njne .L1\nmovl $0, %eax\ncmpl $0, %eax\n.L1:\nmovl $0, %eax\nsetne %al\n");
+    }
+
+    #[test]
+    fn conditional_asm()
+    {
+        let expression = Expression::Conditional(Box::new(int_expression("1")), Box::new(int_expression("2")),
+                                                Box::new(int_expression("3")), Span::default());
+        assert_eq!(expression_asm(&expression, &mut Context::default()),
+                    "movl $1, %eax\ncmpl $0, %eax\nje .L1\nmovl $2, %eax\njmp .L0\n.L1:\nmovl $3, %eax\n.L0:\n");
+    }
+
+    #[test]
+    fn break_and_continue_target_innermost_loop()
+    {
+        let inner = Statement::While(int_expression("2"), Box::new(Statement::Break(Span::default())), Span::default());
+        let body = Statement::Compound(vec!(BlockItem::Statement(inner), BlockItem::Statement(Statement::Continue(Span::default()))),
+                                        Span::default());
+        let outer = Statement::DoWhile(Box::new(body), int_expression("1"), Span::default());
+        assert_eq!(statement_asm(&outer, &mut Context::default()),
+                    ".L0:\n\
+                    .L3:\nmovl $2, %eax\ncmpl $0, %eax\nje .L4\njmp .L4\njmp .L3\n.L4:\n\
+                    jmp .L1\n\
+                    .L1:\nmovl $1, %eax\ncmpl $0, %eax\njne .L0\n.L2:\n");
+    }
+
+    fn switch_of(values : &[i64]) -> Statement
+    {
+        let cases = SwitchCases{ty: Type::Int, values: values.iter().map(|value| (*value, Span::default())).collect(), default: None};
+        let body = values.iter().map(|value| BlockItem::Statement(Statement::Case(*value, Box::new(Statement::Null(Span::default())), Span::default())))
+                        .collect();
+        return Statement::Switch(int_expression("0"), Box::new(Statement::Compound(body, Span::default())), cases, Span::default());
+    }
+
+    #[test]
+    fn dense_switch_uses_jump_table()
+    {
+        let mut context = Context::default();
+        let asm = statement_asm(&switch_of(&[3, 1, 5, 2]), &mut context);
+        assert!(asm.starts_with("movl $0, %eax\nsubl $1, %eax\ncmpl $4, %eax\nja .L0\njmp *.LT0(,%eax,4)\n"), "{}", asm);
+        assert_eq!(context.jump_tables, vec!(vec!(".L2", ".L4", ".L1", ".L0", ".L3")));
+    }
+
+    #[test]
+    fn sparse_switch_uses_binary_search()
+    {
+        let mut context = Context::default();
+        let asm = statement_asm(&switch_of(&[1000, -7, 30, 2]), &mut context);
+        assert!(context.jump_tables.is_empty());
+        assert!(asm.starts_with("movl $0, %eax\n\
+                                cmpl $30, %eax\nje .L3\njl .L5\n\
+                                cmpl $1000, %eax\nje .L1\njmp .L0\n\
+                                .L5:\n\
+                                cmpl $-7, %eax\nje .L2\ncmpl $2, %eax\nje .L4\njmp .L0\n"), "{}", asm);
+    }
+
+    #[test]
+    fn string_literals_go_to_rodata()
+    {
+        let mut context = Context::default();
+        let first = Factor::StringLiteral(b"hi \"you\"\n".to_vec(), Span::default());
+        let second = Factor::StringLiteral(b"\\\x01".to_vec(), Span::default());
+        assert_eq!(factor_asm(&first, &mut context), "movl $.LC0, %eax\n");
+        assert_eq!(factor_asm(&second, &mut context), "movl $.LC1, %eax\n");
+        assert_eq!(rodata_asm(&context),
+                    ".section .rodata\n.LC0:\n.string \"hi \\\"you\\\"\\012\"\n.LC1:\n.string \"\\\\\\001\"\n");
+    }
+
+    #[test]
+    fn sibling_blocks_share_slots()
+    {
+        assert_eq!(compile("int main() { int a; { int b; } { int c; int d; } }"),
+                    ".globl main\nmain:\npush %ebp\nmovl %esp, %ebp\nsubl $12, %esp\n\
+                    movl $0, %eax\nmovl %ebp, %esp\npop %ebp\nret\n");
+    }
 }
@@ -1,87 +1,332 @@
+use crate::diagnostics::*;
 use crate::lexxer::*;
-use std::{collections::VecDeque, f32::consts::E};
+use crate::literal::*;
+use crate::source_map::Span;
+use crate::symbol_table::*;
+use crate::types::{RecordKind, RecordType, Type, MAX_OBJECT_SIZE};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 
 #[derive(Debug)]
 pub enum Constant
 {
-    Integer(String),
+    Integer(IntegerLiteral),
+    Character(i32),
+    /// A reference to an enumeration constant, with its value.
+    Enumerator(String, i32),
 }
 
-#[derive(Debug)]
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
 pub enum UniOperator
 {
     Negation,
     BitwiseComplement,
     LogicalNegation,
+    /// `*pointer`
+    Dereference,
+    /// `&lvalue`
+    AddressOf,
 }
 
-#[derive(Debug)]
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum IncDecOperator
+{
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
 pub enum BiOperator
 {
     Addition,
     Subtraction,
     Multiplication,
     Division,
+    Modulo,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    Equal,
+    NotEqual,
+    LogicalAnd,
+    LogicalOr,
 }
 
 
 #[derive(Debug)]
 pub enum Expression
 {
-    Term(Box<Term>, Vec<(BiOperator, Term)>),
+    /// `target = value`, where the target is an lvalue.
+    Assign(Box<Factor>, Box<Expression>, Span),
+    /// `target oper= value`
+    CompoundAssign(BiOperator, Box<Factor>, Box<Expression>, Span),
+    /// `lhs, rhs`
+    Comma(Box<Expression>, Box<Expression>, Span),
+    /// `lhs oper rhs`, with the type of the result, worked out once when the
+    /// operands have been converted.
+    BinOp(BiOperator, Box<Expression>, Box<Expression>, Type, Span),
+    /// `condition ? then : otherwise`
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>, Span),
+    /// An implicit conversion of the value to another type, such as of the
+    /// operands of an arithmetic operator to their common type.
+    Convert(Type, Box<Expression>),
+    Factor(Factor),
+}
+
+#[derive(Debug)]
+pub enum Factor
+{
+    Constant(Constant, Span),
+    StringLiteral(Vec<u8>, Span),
+    Variable(Variable, Span),
+    UnOp(UniOperator, Box<Factor>, Span),
+    /// `++target`, `target--` and so on, where the target is an lvalue.
+    IncDec(IncDecOperator, Box<Factor>, Span),
+    /// `name(arguments)`, with the type the function returns.
+    Call(String, Vec<Expression>, Type, Span),
+    /// `(type) operand`
+    Cast(Type, Box<Factor>, Span),
+    /// `sizeof operand` or `sizeof(type)`, with the type whose size it is.
+    SizeOf(Type, Span),
+    /// `object.member`, with the offset of the member in bytes and its type.
+    /// `pointer->member` is `(*pointer).member`.
+    Member(Box<Factor>, String, usize, Type, Span),
+    Expr(Box<Expression>, Span)
+}
+
+/// A reference to a declared variable.
+#[derive(Debug,Clone)]
+pub struct Variable
+{
+    pub name : String,
+    pub id : VarId,
+    pub ty : Type,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum StorageClass
+{
+    Static,
+    Extern,
+    /// `typedef`, which is a storage class only in syntax: it declares a name
+    /// for a type rather than a variable.
+    Typedef,
 }
 
+/// The specifiers in front of a declarator, such as `static unsigned int`.
+#[derive(Debug,Clone)]
+pub struct DeclSpecifiers
+{
+    pub storage : Option<StorageClass>,
+    pub ty : Type,
+    pub span : Span,
+}
 
 #[derive(Debug)]
-pub enum Term
+pub struct Declaration
 {
-    Factor(Factor, Vec<(BiOperator, Factor)>)
+    pub name : String,
+    pub id : VarId,
+    pub ty : Type,
+    pub storage : Option<StorageClass>,
+    pub init : Option<Initializer>,
+    /// The whole declaration, which may declare other variables as well.
+    pub span : Span,
 }
 
+/// The initial value of a declared variable.
 #[derive(Debug)]
-pub enum Factor
+pub enum Initializer
 {
-    Constant(Constant),
-    UnOp(UniOperator, Box<Factor>),
-    Expr(Expression)
+    /// `= value`, converted to the type of the variable.
+    Single(Expression),
+    /// `= { ... }`, or a string for an array of characters: the value of
+    /// every scalar it gives, with its offset in bytes into the variable, in
+    /// order. The rest of the variable is zero.
+    List(Vec<(usize, Expression)>),
 }
 
 #[derive(Debug)]
 pub enum Statement
 {
-    Return(Expression),
+    /// `return value;`, where only functions returning `void` leave out the
+    /// value.
+    Return(Option<Expression>, Span),
+    Expression(Expression, Span),
+    Compound(Vec<BlockItem>, Span),
+    If(Expression, Box<Statement>, Option<Box<Statement>>, Span),
+    While(Expression, Box<Statement>, Span),
+    DoWhile(Box<Statement>, Expression, Span),
+    /// `for (init; condition; step) body`, where every clause may be left out.
+    For(Option<ForInit>, Option<Expression>, Option<Expression>, Box<Statement>, Span),
+    Break(Span),
+    Continue(Span),
+    /// A lone `;`.
+    Null(Span),
+    Switch(Expression, Box<Statement>, SwitchCases, Span),
+    /// `case value: statement`, labelling a statement of the innermost switch.
+    Case(i64, Box<Statement>, Span),
+    Default(Box<Statement>, Span),
+    /// `name: statement`, a target for `goto`.
+    Labeled(String, Box<Statement>, Span),
+    Goto(String, Span),
+}
+
+/// The labels found in the body of one `switch`.
+#[derive(Debug)]
+pub struct SwitchCases
+{
+    /// Promoted type of the controlling expression, which every case value
+    /// is converted to.
+    pub ty : Type,
+    /// Every case value with the span of its label, in source order.
+    pub values : Vec<(i64, Span)>,
+    pub default : Option<Span>,
+}
+
+/// First clause of a `for` statement. A declared variable is only in scope
+/// within the loop.
+#[derive(Debug)]
+pub enum ForInit
+{
+    Declaration(Vec<Declaration>),
+    Expression(Expression),
+}
+
+/// One entry of a `{ }` block. Declarations are not statements in C, they may
+/// only appear directly inside a block.
+#[derive(Debug)]
+pub enum BlockItem
+{
+    Statement(Statement),
+    Declaration(Declaration),
+    /// A declaration of types only, such as `struct point { int x, y; };`,
+    /// which generates nothing.
+    TypeDeclaration(Span),
+}
+
+#[derive(Debug)]
+pub struct Parameter
+{
+    /// Parameters of a prototype may be left unnamed.
+    pub name : Option<String>,
+    pub id : Option<VarId>,
+    pub ty : Type,
+    pub span : Span,
 }
 
+/// A function definition, or a prototype when it has no body.
 #[derive(Debug)]
 pub struct FunctionDecl
 {
     pub name : String,
-    pub body : Statement,
+    pub return_type : Type,
+    pub params : Vec<Parameter>,
+    pub storage : Option<StorageClass>,
+    pub body : Option<Vec<BlockItem>>,
+    pub span : Span,
+}
+
+#[derive(Debug)]
+pub enum ExternalDeclaration
+{
+    Function(FunctionDecl),
+    Variable(Declaration),
+    /// See `BlockItem::TypeDeclaration`.
+    TypeDeclaration(Span),
 }
 
 #[derive(Debug)]
-pub enum Program
+pub struct Program
+{
+    pub declarations : Vec<ExternalDeclaration>,
+}
+
+/// State the parser carries from one node to the next.
+pub struct ParseContext<'a>
 {
-    Declaration(FunctionDecl),
+    pub diagnostics : &'a mut Diagnostics,
+    pub symbols : SymbolTable,
+    /// Number of loops around the statement being parsed.
+    pub loop_depth : usize,
+    /// Labels collected so far for each switch around the statement being
+    /// parsed, innermost last.
+    pub switches : Vec<SwitchCases>,
+    /// Labels defined so far in the current function. Labels have function
+    /// scope, so they are checked once the whole body has been parsed.
+    pub labels : HashMap<String, Span>,
+    /// Every `goto` of the current function with the label it jumps to.
+    pub gotos : Vec<(String, Span)>,
+    /// Every function declared so far.
+    pub functions : HashMap<String, FunctionSymbol>,
+    /// Every variable declared at file scope or with `extern` so far.
+    pub globals : HashMap<String, GlobalSymbol>,
+    /// Every variable with static storage declared so far, whose address is
+    /// a constant.
+    pub statics : HashSet<VarId>,
+    /// What the function being parsed returns.
+    pub return_type : Type,
+    /// Bytes of the automatic variables declared so far in the function
+    /// being parsed, which all go in its frame.
+    pub locals_size : usize,
+}
+
+impl<'a> ParseContext<'a>
+{
+    pub fn new(diagnostics : &'a mut Diagnostics) -> ParseContext<'a>
+    {
+        return ParseContext{diagnostics, symbols: SymbolTable::new(), loop_depth: 0, switches: Vec::new(),
+                            labels: HashMap::new(), gotos: Vec::new(), functions: HashMap::new(),
+                            globals: HashMap::new(), statics: HashSet::new(), return_type: Type::Int, locals_size: 0};
+    }
 }
 
 impl Constant 
 {
-    pub fn new(token : &LexToken) -> Option<Constant>
+    pub fn new(token : &Token, diagnostics : &mut Diagnostics) -> Option<Constant>
     {
-        match token
+        match &token.kind
         {
             LexToken::IntLiteral(int_str) => {
-                if let Ok(_) = int_str.parse::<i32>()
+                match parse_integer(int_str)
                 {
-                    return Some(Constant::Integer(int_str.to_owned()));
-                }
-                else
+                    Ok(literal) => {
+                        if literal.radix == 10 && literal.ty.is_unsigned() && !int_str.contains(['u', 'U'])
+                        {
+                            diagnostics.emit(Diagnostic::warning(W_LARGE_DECIMAL_CONSTANT,
+                                                &format!("integer constant `{}` is so large that it is unsigned", int_str))
+                                                .with_primary(token.span, "treated as `unsigned long long`"));
+                        }
+                        return Some(Constant::Integer(literal));
+                    },
+                    Err(err) => {
+                        diagnostics.emit(Diagnostic::error(E_INVALID_CONSTANT, &err.message(int_str))
+                                            .with_primary(token.span, "invalid integer constant"));
+                        return None;
+                    },
+                };
+            },
+            LexToken::CharLiteral(spelling) => {
+                match parse_char(spelling)
                 {
-                    println!("Error parsing the Constant");
-                    return None;
-                }
+                    Ok((value, warnings)) => {
+                        report_literal_warnings(&warnings, token.span, diagnostics);
+                        return Some(Constant::Character(value));
+                    },
+                    Err(err) => {
+                        diagnostics.emit(Diagnostic::error(E_INVALID_LITERAL, &err.message(spelling))
+                                            .with_primary(token.span, "invalid character constant"));
+                        return None;
+                    },
+                };
             },
             _ => return None,
         };
@@ -97,6 +342,8 @@ impl UniOperator
             LexToken::Negation => return Some(UniOperator::Negation),
             LexToken::BitwiseComplement => return Some(UniOperator::BitwiseComplement),
             LexToken::LogicalNegation => return Some(UniOperator::LogicalNegation),
+            LexToken::Multiplication => return Some(UniOperator::Dereference),
+            LexToken::BitwiseAnd => return Some(UniOperator::AddressOf),
             _ => return None,
         };
     }
@@ -112,263 +359,3040 @@ impl BiOperator
             LexToken::Negation => return Some(BiOperator::Subtraction),
             LexToken::Multiplication => return Some(BiOperator::Multiplication),
             LexToken::Division => return Some(BiOperator::Division),
+            LexToken::Modulo => return Some(BiOperator::Modulo),
+            LexToken::BitwiseAnd => return Some(BiOperator::BitwiseAnd),
+            LexToken::BitwiseOr => return Some(BiOperator::BitwiseOr),
+            LexToken::BitwiseXor => return Some(BiOperator::BitwiseXor),
+            LexToken::ShiftLeft => return Some(BiOperator::ShiftLeft),
+            LexToken::ShiftRight => return Some(BiOperator::ShiftRight),
+            LexToken::LessThan => return Some(BiOperator::LessThan),
+            LexToken::GreaterThan => return Some(BiOperator::GreaterThan),
+            LexToken::LessThanOrEqual => return Some(BiOperator::LessThanOrEqual),
+            LexToken::GreaterThanOrEqual => return Some(BiOperator::GreaterThanOrEqual),
+            LexToken::Equal => return Some(BiOperator::Equal),
+            LexToken::NotEqual => return Some(BiOperator::NotEqual),
+            LexToken::LogicalAnd => return Some(BiOperator::LogicalAnd),
+            LexToken::LogicalOr => return Some(BiOperator::LogicalOr),
+            _ => return None,
+        };
+    }
+
+    /// The operator of a compound assignment token such as `+=`.
+    pub fn from_assignment(token: &LexToken) -> Option<BiOperator>
+    {
+        match token
+        {
+            LexToken::AdditionAssign => return Some(BiOperator::Addition),
+            LexToken::SubtractionAssign => return Some(BiOperator::Subtraction),
+            LexToken::MultiplicationAssign => return Some(BiOperator::Multiplication),
+            LexToken::DivisionAssign => return Some(BiOperator::Division),
+            LexToken::ModuloAssign => return Some(BiOperator::Modulo),
+            LexToken::ShiftLeftAssign => return Some(BiOperator::ShiftLeft),
+            LexToken::ShiftRightAssign => return Some(BiOperator::ShiftRight),
+            LexToken::BitwiseAndAssign => return Some(BiOperator::BitwiseAnd),
+            LexToken::BitwiseOrAssign => return Some(BiOperator::BitwiseOr),
+            LexToken::BitwiseXorAssign => return Some(BiOperator::BitwiseXor),
             _ => return None,
         };
     }
+
+    /// Computes `lhs oper rhs` on operands of type `ty` the way the
+    /// generated code would, or `None` if the operation is undefined.
+    pub fn fold(&self, lhs : i64, rhs : i64, ty : &Type) -> Option<i64>
+    {
+        let (ulhs, urhs) = (lhs as u64, rhs as u64);
+        let unsigned = ty.is_unsigned() || ty.is_pointer();
+        let bits = ty.size() as i64 * 8;
+        let value = match self
+        {
+            BiOperator::Addition => lhs.wrapping_add(rhs),
+            BiOperator::Subtraction => lhs.wrapping_sub(rhs),
+            BiOperator::Multiplication => lhs.wrapping_mul(rhs),
+            // The most negative value divided by -1 overflows.
+            BiOperator::Division | BiOperator::Modulo if !unsigned && rhs == -1 && ty.convert(lhs.wrapping_neg()) != lhs.wrapping_neg() => return None,
+            BiOperator::Division if unsigned => ulhs.checked_div(urhs)? as i64,
+            BiOperator::Division => lhs.checked_div(rhs)?,
+            BiOperator::Modulo if unsigned => ulhs.checked_rem(urhs)? as i64,
+            BiOperator::Modulo => lhs.checked_rem(rhs)?,
+            BiOperator::BitwiseAnd => lhs & rhs,
+            BiOperator::BitwiseOr => lhs | rhs,
+            BiOperator::BitwiseXor => lhs ^ rhs,
+            BiOperator::ShiftLeft | BiOperator::ShiftRight if !(0..bits).contains(&rhs) => return None,
+            BiOperator::ShiftLeft => (ulhs << rhs) as i64,
+            BiOperator::ShiftRight if unsigned => (ulhs >> rhs) as i64,
+            BiOperator::ShiftRight => lhs >> rhs,
+            BiOperator::LessThan if unsigned => return Some((ulhs < urhs) as i64),
+            BiOperator::LessThan => return Some((lhs < rhs) as i64),
+            BiOperator::GreaterThan if unsigned => return Some((ulhs > urhs) as i64),
+            BiOperator::GreaterThan => return Some((lhs > rhs) as i64),
+            BiOperator::LessThanOrEqual if unsigned => return Some((ulhs <= urhs) as i64),
+            BiOperator::LessThanOrEqual => return Some((lhs <= rhs) as i64),
+            BiOperator::GreaterThanOrEqual if unsigned => return Some((ulhs >= urhs) as i64),
+            BiOperator::GreaterThanOrEqual => return Some((lhs >= rhs) as i64),
+            BiOperator::Equal => return Some((lhs == rhs) as i64),
+            BiOperator::NotEqual => return Some((lhs != rhs) as i64),
+            BiOperator::LogicalAnd => return Some((lhs != 0 && rhs != 0) as i64),
+            BiOperator::LogicalOr => return Some((lhs != 0 || rhs != 0) as i64),
+        };
+        return Some(ty.convert(value));
+    }
+
+    /// The types the operands are converted to before the operation: their
+    /// common type, except that the operands of a shift are only promoted and
+    /// those of `&&` and `||` are only compared with zero. Pointers are left
+    /// as they are, see `Expression::pointer_binop`.
+    pub fn operand_types(&self, lhs : &Type, rhs : &Type) -> (Type, Type)
+    {
+        match self
+        {
+            _ if lhs.is_pointer() || rhs.is_pointer() => return (lhs.clone(), rhs.clone()),
+            BiOperator::ShiftLeft | BiOperator::ShiftRight => {
+                // Counts that do not fit in an `int` are out of range anyway,
+                // so a `long long` count is narrowed.
+                let count = if rhs.promote().size() > 4 { Type::Int } else { rhs.promote() };
+                return (lhs.promote(), count);
+            },
+            BiOperator::LogicalAnd | BiOperator::LogicalOr => return (lhs.clone(), rhs.clone()),
+            _ => {
+                let common = Type::common(lhs, rhs);
+                return (common.clone(), common);
+            },
+        };
+    }
+
+    /// Whether the operator compares its operands or combines truth values,
+    /// yielding 0 or 1 as an `int` rather than a value of the operands' type.
+    pub fn is_comparison(&self) -> bool
+    {
+        match self
+        {
+            BiOperator::LessThan | BiOperator::GreaterThan | BiOperator::LessThanOrEqual | BiOperator::GreaterThanOrEqual
+                | BiOperator::Equal | BiOperator::NotEqual | BiOperator::LogicalAnd | BiOperator::LogicalOr => return true,
+            _ => return false,
+        };
+    }
+
+    /// How tightly the operator binds; higher binds tighter. All binary
+    /// operators are left associative.
+    pub fn precedence(&self) -> u8
+    {
+        match self
+        {
+            BiOperator::LogicalOr => return 1,
+            BiOperator::LogicalAnd => return 2,
+            BiOperator::BitwiseOr => return 3,
+            BiOperator::BitwiseXor => return 4,
+            BiOperator::BitwiseAnd => return 5,
+            BiOperator::Equal | BiOperator::NotEqual => return 6,
+            BiOperator::LessThan | BiOperator::GreaterThan
+                | BiOperator::LessThanOrEqual | BiOperator::GreaterThanOrEqual => return 7,
+            BiOperator::ShiftLeft | BiOperator::ShiftRight => return 8,
+            BiOperator::Addition | BiOperator::Subtraction => return 9,
+            BiOperator::Multiplication | BiOperator::Division | BiOperator::Modulo => return 10,
+        };
+    }
 }
 
 
 impl Expression 
 {
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<Expression>
+    /// Parses a full expression, including the comma operator.
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Expression>
     {
-        if let Some(term) = Term::new(tokens)
+        let mut expression = Expression::assignment(tokens, context)?;
+        while tokens.front().map(|token| &token.kind) == Some(&LexToken::Comma)
         {
-            let mut next_terms = Vec::new();
-            
-            while let Some(token) = tokens.pop_front()
-            {
-                if token == LexToken::Addition ||
-                    token == LexToken::Negation
-                {
-                    if let Some(next_term) = Term::new(tokens)
-                    {
-                        next_terms.push((BiOperator::new(&token).unwrap(),next_term));
-                    }
-                    else
-                    {
-                        return None;
-                    }
-                }
-                else
-                {
-                    tokens.push_front(token);
-                    break;
-                }
-            }
-            return Some(Expression::Term(Box::new(term),next_terms));
+            tokens.pop_front();
+            let rhs = Expression::assignment(tokens, context)?;
+            let span = expression.span().to(rhs.span());
+            expression = Expression::Comma(Box::new(expression), Box::new(rhs), span);
         }
-        println!("Failed generating expression");
-        return None;
+        return Some(expression);
     }
-}
 
+    /// Parses an assignment expression, which is what an initializer may be.
+    /// Assignments are right associative.
+    pub fn assignment(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Expression>
+    {
+        // The target is parsed like any operand and checked afterwards, so
+        // `a + b = 1` is reported as a bad target rather than a syntax error.
+        let target = Expression::conditional(tokens, context)?;
+        let oper = match tokens.front().map(|token| &token.kind)
+        {
+            Some(LexToken::Assignment) => None,
+            Some(kind) if BiOperator::from_assignment(kind).is_some() => BiOperator::from_assignment(kind),
+            _ => return Some(target),
+        };
+        let token = tokens.pop_front().unwrap();
+        let value = Expression::assignment(tokens, context)?;
+        let span = target.span().to(value.span());
+        let target = lvalue(target, &token, context)?;
+        check_value(&target.ty(), target.span(), context);
+        check_value(&value.ty(), value.span(), context);
+        let Some(oper) = oper else {
+            let value = convert_for_assignment(value, &target.ty(), context);
+            return Some(Expression::Assign(Box::new(target), Box::new(value), span));
+        };
+        // The value is converted for the operation, whose result is then
+        // converted to the type of the target. Only `+=` and `-=` take a
+        // pointer, which then has to be the target.
+        let (target_type, value_type) = (target.ty(), value.ty());
+        let value = match (target_type.is_pointer(), value_type.is_pointer())
+        {
+            (false, false) if !target_type.is_record() && !value_type.is_record() => convert(value, &oper.operand_types(&target_type, &value_type).1),
+            (true, false) if matches!(oper, BiOperator::Addition | BiOperator::Subtraction) => convert(value, &Type::Int),
+            _ => {
+                context.diagnostics.emit(invalid_operands(&token, &target_type, &value_type, span));
+                value
+            },
+        };
+        return Some(Expression::CompoundAssign(oper, Box::new(target), Box::new(value), span));
+    }
 
-impl Term
-{
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<Term>
+    /// `?:` is right associative: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn conditional(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Expression>
     {
-        if let Some(factor) = Factor::new(tokens)
+        let condition = Expression::binary(tokens, context, 0)?;
+        if tokens.front().map(|token| &token.kind) != Some(&LexToken::QuestionMark)
         {
-            let mut next_factors = Vec::new();
-            
-            while let Some(token) = tokens.pop_front()
-            {
-                if token == LexToken::Multiplication ||
-                    token == LexToken::Division
-                {
-                    if let Some(next_factor) = Factor::new(tokens)
-                    {
-                        next_factors.push((BiOperator::new(&token).unwrap(),next_factor));
-                    }
-                    else
-                    {
-                        return None;
-                    }
-                }
-                else
-                {
-                    tokens.push_front(token);
-                    break;
-                }
-            }
-            return Some(Term::Factor(factor,next_factors));
+            return Some(condition);
         }
-        return None;
+        tokens.pop_front();
+        let then = Expression::new(tokens, context)?;
+        validate_rule(LexToken::Colon, tokens, context.diagnostics)?;
+        let otherwise = Expression::conditional(tokens, context)?;
+        let span = condition.span().to(otherwise.span());
+        check_value(&condition.ty(), condition.span(), context);
+        check_scalar(&condition.ty(), condition.span(), context);
+        // Both operands may be `void`, otherwise they are converted to their
+        // common type.
+        if then.ty() == Type::Void && otherwise.ty() == Type::Void
+        {
+            return Some(Expression::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise), span));
+        }
+        check_value(&then.ty(), then.span(), context);
+        check_value(&otherwise.ty(), otherwise.span(), context);
+        let (then_type, otherwise_type) = (then.ty(), otherwise.ty());
+        let ty = match (then_type.pointee(), otherwise_type.pointee())
+        {
+            (None, None) if !then_type.is_record() && !otherwise_type.is_record() => Type::common(&then_type, &otherwise_type),
+            (None, None) if then_type == otherwise_type => then_type,
+            (Some(_), Some(_)) if then_type == otherwise_type => then_type,
+            // Mixing in a `void *` loses what the other pointer points to.
+            (Some(Type::Void), Some(_)) | (Some(_), Some(Type::Void)) => Type::pointer_to(Type::Void),
+            (Some(_), None) if otherwise.is_null_pointer_constant() => then_type,
+            (None, Some(_)) if then.is_null_pointer_constant() => otherwise_type,
+            _ => {
+                context.diagnostics.emit(Diagnostic::error(E_INCOMPATIBLE_TYPES, "type mismatch in conditional expression")
+                                    .with_primary(then.span(), &format!("has type `{}`", then_type.name()))
+                                    .with_secondary(otherwise.span(), &format!("has type `{}`", otherwise_type.name())));
+                then_type
+            },
+        };
+        let (then, otherwise) = (convert(then, &ty), convert(otherwise, &ty));
+        return Some(Expression::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise), span));
     }
-}
 
-impl Factor
-{
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<Factor>
+    /// Precedence climbing: parses a factor followed by every binary operator
+    /// that binds at least as tightly as `min_precedence`, together with its
+    /// right operand.
+    fn binary(tokens : &mut VecDeque<Token>, context : &mut ParseContext, min_precedence : u8) -> Option<Expression>
     {
-        if let Some(token) = tokens.pop_front() 
+        let mut lhs = decay(Expression::Factor(Factor::new(tokens, context)?));
+        while let Some(oper) = tokens.front().and_then(|token| BiOperator::new(&token.kind))
         {
-            match token
+            if oper.precedence() < min_precedence
             {
-                LexToken::OpenParenth => {
-                    let maybe_expr = Expression::new(tokens);
-                    let maybe_close = tokens.pop_front();
-
-                    if maybe_expr.is_some() &&
-                        maybe_close == Some(LexToken::CloseParenth)
-                    {
-                        return Some(Factor::Expr(maybe_expr.unwrap()));
-                    }
-                    return None;
-                }
-                LexToken::IntLiteral(_) => { 
-                    if let Some(cons) = Constant::new(&token)
-                    {
-                        return Some(Factor::Constant(cons));
-                    }
-                    println!("Failed generating factor");
-                    return None;
-                },
-                _ => {
-                    if let Some(oper) = UniOperator::new(&token)
-                    {
-                        if let Some(factor) = Factor::new(tokens)
-                        {
-                            return Some(Factor::UnOp(oper,Box::new(factor)));
-                        }
-                    }
-                    println!("Failed generating factor");
-                    return None;
-                },
+                break;
             }
+            let token = tokens.pop_front().unwrap();
+            // Only tighter operators may take the right operand, which keeps
+            // operators of the same level left associative.
+            let rhs = Expression::binary(tokens, context, oper.precedence() + 1)?;
+            lhs = Expression::binop(oper, &token, lhs, rhs, context);
         }
-        return None;
+        return Some(lhs);
     }
-}
 
-impl Statement
-{
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<Statement>
+    /// Builds `lhs oper rhs`, converting the operands to the types the
+    /// operator works on.
+    fn binop(oper : BiOperator, token : &Token, lhs : Expression, rhs : Expression, context : &mut ParseContext) -> Expression
     {
-        if validate_rule(LexToken::Return, tokens)
+        check_value(&lhs.ty(), lhs.span(), context);
+        check_value(&rhs.ty(), rhs.span(), context);
+        let span = lhs.span().to(rhs.span());
+        let (lhs_type, rhs_type) = (lhs.ty(), rhs.ty());
+        if lhs_type.is_record() || rhs_type.is_record()
         {
-            if let Some(expression) = Expression::new(tokens)
-            {
-                if validate_rule(LexToken::Semicolon, tokens)
+            // Carry on as integers so the error is not reported again.
+            context.diagnostics.emit(invalid_operands(token, &lhs_type, &rhs_type, span));
+            return Expression::operation(oper, convert(lhs, &Type::Int), convert(rhs, &Type::Int), span);
+        }
+        if (lhs_type.is_pointer() || rhs_type.is_pointer()) && !matches!(oper, BiOperator::LogicalAnd | BiOperator::LogicalOr)
+        {
+            return Expression::pointer_binop(oper, token, lhs, rhs, context);
+        }
+        let (lhs_type, rhs_type) = oper.operand_types(&lhs_type, &rhs_type);
+        return Expression::operation(oper, convert(lhs, &lhs_type), convert(rhs, &rhs_type), span);
+    }
+
+    /// Builds `lhs oper rhs` where an operand is a pointer. An integer may be
+    /// added to or subtracted from a pointer, moving it by that many values
+    /// of the type it points to, and two pointers to the same type may be
+    /// subtracted, giving how many values apart they are as an `int`, which
+    /// is `ptrdiff_t` on i386. Pointers may be compared with each other or
+    /// with a null pointer constant.
+    fn pointer_binop(oper : BiOperator, token : &Token, lhs : Expression, rhs : Expression, context : &mut ParseContext) -> Expression
+    {
+        let span = lhs.span().to(rhs.span());
+        let (lhs_type, rhs_type) = (lhs.ty(), rhs.ty());
+        let (lhs, rhs) = match (oper, lhs_type.pointee(), rhs_type.pointee())
+        {
+            (BiOperator::Addition | BiOperator::Subtraction, Some(_), None) if rhs_type.is_integer() => (lhs, convert(rhs, &Type::Int)),
+            (BiOperator::Addition, None, Some(_)) if lhs_type.is_integer() => (convert(lhs, &Type::Int), rhs),
+            (BiOperator::Subtraction, Some(lhs_pointee), Some(rhs_pointee)) if lhs_pointee == rhs_pointee => (lhs, rhs),
+            (_, Some(lhs_pointee), Some(rhs_pointee)) if oper.is_comparison() => {
+                let equality = matches!(oper, BiOperator::Equal | BiOperator::NotEqual);
+                if lhs_pointee != rhs_pointee && !(equality && (*lhs_pointee == Type::Void || *rhs_pointee == Type::Void))
                 {
-                    return Some(Statement::Return(expression));
+                    context.diagnostics.emit(Diagnostic::error(E_INCOMPATIBLE_TYPES, "comparison of distinct pointer types")
+                                        .with_primary(lhs.span(), &format!("has type `{}`", lhs_type.name()))
+                                        .with_secondary(rhs.span(), &format!("has type `{}`", rhs_type.name())));
                 }
-            }
-        }
-        println!("Error parsing the Statement");
-        None
+                (lhs, rhs)
+            },
+            (_, Some(_), None) if oper.is_comparison() && rhs.is_null_pointer_constant() => (lhs, convert(rhs, &lhs_type)),
+            (_, None, Some(_)) if oper.is_comparison() && lhs.is_null_pointer_constant() => (convert(lhs, &rhs_type), rhs),
+            _ => {
+                // Carry on as integers so the error is not reported again.
+                context.diagnostics.emit(invalid_operands(token, &lhs_type, &rhs_type, span));
+                (convert(lhs, &Type::Int), convert(rhs, &Type::Int))
+            },
+        };
+        return Expression::operation(oper, lhs, rhs, span);
+    }
+
+    /// Builds the node for `lhs oper rhs` once the operands have been
+    /// converted, together with the type of its value.
+    pub fn operation(oper : BiOperator, lhs : Expression, rhs : Expression, span : Span) -> Expression
+    {
+        let (lhs_type, rhs_type) = (lhs.ty(), rhs.ty());
+        let ty = match (lhs_type.is_pointer(), rhs_type.is_pointer())
+        {
+            _ if oper.is_comparison() => Type::Int,
+            // The difference of two pointers.
+            (true, true) => Type::Int,
+            (false, true) => rhs_type,
+            _ => lhs_type,
+        };
+        return Expression::BinOp(oper, Box::new(lhs), Box::new(rhs), ty, span);
     }
-}
 
+    /// Whether the expression is an integer constant 0, possibly cast to
+    /// `void *`, which converts to a null pointer of any type.
+    pub fn is_null_pointer_constant(&self) -> bool
+    {
+        let ty = self.ty();
+        return (ty.is_integer() || ty == Type::pointer_to(Type::Void)) && self.constant_value() == Some(0);
+    }
 
-impl FunctionDecl 
-{
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<FunctionDecl>
+    /// Value of an integer constant expression in its type, or `None` if the
+    /// expression is not one.
+    pub fn constant_value(&self) -> Option<i64>
     {
-        if validate_rule(LexToken::Int, tokens )
+        match self
         {
-            if let Some(LexToken::Identifier(func_name)) = tokens.pop_front()
-            {
-                let func_lex_tokens = vec!{LexToken::OpenParenth
-                            ,LexToken::CloseParenth
-                            ,LexToken::OpenBrace};
-                if validate_rules(&func_lex_tokens, tokens)
+            // C forbids assignments and the comma operator in constant
+            // expressions.
+            Expression::Assign(..) | Expression::CompoundAssign(..) | Expression::Comma(..) => return None,
+            // Addresses are only known once the program is linked.
+            Expression::BinOp(_, lhs, rhs, _, _) if lhs.ty().is_pointer() || rhs.ty().is_pointer() => return None,
+            Expression::BinOp(oper, lhs, rhs, _, _) => {
+                let lhs_value = lhs.constant_value()?;
+                // The right operand is not evaluated when the left one decides
+                // the result, so it need not be valid.
+                match oper
                 {
-                    if let Some(func_body) = Statement::new(tokens)
-                    {
-                        if validate_rule(LexToken::CloseBrace, tokens)
-                        {
-                            return Some(FunctionDecl{name: func_name, body: func_body});
-                        }
-                    }
+                    BiOperator::LogicalAnd if lhs_value == 0 => return Some(0),
+                    BiOperator::LogicalOr if lhs_value != 0 => return Some(1),
+                    _ => {},
+                };
+                return oper.fold(lhs_value, rhs.constant_value()?, &lhs.ty());
+            },
+            Expression::Conditional(condition, then, otherwise, _) => {
+                if condition.constant_value()? != 0
+                {
+                    return then.constant_value();
                 }
-            }
-            else {
-                println!("FAILED WITH ID");
-            }
-        }
-        println!("Error parsing the Function Decl");
-        None
+                return otherwise.constant_value();
+            },
+            Expression::Convert(ty, expression) => return Some(ty.convert(expression.constant_value()?)),
+            Expression::Factor(factor) => return factor.constant_value(),
+        };
     }
-}
 
-impl Program 
-{
-    pub fn new(tokens : &mut VecDeque<LexToken>) -> Option<Program>
+    /// The object whose address, moved by an offset in bytes, is the value
+    /// of the expression when the linker can work it out, as for `&x + 1`,
+    /// `array` or `"text"`. Only objects with static storage have such an
+    /// address.
+    pub fn address_constant(&self) -> Option<(&Factor, i64)>
     {
-        if let Some(decl) = FunctionDecl::new(tokens)
+        match self
         {
-            return Some(Program::Declaration(decl));
-        } 
-        println!("Error parsing the Program");
-        None
+            Expression::Convert(ty, expression) if ty.is_pointer() && expression.ty().is_array() => {
+                let Expression::Factor(array) = expression.as_ref() else { return None };
+                return array.object_address();
+            },
+            Expression::Convert(ty, expression) if ty.is_pointer() && expression.ty().is_pointer() => return expression.address_constant(),
+            Expression::BinOp(oper @ (BiOperator::Addition | BiOperator::Subtraction), lhs, rhs, _, _) if !rhs.ty().is_pointer() || !lhs.ty().is_pointer() => {
+                let (pointer, index) = if lhs.ty().is_pointer() { (lhs, rhs) } else { (rhs, lhs) };
+                let delta = index.constant_value()? * pointer.ty().pointee()?.size().max(1) as i64;
+                let (object, offset) = pointer.address_constant()?;
+                match oper
+                {
+                    BiOperator::Subtraction => return Some((object, offset - delta)),
+                    _ => return Some((object, offset + delta)),
+                };
+            },
+            Expression::Factor(factor) => return factor.address_constant(),
+            _ => return None,
+        };
     }
-}
-
 
-fn validate_rules(req_tokens : &Vec<LexToken>, tokens : &mut VecDeque<LexToken>) -> bool
-{
-    if tokens.len() < req_tokens.len()
+    /// The type of the value, once the operands have been converted.
+    pub fn ty(&self) -> Type
     {
-        println!("FAILURE, Not enough tokens left");
-        return false;
+        match self
+        {
+            Expression::Assign(target, _, _) | Expression::CompoundAssign(_, target, _, _) => return target.ty(),
+            Expression::Comma(_, rhs, _) => return rhs.ty(),
+            Expression::BinOp(_, _, _, ty, _) => return ty.clone(),
+            Expression::Conditional(_, then, _, _) => return then.ty(),
+            Expression::Convert(ty, _) => return ty.clone(),
+            Expression::Factor(factor) => return factor.ty(),
+        };
     }
 
-    for (match_token,req_token)  in tokens.drain(0..req_tokens.len()).zip(req_tokens)
+    pub fn span(&self) -> Span
     {
-        if *req_token != match_token
+        match self
         {
-            println!("FAILURE, Expected Token {:?}, Found token {:?}", req_token,match_token);
-            return false;
-        }
+            Expression::Assign(_, _, span) => return *span,
+            Expression::CompoundAssign(_, _, _, span) => return *span,
+            Expression::Comma(_, _, span) => return *span,
+            Expression::BinOp(_, _, _, _, span) => return *span,
+            Expression::Conditional(_, _, _, span) => return *span,
+            Expression::Convert(_, expression) => return expression.span(),
+            Expression::Factor(factor) => return factor.span(),
+        };
     }
-    true
 }
 
-fn validate_rule(req_token : LexToken, tokens : &mut VecDeque<LexToken>) -> bool
+impl Factor
 {
-    if let Some(token) = tokens.pop_front()
+    /// Parses a unary expression: prefix operators applied to a primary
+    /// expression and its postfix operators.
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
     {
-        if req_token == token 
+        let token = match tokens.front()
+        {
+            Some(token) => token.clone(),
+            None => return Factor::primary(tokens, context),
+        };
+        if let Some(oper) = UniOperator::new(&token.kind)
+        {
+            tokens.pop_front();
+            let factor = Factor::new(tokens, context)?;
+            let span = token.span.to(factor.span());
+            if oper == UniOperator::AddressOf
+            {
+                let factor = lvalue(Expression::Factor(factor), &token, context)?;
+                return Some(Factor::UnOp(oper, Box::new(factor), span));
+            }
+            let factor = decay_factor(factor);
+            check_value(&factor.ty(), factor.span(), context);
+            let ty = factor.ty();
+            let valid = match oper
+            {
+                UniOperator::Dereference => ty.is_pointer(),
+                UniOperator::Negation | UniOperator::BitwiseComplement => ty.is_integer(),
+                _ => ty.is_scalar(),
+            };
+            if !valid && ty != Type::Void
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS,
+                                    &format!("invalid operand to unary `{}` (have `{}`)", token.kind.to_str().unwrap_or_default(), ty.name()))
+                                    .with_primary(factor.span(), &format!("has type `{}`", ty.name())));
+            }
+            return Some(Factor::UnOp(oper,Box::new(factor),span));
+        }
+        if token.kind == LexToken::OpenParenth && is_type_specifier(tokens.get(1).map(|token| &token.kind), &context.symbols)
         {
-            return true;
+            tokens.pop_front();
+            let ty = type_name(tokens, context)?;
+            validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+            let factor = decay_factor(Factor::new(tokens, context)?);
+            // Casting to `void` discards the value, which may then be missing.
+            let span = token.span.to(factor.span());
+            if ty != Type::Void
+            {
+                check_value(&factor.ty(), factor.span(), context);
+                if (!ty.is_scalar() || !factor.ty().is_scalar()) && factor.ty() != Type::Void
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS,
+                                        &format!("invalid cast from `{}` to `{}`", factor.ty().name(), ty.name()))
+                                        .with_primary(span, "only scalars can be cast"));
+                }
+            }
+            return Some(Factor::Cast(ty, Box::new(factor), span));
         }
-        else
+        if token.kind == LexToken::Increment || token.kind == LexToken::Decrement
         {
-            println!("FAILURE, Expected Token {:?}, Found token {:?}", req_token,token);
+            tokens.pop_front();
+            let factor = Factor::new(tokens, context)?;
+            let span = token.span.to(factor.span());
+            let target = Factor::increment_target(factor, &token, context)?;
+            let oper = if token.kind == LexToken::Increment { IncDecOperator::PreIncrement } else { IncDecOperator::PreDecrement };
+            return Some(Factor::IncDec(oper, Box::new(target), span));
+        }
+        if token.kind == LexToken::Sizeof
+        {
+            return Factor::size_of(tokens, context);
         }
-        return req_token == token
-    }
-    false
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    fn make_deq(tokens : Vec<LexToken>) -> VecDeque<LexToken>{
-        tokens.into_iter().collect()
+        let mut factor = Factor::primary(tokens, context)?;
+        while let Some(token) = tokens.front().filter(|token| matches!(token.kind, LexToken::Increment | LexToken::Decrement
+                                                                                 | LexToken::OpenBracket | LexToken::Dot | LexToken::Arrow)).cloned()
+        {
+            if token.kind == LexToken::OpenBracket
+            {
+                factor = Factor::subscript(factor, tokens, context)?;
+                continue;
+            }
+            if token.kind == LexToken::Dot || token.kind == LexToken::Arrow
+            {
+                factor = Factor::member(factor, tokens, context)?;
+                continue;
+            }
+            tokens.pop_front();
+            let span = factor.span().to(token.span);
+            let target = Factor::increment_target(factor, &token, context)?;
+            let oper = if token.kind == LexToken::Increment { IncDecOperator::PostIncrement } else { IncDecOperator::PostDecrement };
+            factor = Factor::IncDec(oper, Box::new(target), span);
+        }
+        return Some(factor);
     }
 
-    #[test]
-    fn validate_rule_true()
+    /// Parses `sizeof operand` or `sizeof(type)`. The operand is not
+    /// evaluated, only its type matters, and an array operand does not decay.
+    fn size_of(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
     {
-        let mut deq = make_deq(vec!(LexToken::Semicolon));
-        assert_eq!(true, validate_rule(LexToken::Semicolon, &mut deq));
-        assert_eq!(0,deq.len());
+        let start = validate_rule(LexToken::Sizeof, tokens, context.diagnostics)?;
+        let (ty, span) = if tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenParenth)
+                            && is_type_specifier(tokens.get(1).map(|token| &token.kind), &context.symbols)
+        {
+            tokens.pop_front();
+            let ty = type_name(tokens, context)?;
+            let end = validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+            (ty, start.to(end))
+        }
+        else
+        {
+            let operand = Factor::new(tokens, context)?;
+            (operand.ty(), start.to(operand.span()))
+        };
+        if !ty.is_complete()
+        {
+            let incomplete = if ty == Type::Void { "" } else { "incomplete type " };
+            context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("invalid application of `sizeof` to {}`{}`", incomplete, ty.name()))
+                                .with_primary(span, &format!("`{}` has no size", ty.name())));
+        }
+        return Some(Factor::SizeOf(ty, span));
     }
 
-    #[test]
-    fn validate_rule_false()
+    /// Parses `[index]` after `array`. C defines `a[i]` as `*(a + i)`, so
+    /// either operand may be the pointer.
+    fn subscript(array : Factor, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
     {
-        let mut deq = make_deq(vec!(LexToken::CloseBrace));
-        assert_eq!(false, validate_rule(LexToken::Semicolon, &mut deq));
-        assert_eq!(0,deq.len());
+        let open = validate_rule(LexToken::OpenBracket, tokens, context.diagnostics)?;
+        let index = Expression::new(tokens, context)?;
+        let end = validate_rule(LexToken::CloseBracket, tokens, context.diagnostics)?;
+        let span = array.span().to(end);
+        let array = decay(Expression::Factor(array));
+        check_value(&array.ty(), array.span(), context);
+        check_value(&index.ty(), index.span(), context);
+        let (array_type, index_type) = (array.ty(), index.ty());
+        let (array, index) = match (array_type.is_pointer(), index_type.is_pointer())
+        {
+            (true, false) if index_type.is_integer() => (array, index),
+            (false, true) if array_type.is_integer() => (index, array),
+            (true, _) => {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS, "array subscript is not an integer")
+                                    .with_primary(index.span(), &format!("has type `{}`", index_type.name())));
+                return None;
+            },
+            _ => {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS, "subscripted value is neither array nor pointer")
+                                    .with_primary(array.span(), &format!("has type `{}`", array_type.name())));
+                return None;
+            },
+        };
+        let address = Expression::pointer_binop(BiOperator::Addition, &Token{kind: LexToken::Addition, span: open}, array, index, context);
+        return Some(Factor::UnOp(UniOperator::Dereference, Box::new(Factor::Expr(Box::new(address), span)), span));
     }
 
-    #[test]
-    fn validate_rules_true()
+    /// Parses `.member` after a struct or union, or `->member` after a
+    /// pointer to one.
+    fn member(object : Factor, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
     {
-        let mut deq = make_deq(vec!(LexToken::Int
-                        , LexToken::Identifier(String::from("main"))
-                        , LexToken::OpenParenth
-                        , LexToken::CloseParenth));
+        let oper = tokens.pop_front().unwrap();
+        let (name, name_span) = match tokens.pop_front()
+        {
+            Some(Token{kind: LexToken::Identifier(name), span}) => (name, span),
+            token => {
+                report_unexpected("member name", token.as_ref(), context.diagnostics);
+                return None;
+            },
+        };
+        let span = object.span().to(name_span);
+        let object = if oper.kind == LexToken::Arrow
+        {
+            let pointer = decay_factor(object);
+            let ty = pointer.ty();
+            if !matches!(ty.pointee(), Some(Type::Record(_)))
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS, &format!("invalid type argument of `->` (have `{}`)", ty.name()))
+                                    .with_primary(pointer.span(), "not a pointer to a struct or union"));
+                return None;
+            }
+            let pointer_span = pointer.span();
+            Factor::UnOp(UniOperator::Dereference, Box::new(pointer), pointer_span)
+        }
+        else
+        {
+            object
+        };
+        let Type::Record(record) = object.ty() else {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS,
+                                &format!("request for member `{}` in something not a struct or union", name))
+                                .with_primary(object.span(), &format!("has type `{}`", object.ty().name())));
+            return None;
+        };
+        if !record.is_complete()
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("invalid use of incomplete type `{}`", record.name()))
+                                .with_primary(object.span(), "its members are not known"));
+            return None;
+        }
+        let Some((ty, offset)) = record.member(&name) else {
+            context.diagnostics.emit(Diagnostic::error(E_NO_MEMBER, &format!("`{}` has no member named `{}`", record.name(), name))
+                                .with_primary(name_span, "unknown member"));
+            return None;
+        };
+        return Some(Factor::Member(Box::new(object), name, offset, ty, span));
+    }
+
+    /// Checks the operand of `++` or `--`, which may be an integer or a
+    /// pointer, moved by one value of the type it points to.
+    fn increment_target(factor : Factor, token : &Token, context : &mut ParseContext) -> Option<Factor>
+    {
+        let target = lvalue(Expression::Factor(factor), token, context)?;
+        check_value(&target.ty(), target.span(), context);
+        let ty = target.ty();
+        if ty.is_record()
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS,
+                                &format!("invalid operand to `{}` (have `{}`)", token.kind.to_str().unwrap_or_default(), ty.name()))
+                                .with_primary(target.span(), &format!("has type `{}`", ty.name())));
+        }
+        return Some(target);
+    }
+
+    fn primary(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
+    {
+        let token = match tokens.pop_front()
+        {
+            Some(token) => token,
+            None => {
+                context.diagnostics.emit(Diagnostic::error(E_UNEXPECTED_EOF, "expected expression, found end of file"));
+                return None;
+            },
+        };
+        match token.kind
+        {
+            LexToken::OpenParenth => {
+                let expr = Expression::new(tokens, context)?;
+                let close = validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+                // A parenthesized array is still an array, as in `sizeof (a)`.
+                let expr = match expr
+                {
+                    Expression::Convert(_, array) if array.ty().is_array() => *array,
+                    expr => expr,
+                };
+                return Some(Factor::Expr(Box::new(expr), token.span.to(close)));
+            }
+            LexToken::IntLiteral(_) | LexToken::CharLiteral(_) => { 
+                let cons = Constant::new(&token, context.diagnostics)?;
+                return Some(Factor::Constant(cons, token.span));
+            },
+            LexToken::StringLiteral(_) => {
+                tokens.push_front(token);
+                return Factor::string_literal(tokens, context);
+            },
+            LexToken::Identifier(name) if tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenParenth) => {
+                return Factor::call(name, token.span, tokens, context);
+            },
+            LexToken::Identifier(_) => return Factor::identifier(&token, context),
+            _ => {
+                context.diagnostics.emit(Diagnostic::error(E_EXPECTED_EXPRESSION,
+                                    &format!("expected expression, found {}", describe_token(Some(&token.kind))))
+                                    .with_primary(token.span, "expected expression"));
+                return None;
+            },
+        }
+    }
+
+    /// Parses the arguments of a call to `name` and checks them against its
+    /// declaration.
+    fn call(name : String, name_span : Span, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
+    {
+        validate_rule(LexToken::OpenParenth, tokens, context.diagnostics)?;
+        let mut args = Vec::new();
+        if tokens.front().map(|token| &token.kind) != Some(&LexToken::CloseParenth)
+        {
+            args.push(Expression::assignment(tokens, context)?);
+            while tokens.front().map(|token| &token.kind) == Some(&LexToken::Comma)
+            {
+                tokens.pop_front();
+                args.push(Expression::assignment(tokens, context)?);
+            }
+        }
+        let end = validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+        let span = name_span.to(end);
+        for arg in &args
+        {
+            check_value(&arg.ty(), arg.span(), context);
+        }
+
+        if let Some(variable) = context.symbols.lookup(&name)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_NOT_FUNCTION, &format!("called object `{}` is not a function", name))
+                                .with_primary(name_span, "not a function")
+                                .with_secondary(variable.span, &format!("declared here as {}", symbol_kind(variable))));
+            return None;
+        }
+        // Without the types of the parameters, the arguments are only
+        // promoted.
+        let promoted = |args : Vec<Expression>| -> Vec<Expression> {
+            return args.into_iter().map(|arg| {
+                let ty = arg.ty().promote();
+                convert(arg, &ty)
+            }).collect();
+        };
+        match context.functions.get(&name)
+        {
+            Some(function) if function.params.as_ref().is_some_and(|params| params.len() != args.len()) => {
+                let count = function.params.as_ref().map_or(0, |params| params.len());
+                context.diagnostics.emit(Diagnostic::error(E_ARGUMENT_COUNT,
+                                    &format!("function `{}` takes {} argument(s) but {} were supplied", name, count, args.len()))
+                                    .with_primary(span, "wrong number of arguments")
+                                    .with_secondary(function.span, "declared here"));
+                return Some(Factor::Call(name, args, function.return_type.clone(), span));
+            },
+            // Arguments are converted to the types of the parameters, as if
+            // by assignment.
+            Some(function) => {
+                let (params, return_type) = (function.params.clone(), function.return_type.clone());
+                let args = match params
+                {
+                    Some(params) => args.into_iter().zip(&params).map(|(arg, ty)| convert_for_assignment(arg, ty, context)).collect(),
+                    None => promoted(args),
+                };
+                return Some(Factor::Call(name, args, return_type, span));
+            },
+            // Like C89, calling an undeclared function declares it as
+            // returning `int`, with unspecified parameters.
+            None => {
+                context.diagnostics.emit(Diagnostic::warning(W_IMPLICIT_DECLARATION, &format!("implicit declaration of function `{}`", name))
+                                    .with_primary(name_span, "")
+                                    .with_note("declare it before calling it"));
+                return Some(Factor::Call(name, promoted(args), Type::Int, span));
+            },
+        };
+    }
+
+    /// Adjacent string literals are concatenated into one, after each piece's
+    /// escape sequences have been decoded on their own.
+    fn string_literal(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Factor>
+    {
+        let mut bytes = Vec::new();
+        let mut span : Option<Span> = None;
+        let mut valid = true;
+        while let Some(Token{kind: LexToken::StringLiteral(spelling), span: piece_span}) = tokens.front().cloned()
+        {
+            tokens.pop_front();
+            span = Some(span.map_or(piece_span, |span| span.to(piece_span)));
+            match decode_escapes(literal_body(&spelling))
+            {
+                Ok(decoded) => {
+                    report_literal_warnings(&decoded.warnings, piece_span, context.diagnostics);
+                    bytes.extend(decoded.bytes);
+                },
+                Err(err) => {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_LITERAL, &err.message(&spelling))
+                                        .with_primary(piece_span, "invalid string literal"));
+                    valid = false;
+                },
+            }
+        }
+        if !valid
+        {
+            return None;
+        }
+        return Some(Factor::StringLiteral(bytes, span?));
+    }
+
+    /// Whether the factor designates an object, which has an address and can
+    /// be assigned to unless it is an array.
+    pub fn is_lvalue(&self) -> bool
+    {
+        match self
+        {
+            Factor::Variable(..) | Factor::StringLiteral(..) | Factor::UnOp(UniOperator::Dereference, _, _) => return true,
+            Factor::Expr(expression, _) => return matches!(expression.as_ref(), Expression::Factor(factor) if factor.is_lvalue()),
+            // A member of a struct returned by a call is not an object.
+            Factor::Member(object, _, _, _, _) => return object.is_lvalue(),
+            _ => return false,
+        };
+    }
+
+    pub fn constant_value(&self) -> Option<i64>
+    {
+        match self
+        {
+            Factor::Constant(Constant::Integer(literal), _) => return Some(Type::from(literal.ty).convert(literal.value as i64)),
+            Factor::Constant(Constant::Character(value) | Constant::Enumerator(_, value), _) => return Some(*value as i64),
+            Factor::UnOp(oper, factor, _) => {
+                let value = factor.constant_value()?;
+                let ty = factor.ty().promote();
+                match oper
+                {
+                    UniOperator::Negation => return Some(ty.convert(value.wrapping_neg())),
+                    UniOperator::BitwiseComplement => return Some(ty.convert(!value)),
+                    UniOperator::LogicalNegation => return Some((value == 0) as i64),
+                    // Addresses are only known once the program is linked.
+                    UniOperator::Dereference | UniOperator::AddressOf => return None,
+                };
+            },
+            Factor::SizeOf(ty, _) => return Some(ty.size() as i64),
+            Factor::Cast(Type::Void, _, _) => return None,
+            Factor::Cast(ty, factor, _) => return Some(ty.convert(factor.constant_value()?)),
+            Factor::Expr(expression, _) => return expression.constant_value(),
+            Factor::StringLiteral(..) | Factor::Variable(..) | Factor::IncDec(..) | Factor::Call(..) | Factor::Member(..) => return None,
+        };
+    }
+
+    /// See `Expression::address_constant`.
+    fn address_constant(&self) -> Option<(&Factor, i64)>
+    {
+        match self
+        {
+            Factor::UnOp(UniOperator::AddressOf, object, _) => return object.object_address(),
+            Factor::Cast(ty, factor, _) if ty.is_pointer() && factor.ty().is_pointer() => return factor.address_constant(),
+            Factor::Expr(expression, _) => return expression.address_constant(),
+            _ => return None,
+        };
+    }
+
+    /// The variable or string literal the lvalue is part of, and the offset
+    /// in bytes of the lvalue into it, when that offset is a constant.
+    fn object_address(&self) -> Option<(&Factor, i64)>
+    {
+        match self
+        {
+            Factor::Variable(..) | Factor::StringLiteral(..) => return Some((self, 0)),
+            Factor::UnOp(UniOperator::Dereference, pointer, _) => return pointer.address_constant(),
+            Factor::Member(object, _, offset, _, _) => {
+                let (object, object_offset) = object.object_address()?;
+                return Some((object, object_offset + *offset as i64));
+            },
+            Factor::Expr(expression, _) => match expression.as_ref()
+            {
+                Expression::Factor(factor) => return factor.object_address(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+    }
+
+    pub fn ty(&self) -> Type
+    {
+        match self
+        {
+            Factor::Constant(Constant::Integer(literal), _) => return Type::from(literal.ty),
+            Factor::Constant(Constant::Character(_) | Constant::Enumerator(..), _) => return Type::Int,
+            // The array holds the terminating NUL too.
+            Factor::StringLiteral(bytes, _) => return Type::array_of(Type::Char, bytes.len() + 1),
+            Factor::Variable(variable, _) => return variable.ty.clone(),
+            Factor::IncDec(_, target, _) => return target.ty(),
+            Factor::UnOp(UniOperator::LogicalNegation, _, _) => return Type::Int,
+            // Dereferencing anything else has been reported, and gives an `int`.
+            Factor::UnOp(UniOperator::Dereference, factor, _) => return factor.ty().pointee().cloned().unwrap_or(Type::Int),
+            Factor::UnOp(UniOperator::AddressOf, factor, _) => return Type::pointer_to(factor.ty()),
+            Factor::UnOp(_, factor, _) => return factor.ty().promote(),
+            // `size_t` is `unsigned int` on i386.
+            Factor::SizeOf(..) => return Type::UnsignedInt,
+            Factor::Call(_, _, ty, _) | Factor::Cast(ty, _, _) | Factor::Member(_, _, _, ty, _) => return ty.clone(),
+            Factor::Expr(expression, _) => return expression.ty(),
+        };
+    }
+
+    pub fn span(&self) -> Span
+    {
+        match self
+        {
+            Factor::Constant(_, span) => return *span,
+            Factor::StringLiteral(_, span) => return *span,
+            Factor::Variable(_, span) => return *span,
+            Factor::UnOp(_, _, span) => return *span,
+            Factor::IncDec(_, _, span) => return *span,
+            Factor::Call(_, _, _, span) => return *span,
+            Factor::Cast(_, _, span) => return *span,
+            Factor::SizeOf(_, span) => return *span,
+            Factor::Member(_, _, _, _, span) => return *span,
+            Factor::Expr(_, span) => return *span,
+        };
+    }
+
+    /// Looks up what an identifier token refers to: a variable, or an
+    /// enumeration constant. A typedef name is not an expression.
+    fn identifier(token : &Token, context : &mut ParseContext) -> Option<Factor>
+    {
+        let name = match &token.kind
+        {
+            LexToken::Identifier(name) => name,
+            _ => return None,
+        };
+        match context.symbols.lookup(name)
+        {
+            Some(Symbol{kind: SymbolKind::Variable(id), ty, ..}) => {
+                return Some(Factor::Variable(Variable{name: name.clone(), id: *id, ty: ty.clone()}, token.span));
+            },
+            Some(Symbol{kind: SymbolKind::Enumerator(value), ..}) => {
+                return Some(Factor::Constant(Constant::Enumerator(name.clone(), *value), token.span));
+            },
+            Some(Symbol{kind: SymbolKind::Typedef, ..}) => {
+                context.diagnostics.emit(Diagnostic::error(E_EXPECTED_EXPRESSION, &format!("expected expression, found type name `{}`", name))
+                                    .with_primary(token.span, "names a type"));
+                return None;
+            },
+            None => {
+                context.diagnostics.emit(Diagnostic::error(E_UNDECLARED,
+                                    &format!("use of undeclared identifier `{}`", name))
+                                    .with_primary(token.span, "not declared in this scope"));
+                return None;
+            },
+        };
+    }
+}
+
+impl DeclSpecifiers
+{
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<DeclSpecifiers>
+    {
+        let mut storage = None;
+        let mut type_specifiers = Vec::new();
+        let mut named = None;
+        let mut span : Option<Span> = None;
+        // An identifier after a type specifier is the declarator, even when
+        // it names a type too: `T T;` hides the typedef name `T`.
+        while let Some(token) = tokens.front().filter(|token| match &token.kind
+        {
+            LexToken::Identifier(_) if !type_specifiers.is_empty() => false,
+            kind => starts_declaration(Some(kind), &context.symbols),
+        }).cloned()
+        {
+            tokens.pop_front();
+            span = Some(span.map_or(token.span, |span| span.to(token.span)));
+            match token.kind
+            {
+                LexToken::Struct | LexToken::Union => {
+                    let (ty, record_span) = DeclSpecifiers::record(&token, tokens, context)?;
+                    span = span.map(|span| span.to(record_span));
+                    named = Some(ty);
+                    type_specifiers.push(token.kind);
+                },
+                LexToken::Enum => {
+                    let (ty, enum_span) = DeclSpecifiers::enumeration(&token, tokens, context)?;
+                    span = span.map(|span| span.to(enum_span));
+                    named = Some(ty);
+                    type_specifiers.push(token.kind);
+                },
+                LexToken::Identifier(ref name) => {
+                    named = context.symbols.typedef(name).cloned();
+                    type_specifiers.push(token.kind);
+                },
+                LexToken::Static | LexToken::Extern | LexToken::Typedef if storage.is_none() => {
+                    storage = Some(match token.kind
+                    {
+                        LexToken::Static => StorageClass::Static,
+                        LexToken::Extern => StorageClass::Extern,
+                        _ => StorageClass::Typedef,
+                    });
+                },
+                LexToken::Static | LexToken::Extern | LexToken::Typedef => {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, "multiple storage classes in one declaration")
+                                        .with_primary(token.span, ""));
+                },
+                kind => type_specifiers.push(kind),
+            };
+        }
+        if type_specifiers.is_empty()
+        {
+            report_unexpected("a type specifier", tokens.front(), context.diagnostics);
+            return None;
+        }
+        let span = span?;
+        let ty = match named
+        {
+            Some(ty) if type_specifiers.len() == 1 => ty,
+            _ => DeclSpecifiers::type_of(&type_specifiers, span, context),
+        };
+        return Some(DeclSpecifiers{storage, ty, span});
+    }
+
+    /// Parses a struct or union specifier after its keyword: a tag, members
+    /// in braces, or both. A tag alone names the type declared with it in the
+    /// innermost scope that has one, or declares a new type whose members may
+    /// be given later, as does `struct tag;` in any case.
+    fn record(keyword : &Token, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<(Type, Span)>
+    {
+        let kind = if keyword.kind == LexToken::Struct { RecordKind::Struct } else { RecordKind::Union };
+        let mut span = keyword.span;
+        let tag = DeclSpecifiers::tag(&mut span, tokens);
+        let defines = tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBrace);
+        let Some(tag) = tag else {
+            if !defines
+            {
+                report_unexpected("a tag or `{`", tokens.front(), context.diagnostics);
+                return None;
+            }
+            let record = RecordType::new(kind, None);
+            let end = DeclSpecifiers::members(&record, tokens, context)?;
+            return Some((Type::Record(record), span.to(end)));
+        };
+        let local = defines || tokens.front().map(|token| &token.kind) == Some(&LexToken::Semicolon);
+        let previous = if local { context.symbols.local_tag(&tag) } else { context.symbols.lookup_tag(&tag) }.cloned();
+        let record = match previous
+        {
+            Some(previous) => match previous.ty
+            {
+                Type::Record(record) if record.kind() == kind && !(defines && record.is_complete()) => record,
+                Type::Record(record) if record.kind() == kind => {
+                    context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redefinition of `{}`", record.name()))
+                                        .with_primary(span, "defined again here")
+                                        .with_secondary(previous.span, "previously declared here"));
+                    RecordType::new(kind, Some(tag))
+                },
+                _ => {
+                    context.diagnostics.emit(Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("`{}` defined as wrong kind of tag", tag))
+                                        .with_primary(span, "")
+                                        .with_secondary(previous.span, &format!("declared here as `{}`", tag_name(&previous.ty, &tag))));
+                    RecordType::new(kind, Some(tag))
+                },
+            },
+            None => {
+                let record = RecordType::new(kind, Some(tag.clone()));
+                context.symbols.declare_tag(&tag, Type::Record(record.clone()), span);
+                record
+            },
+        };
+        if defines
+        {
+            span = span.to(DeclSpecifiers::members(&record, tokens, context)?);
+        }
+        return Some((Type::Record(record), span));
+    }
+
+    /// Parses an enum specifier after its keyword: a tag, enumerators in
+    /// braces, or both. Enumerated types are `int`, so a tag alone only has
+    /// to name an enum defined earlier.
+    fn enumeration(keyword : &Token, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<(Type, Span)>
+    {
+        let mut span = keyword.span;
+        let tag = DeclSpecifiers::tag(&mut span, tokens);
+        let defines = tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBrace);
+        match &tag
+        {
+            Some(tag) => {
+                let previous = if defines { context.symbols.local_tag(tag) } else { context.symbols.lookup_tag(tag) }.cloned();
+                match previous
+                {
+                    Some(previous) if previous.ty.is_record() => {
+                        context.diagnostics.emit(Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("`{}` defined as wrong kind of tag", tag))
+                                            .with_primary(span, "")
+                                            .with_secondary(previous.span, &format!("declared here as `{}`", tag_name(&previous.ty, tag))));
+                    },
+                    Some(previous) if defines => {
+                        context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redefinition of `enum {}`", tag))
+                                            .with_primary(span, "defined again here")
+                                            .with_secondary(previous.span, "previously defined here"));
+                    },
+                    Some(_) => {},
+                    None if defines => context.symbols.declare_tag(tag, Type::Int, span),
+                    None => {
+                        context.diagnostics.emit(Diagnostic::error(E_UNDECLARED, &format!("use of undefined `enum {}`", tag))
+                                            .with_primary(span, "not defined in this scope"));
+                    },
+                };
+            },
+            None if !defines => {
+                report_unexpected("a tag or `{`", tokens.front(), context.diagnostics);
+                return None;
+            },
+            None => {},
+        };
+        if defines
+        {
+            span = span.to(DeclSpecifiers::enumerators(tokens, context)?);
+        }
+        return Some((Type::Int, span));
+    }
+
+    /// Parses the enumerators of an enum, in braces, and declares them in the
+    /// innermost scope. Each one is one more than the one before it unless it
+    /// is given a value. Returns the span of the closing brace.
+    fn enumerators(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Span>
+    {
+        validate_rule(LexToken::OpenBrace, tokens, context.diagnostics)?;
+        let mut value : i64 = 0;
+        loop
+        {
+            let (name, name_span) = match tokens.pop_front()
+            {
+                Some(Token{kind: LexToken::Identifier(name), span}) => (name, span),
+                token => {
+                    report_unexpected("an enumerator", token.as_ref(), context.diagnostics);
+                    return None;
+                },
+            };
+            if tokens.front().map(|token| &token.kind) == Some(&LexToken::Assignment)
+            {
+                tokens.pop_front();
+                let expression = Expression::conditional(tokens, context)?;
+                match if expression.ty().is_integer() { expression.constant_value() } else { None }
+                {
+                    Some(constant) => value = constant,
+                    None => {
+                        context.diagnostics.emit(Diagnostic::error(E_NOT_CONSTANT, &format!("value of enumerator `{}` is not an integer constant", name))
+                                            .with_primary(expression.span(), "not a constant"));
+                    },
+                };
+            }
+            if i32::try_from(value).is_err()
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_CONSTANT, &format!("value of enumerator `{}` is outside the range of `int`", name))
+                                    .with_primary(name_span, &format!("would be `{}`", value)));
+                value = 0;
+            }
+            if let Some(function) = context.functions.get(&name).filter(|_| context.symbols.is_file_scope())
+            {
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                    .with_primary(name_span, "declared here as an enumerator")
+                                    .with_secondary(function.span, "previously declared as a function"));
+            }
+            else if let Err(previous) = context.symbols.declare_enumerator(&name, value as i32, name_span)
+            {
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redeclaration of `{}`", name))
+                                    .with_primary(name_span, "redeclared here")
+                                    .with_secondary(previous.span, "previous declaration is here"));
+            }
+            value += 1;
+            if tokens.front().map(|token| &token.kind) != Some(&LexToken::Comma)
+            {
+                break;
+            }
+            tokens.pop_front();
+            // The list may end with a comma.
+            if tokens.front().map(|token| &token.kind) == Some(&LexToken::CloseBrace)
+            {
+                break;
+            }
+        }
+        return validate_rule(LexToken::CloseBrace, tokens, context.diagnostics);
+    }
+
+    /// Takes the tag after `struct`, `union` or `enum`, if there is one,
+    /// extending `span` over it.
+    fn tag(span : &mut Span, tokens : &mut VecDeque<Token>) -> Option<String>
+    {
+        let Some(Token{kind: LexToken::Identifier(tag), span: tag_span}) = tokens.front() else {
+            return None;
+        };
+        *span = span.to(*tag_span);
+        let tag = tag.clone();
+        tokens.pop_front();
+        return Some(tag);
+    }
+
+    /// Parses the member declarations of a struct or union, in braces, and
+    /// lays it out. Returns the span of the closing brace.
+    fn members(record : &RecordType, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Span>
+    {
+        let start = validate_rule(LexToken::OpenBrace, tokens, context.diagnostics)?;
+        let mut members = Vec::new();
+        let mut names : HashMap<String, Span> = HashMap::new();
+        while tokens.front().map(|token| &token.kind) != Some(&LexToken::CloseBrace)
+        {
+            let specifiers = DeclSpecifiers::new(tokens, context)?;
+            if specifiers.storage.is_some()
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, "storage class in a member declaration")
+                                    .with_primary(specifiers.span, "members cannot have one"));
+            }
+            // A struct or union without a tag or a name lends its members to
+            // this one.
+            if tokens.front().map(|token| &token.kind) == Some(&LexToken::Semicolon)
+            {
+                tokens.pop_front();
+                if matches!(&specifiers.ty, Type::Record(inner) if inner.tag().is_none())
+                {
+                    members.push((None, specifiers.ty));
+                }
+                continue;
+            }
+            loop
+            {
+                let declarator = Declarator::new(false, tokens, context)?;
+                let (name, name_span) = declarator.name()?;
+                let ty = declarator.ty(specifiers.ty.clone(), context);
+                if !ty.is_complete()
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("member `{}` has incomplete type `{}`", name, ty.name()))
+                                        .with_primary(name_span, "its size is not known"));
+                }
+                else if let Some(previous) = names.get(&name)
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("duplicate member `{}`", name))
+                                        .with_primary(name_span, "")
+                                        .with_secondary(*previous, "previously declared here"));
+                }
+                else
+                {
+                    names.insert(name.clone(), name_span);
+                    members.push((Some(name), ty));
+                }
+                if tokens.front().map(|token| &token.kind) != Some(&LexToken::Comma)
+                {
+                    break;
+                }
+                tokens.pop_front();
+            }
+            validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+        }
+        let end = validate_rule(LexToken::CloseBrace, tokens, context.diagnostics)?;
+        // The members may have defined the type already, as in
+        // `struct s { struct s { int x; } inner; }`.
+        if record.is_complete()
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("nested redefinition of `{}`", record.name()))
+                                .with_primary(end, ""));
+            return Some(end);
+        }
+        record.define(members);
+        if Type::Record(record.clone()).checked_size().is_none()
+        {
+            context.diagnostics.emit(too_large(&format!("`{}` is too large", record.name()), start.to(end)));
+        }
+        return Some(end);
+    }
+
+    /// The type named by a list of type specifiers, which may come in any
+    /// order: `unsigned long int` and `long unsigned` are the same type. An
+    /// invalid list is reported and taken as `int`, so parsing goes on.
+    fn type_of(specifiers : &[LexToken], span : Span, context : &mut ParseContext) -> Type
+    {
+        let count = |kind : LexToken| specifiers.iter().filter(|specifier| **specifier == kind).count();
+        let signedness = (count(LexToken::Signed), count(LexToken::Unsigned));
+        let unsigned = signedness == (0, 1);
+        let named = specifiers.iter().filter(|specifier| {
+            matches!(specifier, LexToken::Struct | LexToken::Union | LexToken::Enum | LexToken::Identifier(_))
+        }).count();
+        let ty = match (count(LexToken::Void), count(LexToken::Bool), count(LexToken::Char), count(LexToken::Short),
+                        count(LexToken::Int), count(LexToken::Long), signedness)
+        {
+            // A struct, union or enum or a typedef name cannot be combined
+            // with other types.
+            _ if named > 0 => None,
+            (1, 0, 0, 0, 0, 0, (0, 0)) => Some(Type::Void),
+            (0, 1, 0, 0, 0, 0, (0, 0)) => Some(Type::Bool),
+            (0, 0, 1, 0, 0, 0, (0, 0)) => Some(Type::Char),
+            (0, 0, 1, 0, 0, 0, (1, 0)) => Some(Type::SignedChar),
+            (0, 0, 1, 0, 0, 0, (0, 1)) => Some(Type::UnsignedChar),
+            (0, 0, 0, 1, 0 | 1, 0, (0 | 1, 0) | (0, 1)) => Some(if unsigned { Type::UnsignedShort } else { Type::Short }),
+            (0, 0, 0, 0, 0 | 1, 0, (0 | 1, 0) | (0, 1)) => Some(if unsigned { Type::UnsignedInt } else { Type::Int }),
+            (0, 0, 0, 0, 0 | 1, 1, (0 | 1, 0) | (0, 1)) => Some(if unsigned { Type::UnsignedLong } else { Type::Long }),
+            (0, 0, 0, 0, 0 | 1, 2, (0 | 1, 0) | (0, 1)) => Some(if unsigned { Type::UnsignedLongLong } else { Type::LongLong }),
+            _ => None,
+        };
+        if let Some(ty) = ty
+        {
+            return ty;
+        }
+        let spelling : Vec<&str> = specifiers.iter().filter_map(|specifier| specifier.to_str()).collect();
+        context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, &format!("invalid combination of type specifiers `{}`", spelling.join(" ")))
+                            .with_primary(span, "does not name a type"));
+        return Type::Int;
+    }
+}
+
+impl Declaration
+{
+    /// Parses the comma-separated declarators of a declaration in a block
+    /// and the final `;`, once the specifiers they share have been read.
+    pub fn list(specifiers : DeclSpecifiers, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Vec<Declaration>>
+    {
+        let first = Declaration::new(specifiers.clone(), tokens, context)?;
+        return Declaration::rest_of_list(first, specifiers, tokens, context);
+    }
+
+    /// Parses the declarators following `first`, in a block or at file scope,
+    /// and the final `;`.
+    pub fn rest_of_list(first : Declaration, specifiers : DeclSpecifiers, tokens : &mut VecDeque<Token>,
+                        context : &mut ParseContext) -> Option<Vec<Declaration>>
+    {
+        let mut declarations = vec!(first);
+        while tokens.front().map(|token| &token.kind) == Some(&LexToken::Comma)
+        {
+            tokens.pop_front();
+            let declaration = if context.symbols.is_file_scope()
+            {
+                let declarator = Declarator::new(false, tokens, context)?;
+                let ty = declarator.ty(specifiers.ty.clone(), context);
+                let (name, name_span) = declarator.name()?;
+                Declaration::file_scope(specifiers.clone(), ty, name, name_span, tokens, context)?
+            }
+            else
+            {
+                Declaration::new(specifiers.clone(), tokens, context)?
+            };
+            declarations.push(declaration);
+        }
+        let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+        for declaration in &mut declarations
+        {
+            declaration.span = declaration.span.to(end);
+        }
+        return Some(declarations);
+    }
+
+    /// Parses a declarator in a block, once its specifiers have been read.
+    fn new(specifiers : DeclSpecifiers, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Declaration>
+    {
+        let declarator = Declarator::new(false, tokens, context)?;
+        let ty = declarator.ty(specifiers.ty.clone(), context);
+        let (name, name_span) = declarator.name()?;
+        // The variable is in scope from the end of its declarator on, so its
+        // own initializer can already refer to it.
+        let declared = match specifiers.storage
+        {
+            // A block scope `extern` refers to the global of that name.
+            Some(StorageClass::Extern) => {
+                let id = Declaration::declare_global(&name, name_span, specifiers.storage, &ty, false, context);
+                context.symbols.declare_id(&name, ty.clone(), name_span, id).map(|_| id)
+            },
+            _ => context.symbols.declare(&name, ty.clone(), name_span),
+        };
+        if let (Ok(id), Some(StorageClass::Static)) = (&declared, specifiers.storage)
+        {
+            context.statics.insert(*id);
+        }
+        let id = match declared
+        {
+            Ok(id) => id,
+            Err(previous) => {
+                // Keep parsing with the earlier variable so later errors are reported too.
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redeclaration of `{}`", name))
+                                    .with_primary(name_span, "redeclared here")
+                                    .with_secondary(previous.span, "previous declaration is here"));
+                previous.id().unwrap_or_else(|| context.symbols.new_id())
+            },
+        };
+        return Declaration::rest(specifiers, ty, name, name_span, id, tokens, context);
+    }
+
+    /// Parses the declarators of a `typedef` and the final `;`, and declares
+    /// each name as standing for its type in the innermost scope. Returns the
+    /// span of the whole declaration.
+    pub fn typedef(specifiers : DeclSpecifiers, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Span>
+    {
+        Declaration::typedef_name(&specifiers, tokens, context)?;
+        while tokens.front().map(|token| &token.kind) == Some(&LexToken::Comma)
+        {
+            tokens.pop_front();
+            Declaration::typedef_name(&specifiers, tokens, context)?;
+        }
+        let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+        return Some(specifiers.span.to(end));
+    }
+
+    /// Parses one declarator of a `typedef` and declares its name.
+    fn typedef_name(specifiers : &DeclSpecifiers, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<()>
+    {
+        let declarator = Declarator::new(false, tokens, context)?;
+        let ty = declarator.ty(specifiers.ty.clone(), context);
+        let (name, name_span) = declarator.name()?;
+        if let Some(function) = context.functions.get(&name).filter(|_| context.symbols.is_file_scope())
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                .with_primary(name_span, "declared here as a type")
+                                .with_secondary(function.span, "previously declared as a function"));
+        }
+        match context.symbols.declare_typedef(&name, ty.clone(), name_span)
+        {
+            Ok(()) => {},
+            // A typedef may be repeated, as long as the type is the same.
+            Err(previous) if previous.kind == SymbolKind::Typedef && previous.ty == ty => {},
+            Err(previous) if previous.kind == SymbolKind::Typedef => {
+                context.diagnostics.emit(Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("conflicting types for `{}`", name))
+                                    .with_primary(name_span, &format!("declared here as `{}`", ty.name()))
+                                    .with_secondary(previous.span, &format!("previously declared as `{}`", previous.ty.name())));
+            },
+            Err(previous) => {
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                    .with_primary(name_span, "declared here as a type")
+                                    .with_secondary(previous.span, &format!("previously declared as {}", symbol_kind(&previous))));
+            },
+        };
+        return Some(());
+    }
+
+    /// Parses the rest of a declarator at file scope, once the specifiers and
+    /// the declarator itself have been read.
+    pub fn file_scope(specifiers : DeclSpecifiers, ty : Type, name : String, name_span : Span, tokens : &mut VecDeque<Token>,
+                        context : &mut ParseContext) -> Option<Declaration>
+    {
+        let has_init = tokens.front().map(|token| &token.kind) == Some(&LexToken::Assignment);
+        let id = Declaration::declare_global(&name, name_span, specifiers.storage, &ty, has_init, context);
+        match context.symbols.lookup(&name)
+        {
+            // Redeclarations are already in scope.
+            Some(symbol) if symbol.id().is_some() => {},
+            Some(symbol) => {
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                    .with_primary(name_span, "declared here as a variable")
+                                    .with_secondary(symbol.span, &format!("previously declared as {}", symbol_kind(symbol))));
+            },
+            None => context.symbols.declare_id(&name, ty.clone(), name_span, id).unwrap(),
+        };
+        return Declaration::rest(specifiers, ty, name, name_span, id, tokens, context);
+    }
+
+    /// Parses the optional initializer of a declarator.
+    fn rest(specifiers : DeclSpecifiers, mut ty : Type, name : String, name_span : Span, id : VarId, tokens : &mut VecDeque<Token>,
+            context : &mut ParseContext) -> Option<Declaration>
+    {
+        if ty == Type::Void
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("variable `{}` declared `void`", name))
+                                .with_primary(name_span, "variables cannot have type `void`"));
+        }
+        // Only a declaration of a variable defined elsewhere may leave out
+        // the members of its struct.
+        else if !ty.is_complete() && specifiers.storage != Some(StorageClass::Extern)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("variable `{}` has incomplete type `{}`", name, ty.name()))
+                                .with_primary(name_span, "its size is not known"));
+        }
+        let declared = ty.clone();
+        let mut init = None;
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::Assignment)
+        {
+            let start = validate_rule(LexToken::Assignment, tokens, context.diagnostics)?;
+            let (initializer, complete) = Initializer::new(ty, tokens, context)?;
+            ty = complete;
+            let is_static = specifiers.storage.is_some() || context.symbols.is_file_scope();
+            if specifiers.storage == Some(StorageClass::Extern) && !context.symbols.is_file_scope()
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, &format!("`extern` variable `{}` has an initializer", name))
+                                    .with_primary(start, "initializer not allowed"));
+            }
+            // Variables with static storage are initialized before the program
+            // runs, so only with constants.
+            else if is_static
+            {
+                let invalid : Vec<Span> = initializer.values().into_iter().filter(|value| !is_static_constant(value, context))
+                                                .map(|value| value.span()).collect();
+                for span in invalid
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_NOT_CONSTANT, "initializer element is not constant")
+                                        .with_primary(span, "not a constant")
+                                        .with_secondary(name_span, "static storage variable declared here"));
+                }
+            }
+            init = Some(initializer);
+        }
+        if let Type::Array(element, 0) = ty
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_ARRAY_SIZE, &format!("array length missing in `{}`", name))
+                                .with_primary(name_span, "needs a length or an initializer"));
+            ty = Type::array_of(*element, 1);
+        }
+        if specifiers.storage.is_none() && !context.symbols.is_file_scope()
+        {
+            let previous = context.locals_size;
+            context.locals_size += ty.size();
+            if previous <= MAX_LOCALS_SIZE && context.locals_size > MAX_LOCALS_SIZE
+            {
+                context.diagnostics.emit(Diagnostic::error(E_TOO_LARGE,
+                                    &format!("total size of local objects {} exceeds maximum {}", context.locals_size, MAX_LOCALS_SIZE))
+                                    .with_primary(name_span, "the frame of the function cannot hold it"));
+            }
+        }
+        if ty != declared
+        {
+            context.symbols.complete(&name, ty.clone());
+            if let Some(global) = context.globals.get_mut(&name).filter(|global| global.id == id)
+            {
+                global.ty = ty.clone();
+            }
+        }
+        return Some(Declaration{name, id, ty, storage: specifiers.storage, init, span: specifiers.span.to(name_span)});
+    }
+
+    /// Records a declaration of the global `name` and returns its id,
+    /// checking it against earlier declarations of the name.
+    fn declare_global(name : &str, span : Span, storage : Option<StorageClass>, ty : &Type, is_definition : bool,
+                        context : &mut ParseContext) -> VarId
+    {
+        if let Some(function) = context.functions.get(name)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                .with_primary(span, "declared here as a variable")
+                                .with_secondary(function.span, "previously declared as a function"));
+        }
+        let definition = if is_definition { Some(span) } else { None };
+        let Some(previous) = context.globals.get_mut(name) else {
+            let id = context.symbols.new_id();
+            context.statics.insert(id);
+            let internal = storage == Some(StorageClass::Static);
+            context.globals.insert(name.to_string(), GlobalSymbol{id, ty: ty.clone(), span, definition, internal});
+            return id;
+        };
+        if previous.ty != *ty
+        {
+            context.diagnostics.emit(Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("conflicting types for `{}`", name))
+                                .with_primary(span, &format!("declared here as `{}`", ty.name()))
+                                .with_secondary(previous.span, &format!("previously declared as `{}`", previous.ty.name())));
+        }
+        // `extern` keeps the linkage of an earlier declaration, while no
+        // storage class at file scope always means external linkage.
+        let internal = match storage
+        {
+            Some(StorageClass::Static) => true,
+            Some(StorageClass::Extern) => previous.internal,
+            Some(StorageClass::Typedef) | None => false,
+        };
+        if internal != previous.internal
+        {
+            context.diagnostics.emit(linkage_conflict(name, span, internal, previous.span));
+        }
+        if let (Some(span), Some(previous_definition)) = (definition, previous.definition)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redefinition of `{}`", name))
+                                .with_primary(span, "redefined here")
+                                .with_secondary(previous_definition, "previous definition is here"));
+        }
+        previous.definition = previous.definition.or(definition);
+        return previous.id;
+    }
+}
+
+impl Initializer
+{
+    /// Parses the initializer of a variable of type `ty`. An array declared
+    /// without a length takes it from the initializer, so the complete type
+    /// is returned too.
+    fn new(ty : Type, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<(Initializer, Type)>
+    {
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBrace) || is_string_for(&ty, tokens)
+        {
+            let mut values = Vec::new();
+            let ty = Initializer::object(ty, 0, None, tokens, context, &mut values)?;
+            return Some((Initializer::List(Initializer::overlay(values)), ty));
+        }
+        let value = Expression::assignment(tokens, context)?;
+        check_value(&value.ty(), value.span(), context);
+        if ty.is_array()
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, "an array has to be initialized with a list or a string")
+                                .with_primary(value.span(), &format!("has type `{}`", value.ty().name()))
+                                .with_note("write the values of the elements between `{` and `}`"));
+            return Some((Initializer::List(Vec::new()), ty));
+        }
+        return Some((Initializer::Single(convert_for_assignment(value, &ty, context)), ty));
+    }
+
+    /// Parses the initializer of the part of the variable of type `ty` that
+    /// starts `offset` bytes into it, adding the value of every scalar it
+    /// gives to `values`. `first` is its first value if that has been parsed
+    /// already. The braces around the values of an inner array, struct or
+    /// union may be left out, which then takes as many values as it has
+    /// elements or members, so `{1, 2, 3, 4}` initializes an `int[2][2]`
+    /// like `{{1, 2}, {3, 4}}`.
+    fn object(ty : Type, offset : usize, first : Option<Expression>, tokens : &mut VecDeque<Token>, context : &mut ParseContext,
+                values : &mut Vec<(usize, Expression)>) -> Option<Type>
+    {
+        if first.is_none() && is_string_for(&ty, tokens)
+        {
+            return Initializer::string(ty, offset, tokens, context, values);
+        }
+        if first.is_none() && tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBrace)
+        {
+            tokens.pop_front();
+            // The string for an array of characters may be in braces.
+            if is_string_for(&ty, tokens)
+            {
+                let ty = Initializer::string(ty, offset, tokens, context, values)?;
+                Initializer::end(tokens).then_some(())
+                    .or_else(|| { report_unexpected("`}`", tokens.front(), context.diagnostics); None })?;
+                return Some(ty);
+            }
+            if ty.is_array() || ty.is_record()
+            {
+                return Initializer::aggregate(ty, offset, true, None, tokens, context, values);
+            }
+            // A scalar may have its value in braces too.
+            let ty = Initializer::object(ty, offset, None, tokens, context, values)?;
+            if !Initializer::end(tokens)
+            {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, "excess elements in scalar initializer")
+                                    .with_primary(tokens.front().map_or(Span::default(), |token| token.span), "only one value fits"));
+                return None;
+            }
+            return Some(ty);
+        }
+        // A struct or union may be initialized by a value of its type, so
+        // whether its braces are left out is only known from the value.
+        let first = match first
+        {
+            Some(value) => Some(value),
+            None if ty.is_array() || (ty.is_record() && matches!(tokens.front().map(|token| &token.kind), Some(LexToken::StringLiteral(_)))) => None,
+            None => {
+                let value = Expression::assignment(tokens, context)?;
+                check_value(&value.ty(), value.span(), context);
+                Some(value)
+            },
+        };
+        match first
+        {
+            Some(value) if !ty.is_array() && (!ty.is_record() || value.ty() == ty) => {
+                values.push((offset, convert_for_assignment(value, &ty, context)));
+                return Some(ty);
+            },
+            first => return Initializer::aggregate(ty, offset, false, first.map(Started::Value), tokens, context, values),
+        };
+    }
+
+    /// Parses the initializers of the elements of an array or the members of
+    /// a struct or union: in braces all of them up to the closing brace,
+    /// otherwise one for each element or member, stopping early at a
+    /// designator, which belongs to the enclosing list. A union takes one,
+    /// for its first member unless a designator names another.
+    fn aggregate(ty : Type, offset : usize, braced : bool, mut started : Option<Started>, tokens : &mut VecDeque<Token>,
+                    context : &mut ParseContext, values : &mut Vec<(usize, Expression)>) -> Option<Type>
+    {
+        let limit = match &ty
+        {
+            Type::Array(_, length) => *length,
+            Type::Record(record) if record.kind() == RecordKind::Union => record.members().len().min(1),
+            Type::Record(record) => record.members().len(),
+            _ => 0,
+        };
+        // A length of 0 is left for the initializer to give.
+        let open_length = matches!(ty, Type::Array(_, 0));
+        let mut index = 0;
+        let mut length = 0;
+        let mut excess = false;
+        let mut is_first = true;
+        loop
+        {
+            let (mut designators, value) = match started.take()
+            {
+                Some(Started::Designated(designators)) => (designators, None),
+                Some(Started::Value(value)) => (Vec::new(), Some(value)),
+                None => {
+                    if braced && Initializer::end(tokens)
+                    {
+                        break;
+                    }
+                    if !braced && (index >= limit || Initializer::at_end(tokens))
+                    {
+                        break;
+                    }
+                    if !is_first
+                    {
+                        if !braced && matches!(tokens.get(1).map(|token| &token.kind), Some(LexToken::OpenBracket | LexToken::Dot))
+                        {
+                            break;
+                        }
+                        validate_rule(LexToken::Comma, tokens, context.diagnostics)?;
+                    }
+                    (Initializer::designators(tokens, context)?, None)
+                },
+            };
+            is_first = false;
+            if !designators.is_empty()
+            {
+                index = Initializer::designated(&ty, &mut designators, context)?;
+            }
+            else if index >= limit && !open_length
+            {
+                if !excess
+                {
+                    let (message, label) = match &ty
+                    {
+                        Type::Array(..) => ("excess elements in array initializer".to_string(), format!("the array only has {} element(s)", limit)),
+                        Type::Record(record) if record.kind() == RecordKind::Union => (format!("excess elements in `{}` initializer", ty.name()),
+                                                                                          "a union takes one value".to_string()),
+                        _ => (format!("excess elements in `{}` initializer", ty.name()), format!("the struct only has {} member(s)", limit)),
+                    };
+                    let span = match &value
+                    {
+                        Some(value) => value.span(),
+                        None => tokens.front().map_or(Span::default(), |token| token.span),
+                    };
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, &message).with_primary(span, &label));
+                    excess = true;
+                }
+                let part = if limit > 0 { Initializer::part(&ty, limit - 1).0 } else { Type::Int };
+                Initializer::object(part, offset, value, tokens, context, &mut Vec::new())?;
+                continue;
+            }
+            let (part, part_offset) = Initializer::part(&ty, index);
+            if designators.is_empty()
+            {
+                Initializer::object(part, offset + part_offset, value, tokens, context, values)?;
+            }
+            else
+            {
+                Initializer::aggregate(part, offset + part_offset, false, Some(Started::Designated(designators)), tokens, context, values)?;
+            }
+            index += 1;
+            length = length.max(index);
+        }
+        match ty
+        {
+            Type::Array(element, 0) => return Some(Type::Array(element, length)),
+            ty => return Some(ty),
+        };
+    }
+
+    /// Parses the designators in front of an initializer in a list, if it
+    /// has any, and the `=` after them.
+    fn designators(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Vec<Designator>>
+    {
+        let mut designators = Vec::new();
+        loop
+        {
+            match tokens.front().map(|token| &token.kind)
+            {
+                Some(LexToken::OpenBracket) => {
+                    let start = validate_rule(LexToken::OpenBracket, tokens, context.diagnostics)?;
+                    let index = Expression::conditional(tokens, context)?;
+                    let end = validate_rule(LexToken::CloseBracket, tokens, context.diagnostics)?;
+                    let value = if index.ty().is_integer() { index.constant_value() } else { None };
+                    match value
+                    {
+                        Some(value) if value >= 0 => designators.push(Designator::Index(value as usize, start.to(end))),
+                        Some(value) => {
+                            context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, &format!("negative array index `{}` in initializer", value))
+                                                .with_primary(index.span(), ""));
+                            return None;
+                        },
+                        None => {
+                            context.diagnostics.emit(Diagnostic::error(E_NOT_CONSTANT, "array index in initializer is not an integer constant")
+                                                .with_primary(index.span(), "not a constant"));
+                            return None;
+                        },
+                    };
+                },
+                Some(LexToken::Dot) => {
+                    let start = tokens.pop_front().unwrap().span;
+                    match tokens.pop_front()
+                    {
+                        Some(Token{kind: LexToken::Identifier(name), span}) => designators.push(Designator::Member(name, start.to(span))),
+                        token => {
+                            report_unexpected("member name", token.as_ref(), context.diagnostics);
+                            return None;
+                        },
+                    };
+                },
+                _ => break,
+            };
+        }
+        if !designators.is_empty()
+        {
+            validate_rule(LexToken::Assignment, tokens, context.diagnostics)?;
+        }
+        return Some(designators);
+    }
+
+    /// Removes the first of `designators` and gives the index of the element
+    /// or member of `ty` it names. A member of an anonymous struct or union
+    /// is reached through that, so it is then left to be found in there.
+    fn designated(ty : &Type, designators : &mut Vec<Designator>, context : &mut ParseContext) -> Option<usize>
+    {
+        match (ty, designators.remove(0))
+        {
+            (Type::Array(element, length), Designator::Index(index, span)) => {
+                if *length != 0 && index >= *length
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, "array index in initializer exceeds array bounds")
+                                        .with_primary(span, &format!("the array only has {} element(s)", length)));
+                    return None;
+                }
+                // The index gives the length of an array declared without one.
+                if Type::Array(element.clone(), index + 1).checked_size().is_none()
+                {
+                    context.diagnostics.emit(too_large("size of array is too large", span));
+                    return None;
+                }
+                return Some(index);
+            },
+            (Type::Record(record), Designator::Member(name, span)) => {
+                for (index, member) in record.members().iter().enumerate()
+                {
+                    match (&member.name, &member.ty)
+                    {
+                        (Some(member_name), _) if *member_name == name => return Some(index),
+                        (None, Type::Record(inner)) if inner.member(&name).is_some() => {
+                            designators.insert(0, Designator::Member(name, span));
+                            return Some(index);
+                        },
+                        _ => {},
+                    };
+                }
+                context.diagnostics.emit(Diagnostic::error(E_NO_MEMBER, &format!("`{}` has no member named `{}`", record.name(), name))
+                                    .with_primary(span, "unknown member"));
+                return None;
+            },
+            (_, Designator::Index(_, span)) => {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, &format!("array index in initializer of `{}`", ty.name()))
+                                    .with_primary(span, "not an array"));
+                return None;
+            },
+            (_, Designator::Member(name, span)) => {
+                context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, &format!("member `{}` in initializer of `{}`", name, ty.name()))
+                                    .with_primary(span, "not a struct or union"));
+                return None;
+            },
+        };
+    }
+
+    /// The type and offset of element or member `index` of an array, struct
+    /// or union.
+    fn part(ty : &Type, index : usize) -> (Type, usize)
+    {
+        match ty
+        {
+            Type::Array(element, _) => return ((**element).clone(), index * element.size()),
+            Type::Record(record) => {
+                let member = &record.members()[index];
+                return (member.ty.clone(), member.offset);
+            },
+            _ => unreachable!(),
+        };
+    }
+
+    /// A value given for a part of the variable replaces those given for it
+    /// before, as does a value for a member of a union the values for the
+    /// others. The remaining values are ordered by offset.
+    fn overlay(values : Vec<(usize, Expression)>) -> Vec<(usize, Expression)>
+    {
+        let mut kept : Vec<(usize, Expression)> = Vec::new();
+        let mut end = 0;
+        for (offset, value) in values
+        {
+            let size = value.ty().size();
+            if offset < end
+            {
+                kept.retain(|(kept_offset, kept_value)| kept_offset + kept_value.ty().size() <= offset || offset + size <= *kept_offset);
+            }
+            end = end.max(offset + size);
+            kept.push((offset, value));
+        }
+        kept.sort_by_key(|(offset, _)| *offset);
+        return kept;
+    }
+
+    /// Initializes the array of characters `ty` at `offset` with the bytes
+    /// of a string literal, leaving out its terminating NUL if the array has
+    /// no room for it.
+    fn string(ty : Type, offset : usize, tokens : &mut VecDeque<Token>, context : &mut ParseContext,
+                values : &mut Vec<(usize, Expression)>) -> Option<Type>
+    {
+        let Factor::StringLiteral(bytes, span) = Factor::string_literal(tokens, context)? else { unreachable!() };
+        let Type::Array(element, length) = ty else { unreachable!() };
+        let length = if length == 0 { bytes.len() + 1 } else { length };
+        if bytes.len() > length
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_INITIALIZER, "initializer string is too long for the array")
+                                .with_primary(span, &format!("{} characters do not fit in {}", bytes.len(), length)));
+        }
+        for (index, byte) in bytes.iter().take(length).enumerate()
+        {
+            let value = Expression::Factor(Factor::Constant(Constant::Character(*byte as i8 as i32), span));
+            values.push((offset + index, convert(value, &element)));
+        }
+        return Some(Type::Array(element, length));
+    }
+
+    /// Whether the next tokens close a braced list, as `}` or `, }` do.
+    fn at_end(tokens : &VecDeque<Token>) -> bool
+    {
+        match (tokens.front().map(|token| &token.kind), tokens.get(1).map(|token| &token.kind))
+        {
+            (Some(LexToken::CloseBrace), _) | (Some(LexToken::Comma), Some(LexToken::CloseBrace)) => return true,
+            _ => return false,
+        };
+    }
+
+    /// Consumes the end of a braced list if it is next.
+    fn end(tokens : &mut VecDeque<Token>) -> bool
+    {
+        if !Initializer::at_end(tokens)
+        {
+            return false;
+        }
+        if tokens.pop_front().is_some_and(|token| token.kind == LexToken::Comma)
+        {
+            tokens.pop_front();
+        }
+        return true;
+    }
+
+    /// Every value the initializer gives.
+    pub fn values(&self) -> Vec<&Expression>
+    {
+        match self
+        {
+            Initializer::Single(value) => return vec!(value),
+            Initializer::List(values) => return values.iter().map(|(_, value)| value).collect(),
+        };
+    }
+}
+
+impl Statement
+{
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Statement>
+    {
+        match tokens.front().map(|token| &token.kind)
+        {
+            Some(LexToken::Return) => {
+                let start = validate_rule(LexToken::Return, tokens, context.diagnostics)?;
+                let value = Statement::optional_expression(LexToken::Semicolon, tokens, context)?;
+                let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                let span = start.to(end);
+                let return_type = context.return_type.clone();
+                match value
+                {
+                    // Returning the result of a `void` call is allowed, as in gcc.
+                    Some(value) if return_type == Type::Void && value.ty() != Type::Void => {
+                        context.diagnostics.emit(Diagnostic::error(E_RETURN_MISMATCH, "a function returning `void` cannot return a value")
+                                            .with_primary(value.span(), "returned here"));
+                        return Some(Statement::Return(Some(value), span));
+                    },
+                    Some(value) if return_type == Type::Void => return Some(Statement::Return(Some(value), span)),
+                    Some(value) => {
+                        check_value(&value.ty(), value.span(), context);
+                        return Some(Statement::Return(Some(convert_for_assignment(value, &return_type, context)), span));
+                    },
+                    None if return_type != Type::Void => {
+                        context.diagnostics.emit(Diagnostic::error(E_RETURN_MISMATCH, "non-void function should return a value")
+                                            .with_primary(span, &format!("expected a value of type `{}`", return_type.name())));
+                        return Some(Statement::Return(None, span));
+                    },
+                    None => return Some(Statement::Return(None, span)),
+                };
+            },
+            Some(LexToken::OpenBrace) => {
+                let (items, span) = block(tokens, context)?;
+                return Some(Statement::Compound(items, span));
+            },
+            Some(LexToken::If) => {
+                let start = validate_rule(LexToken::If, tokens, context.diagnostics)?;
+                let condition = Statement::condition(tokens, context)?;
+                let then = Statement::new(tokens, context)?;
+                let mut span = start.to(then.span());
+                // An `else` always belongs to the innermost `if` without one,
+                // which is the one still being parsed here.
+                let mut otherwise = None;
+                if tokens.front().map(|token| &token.kind) == Some(&LexToken::Else)
+                {
+                    tokens.pop_front();
+                    let statement = Statement::new(tokens, context)?;
+                    span = span.to(statement.span());
+                    otherwise = Some(Box::new(statement));
+                }
+                return Some(Statement::If(condition, Box::new(then), otherwise, span));
+            },
+            Some(LexToken::While) => {
+                let start = validate_rule(LexToken::While, tokens, context.diagnostics)?;
+                let condition = Statement::condition(tokens, context)?;
+                let body = Statement::loop_body(tokens, context)?;
+                let span = start.to(body.span());
+                return Some(Statement::While(condition, Box::new(body), span));
+            },
+            Some(LexToken::Do) => {
+                let start = validate_rule(LexToken::Do, tokens, context.diagnostics)?;
+                let body = Statement::loop_body(tokens, context)?;
+                validate_rule(LexToken::While, tokens, context.diagnostics)?;
+                validate_rule(LexToken::OpenParenth, tokens, context.diagnostics)?;
+                let condition = Expression::new(tokens, context)?;
+                check_value(&condition.ty(), condition.span(), context);
+                check_scalar(&condition.ty(), condition.span(), context);
+                let end = validate_rules(&vec!(LexToken::CloseParenth, LexToken::Semicolon), tokens, context.diagnostics)?;
+                return Some(Statement::DoWhile(Box::new(body), condition, start.to(end)));
+            },
+            Some(LexToken::For) => {
+                let start = validate_rule(LexToken::For, tokens, context.diagnostics)?;
+                validate_rule(LexToken::OpenParenth, tokens, context.diagnostics)?;
+                context.symbols.push_scope();
+                let init = match tokens.front().map(|token| &token.kind)
+                {
+                    kind if starts_declaration(kind, &context.symbols) => {
+                        let specifiers = DeclSpecifiers::new(tokens, context)?;
+                        let storage = specifiers.storage;
+                        let declarations = Declaration::list(specifiers, tokens, context)?;
+                        if storage.is_some()
+                        {
+                            context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, "a `for` loop can only declare automatic variables")
+                                                .with_primary(declarations[0].span, ""));
+                        }
+                        Some(ForInit::Declaration(declarations))
+                    },
+                    _ => {
+                        let init = Statement::optional_expression(LexToken::Semicolon, tokens, context)?;
+                        validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                        init.map(ForInit::Expression)
+                    },
+                };
+                let condition = Statement::optional_expression(LexToken::Semicolon, tokens, context)?;
+                if let Some(condition) = &condition
+                {
+                    check_value(&condition.ty(), condition.span(), context);
+                    check_scalar(&condition.ty(), condition.span(), context);
+                }
+                validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                let step = Statement::optional_expression(LexToken::CloseParenth, tokens, context)?;
+                validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+                let body = Statement::loop_body(tokens, context)?;
+                context.symbols.pop_scope();
+                let span = start.to(body.span());
+                return Some(Statement::For(init, condition, step, Box::new(body), span));
+            },
+            Some(LexToken::Break) => {
+                let start = validate_rule(LexToken::Break, tokens, context.diagnostics)?;
+                let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                if context.loop_depth == 0 && context.switches.is_empty()
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_OUTSIDE_LOOP, "`break` outside of a loop or switch")
+                                        .with_primary(start, "cannot `break` here"));
+                }
+                return Some(Statement::Break(start.to(end)));
+            },
+            Some(LexToken::Continue) => {
+                let start = validate_rule(LexToken::Continue, tokens, context.diagnostics)?;
+                let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                if context.loop_depth == 0
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_OUTSIDE_LOOP, "`continue` outside of a loop")
+                                        .with_primary(start, "cannot `continue` here"));
+                }
+                return Some(Statement::Continue(start.to(end)));
+            },
+            Some(LexToken::Switch) => {
+                let start = validate_rule(LexToken::Switch, tokens, context.diagnostics)?;
+                let value = Statement::condition(tokens, context)?;
+                if value.ty().is_pointer()
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS, "switch quantity is not an integer")
+                                        .with_primary(value.span(), &format!("has type `{}`", value.ty().name())));
+                }
+                let ty = value.ty().promote();
+                let value = convert(value, &ty);
+                context.switches.push(SwitchCases{ty, values: Vec::new(), default: None});
+                let body = Statement::new(tokens, context);
+                let cases = context.switches.pop().unwrap();
+                let body = body?;
+                let span = start.to(body.span());
+                return Some(Statement::Switch(value, Box::new(body), cases, span));
+            },
+            Some(LexToken::Case) => {
+                let start = validate_rule(LexToken::Case, tokens, context.diagnostics)?;
+                let expression = Expression::conditional(tokens, context)?;
+                validate_rule(LexToken::Colon, tokens, context.diagnostics)?;
+                let label_span = start.to(expression.span());
+                // The value is compared as the promoted controlling expression.
+                let value = expression.constant_value()
+                                .map(|value| context.switches.last().map_or(value, |cases| cases.ty.convert(value)));
+                if value.is_none()
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_NOT_CONSTANT, "case label does not reduce to an integer constant")
+                                        .with_primary(expression.span(), "not a constant"));
+                }
+                match (context.switches.last_mut(), value)
+                {
+                    (Some(_), None) => {},
+                    (Some(cases), Some(value)) => {
+                        if let Some((_, previous)) = cases.values.iter().find(|(previous, _)| *previous == value)
+                        {
+                            context.diagnostics.emit(Diagnostic::error(E_DUPLICATE_CASE, &format!("duplicate case value `{}`", value))
+                                                .with_primary(label_span, "duplicate case")
+                                                .with_secondary(*previous, "previous case is here"));
+                        }
+                        else
+                        {
+                            cases.values.push((value, label_span));
+                        }
+                    },
+                    (None, _) => context.diagnostics.emit(Diagnostic::error(E_OUTSIDE_SWITCH, "`case` label not within a switch statement")
+                                                .with_primary(label_span, "")),
+                };
+                let statement = Statement::new(tokens, context)?;
+                let span = start.to(statement.span());
+                return Some(Statement::Case(value.unwrap_or(0), Box::new(statement), span));
+            },
+            Some(LexToken::Default) => {
+                let start = validate_rule(LexToken::Default, tokens, context.diagnostics)?;
+                validate_rule(LexToken::Colon, tokens, context.diagnostics)?;
+                match context.switches.last_mut()
+                {
+                    Some(SwitchCases{default: Some(previous), ..}) => {
+                        context.diagnostics.emit(Diagnostic::error(E_DUPLICATE_CASE, "multiple default labels in one switch")
+                                            .with_primary(start, "second default label")
+                                            .with_secondary(*previous, "first default label is here"));
+                    },
+                    Some(cases) => cases.default = Some(start),
+                    None => context.diagnostics.emit(Diagnostic::error(E_OUTSIDE_SWITCH, "`default` label not within a switch statement")
+                                                .with_primary(start, "")),
+                };
+                let statement = Statement::new(tokens, context)?;
+                let span = start.to(statement.span());
+                return Some(Statement::Default(Box::new(statement), span));
+            },
+            Some(LexToken::Semicolon) => {
+                let span = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                return Some(Statement::Null(span));
+            },
+            Some(LexToken::Goto) => {
+                let start = validate_rule(LexToken::Goto, tokens, context.diagnostics)?;
+                let (name, name_span) = match tokens.pop_front()
+                {
+                    Some(Token{kind: LexToken::Identifier(name), span}) => (name, span),
+                    token => {
+                        report_unexpected("label name", token.as_ref(), context.diagnostics);
+                        return None;
+                    },
+                };
+                let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                context.gotos.push((name.clone(), name_span));
+                return Some(Statement::Goto(name, start.to(end)));
+            },
+            Some(LexToken::Identifier(_)) if tokens.get(1).map(|token| &token.kind) == Some(&LexToken::Colon) => {
+                let Some(Token{kind: LexToken::Identifier(name), span: start}) = tokens.pop_front() else { unreachable!() };
+                tokens.pop_front();
+                if let Some(previous) = context.labels.get(&name)
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_DUPLICATE_LABEL, &format!("duplicate label `{}`", name))
+                                        .with_primary(start, "redefined here")
+                                        .with_secondary(*previous, "first defined here"));
+                }
+                else
+                {
+                    context.labels.insert(name.clone(), start);
+                }
+                let statement = Statement::new(tokens, context)?;
+                let span = start.to(statement.span());
+                return Some(Statement::Labeled(name, Box::new(statement), span));
+            },
+            _ => {
+                let expression = Expression::new(tokens, context)?;
+                let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+                let span = expression.span().to(end);
+                return Some(Statement::Expression(expression, span));
+            },
+        };
+    }
+
+    pub fn span(&self) -> Span
+    {
+        match self
+        {
+            Statement::Return(_, span) => return *span,
+            Statement::Expression(_, span) => return *span,
+            Statement::Compound(_, span) => return *span,
+            Statement::If(_, _, _, span) => return *span,
+            Statement::While(_, _, span) => return *span,
+            Statement::DoWhile(_, _, span) => return *span,
+            Statement::For(_, _, _, _, span) => return *span,
+            Statement::Break(span) => return *span,
+            Statement::Continue(span) => return *span,
+            Statement::Null(span) => return *span,
+            Statement::Switch(_, _, _, span) => return *span,
+            Statement::Case(_, _, span) => return *span,
+            Statement::Default(_, span) => return *span,
+            Statement::Labeled(_, _, span) => return *span,
+            Statement::Goto(_, span) => return *span,
+        };
+    }
+
+    /// Parses the body of a loop, in which `break` and `continue` are allowed.
+    fn loop_body(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Statement>
+    {
+        context.loop_depth += 1;
+        let body = Statement::new(tokens, context);
+        context.loop_depth -= 1;
+        return body;
+    }
+
+    /// Parses a parenthesized controlling expression.
+    fn condition(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Expression>
+    {
+        validate_rule(LexToken::OpenParenth, tokens, context.diagnostics)?;
+        let condition = Expression::new(tokens, context)?;
+        validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+        check_value(&condition.ty(), condition.span(), context);
+        check_scalar(&condition.ty(), condition.span(), context);
+        return Some(condition);
+    }
+
+    /// Parses an expression unless the next token is `end`.
+    fn optional_expression(end : LexToken, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Option<Expression>>
+    {
+        if tokens.front().map(|token| &token.kind) == Some(&end)
+        {
+            return Some(None);
+        }
+        return Some(Some(Expression::new(tokens, context)?));
+    }
+}
+
+impl BlockItem
+{
+    /// Parses a statement, or a declaration with an item for each of its
+    /// declarators.
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Vec<BlockItem>>
+    {
+        // Labels have their own namespace, so `T:` is a label even if `T` is
+        // a typedef name.
+        let is_label = matches!(tokens.front().map(|token| &token.kind), Some(LexToken::Identifier(_)))
+                        && tokens.get(1).map(|token| &token.kind) == Some(&LexToken::Colon);
+        if is_label || !starts_declaration(tokens.front().map(|token| &token.kind), &context.symbols)
+        {
+            return Some(vec!(BlockItem::Statement(Statement::new(tokens, context)?)));
+        }
+        let specifiers = DeclSpecifiers::new(tokens, context)?;
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::Semicolon)
+        {
+            let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+            return Some(vec!(BlockItem::TypeDeclaration(specifiers.span.to(end))));
+        }
+        if specifiers.storage == Some(StorageClass::Typedef)
+        {
+            return Some(vec!(BlockItem::TypeDeclaration(Declaration::typedef(specifiers, tokens, context)?)));
+        }
+        return Some(Declaration::list(specifiers, tokens, context)?.into_iter().map(BlockItem::Declaration).collect());
+    }
+
+    pub fn span(&self) -> Span
+    {
+        match self
+        {
+            BlockItem::Statement(statement) => return statement.span(),
+            BlockItem::Declaration(declaration) => return declaration.span,
+            BlockItem::TypeDeclaration(span) => return *span,
+        };
+    }
+}
+
+/// Reports every `goto` of the function just parsed whose label it does not
+/// define, and forgets its labels.
+fn check_labels(context : &mut ParseContext)
+{
+    for (name, span) in context.gotos.drain(..)
+    {
+        if !context.labels.contains_key(&name)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_UNDEFINED_LABEL, &format!("use of undeclared label `{}`", name))
+                                .with_primary(span, "not defined in this function"));
+        }
+    }
+    context.labels.clear();
+}
+
+/// Parses `{ block-item* }` in a new scope, so its declarations shadow outer
+/// ones and go out of scope at the closing brace.
+fn block(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<(Vec<BlockItem>, Span)>
+{
+    context.symbols.push_scope();
+    let block = block_in_scope(tokens, context);
+    context.symbols.pop_scope();
+    return block;
+}
+
+/// Parses `{ block-item* }` in the current scope.
+fn block_in_scope(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<(Vec<BlockItem>, Span)>
+{
+    let start = validate_rule(LexToken::OpenBrace, tokens, context.diagnostics)?;
+    let mut items = Vec::new();
+    while tokens.front().is_some_and(|token| token.kind != LexToken::CloseBrace)
+    {
+        items.extend(BlockItem::new(tokens, context)?);
+    }
+    let end = validate_rule(LexToken::CloseBrace, tokens, context.diagnostics)?;
+    return Some((items, start.to(end)));
+}
+
+
+impl FunctionDecl 
+{
+    /// Parses the rest of a function declaration, once its specifiers and
+    /// name have been read.
+    pub fn new(specifiers : DeclSpecifiers, return_type : Type, func_name : String, name_span : Span, tokens : &mut VecDeque<Token>,
+                context : &mut ParseContext) -> Option<FunctionDecl>
+    {
+        if let Some(symbol) = context.symbols.lookup(&func_name).filter(|symbol| symbol.id().is_none())
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", func_name))
+                                .with_primary(name_span, "declared here as a function")
+                                .with_secondary(symbol.span, &format!("previously declared as {}", symbol_kind(symbol))));
+        }
+        // The parameters are in scope in the body, and share its outermost
+        // block: `int f(int a) { int a; }` redeclares `a`.
+        context.symbols.push_scope();
+        let declaration = FunctionDecl::with_params(specifiers, return_type, func_name, name_span, tokens, context);
+        context.symbols.pop_scope();
+        return declaration;
+    }
+
+    fn with_params(specifiers : DeclSpecifiers, return_type : Type, func_name : String, name_span : Span, tokens : &mut VecDeque<Token>,
+                    context : &mut ParseContext) -> Option<FunctionDecl>
+    {
+        let start = specifiers.span;
+        let storage = specifiers.storage;
+        validate_rule(LexToken::OpenParenth, tokens, context.diagnostics)?;
+        // As in C89 and C99, an empty list leaves the parameters unspecified,
+        // so calls are not checked against them, while `(void)` declares none.
+        let mut params = Vec::new();
+        let specified = tokens.front().map(|token| &token.kind) != Some(&LexToken::CloseParenth);
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::Void)
+            && tokens.get(1).map(|token| &token.kind) == Some(&LexToken::CloseParenth)
+        {
+            tokens.pop_front();
+        }
+        else if tokens.front().map(|token| &token.kind) != Some(&LexToken::CloseParenth)
+        {
+            params.push(Parameter::new(tokens, context)?);
+            while tokens.front().map(|token| &token.kind) == Some(&LexToken::Comma)
+            {
+                tokens.pop_front();
+                params.push(Parameter::new(tokens, context)?);
+            }
+        }
+        validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+
+        let is_definition = tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBrace);
+        let param_types = if specified { Some(params.iter().map(|param| param.ty.clone()).collect()) } else { None };
+        FunctionDecl::declare(&func_name, name_span, &return_type, param_types, storage, is_definition, context);
+        if !is_definition
+        {
+            let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+            return Some(FunctionDecl{name: func_name, return_type, params, storage, body: None, span: start.to(end)});
+        }
+
+        for param in params.iter().filter(|param| param.name.is_none())
+        {
+            context.diagnostics.emit(Diagnostic::error(E_EXPECTED_TOKEN, "parameter name omitted")
+                                .with_primary(param.span, "expected a parameter name"));
+        }
+        context.return_type = return_type.clone();
+        context.locals_size = 0;
+        let (func_body, body_span) = block_in_scope(tokens, context)?;
+        check_labels(context);
+        return Some(FunctionDecl{name: func_name, return_type, params, storage, body: Some(func_body), span: start.to(body_span)});
+    }
+
+    /// Records a declaration of a function, checking it against earlier ones.
+    fn declare(name : &str, span : Span, return_type : &Type, params : Option<Vec<Type>>, storage : Option<StorageClass>, is_definition : bool,
+                context : &mut ParseContext)
+    {
+        if let Some(global) = context.globals.get(name)
+        {
+            context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("`{}` redeclared as a different kind of symbol", name))
+                                .with_primary(span, "declared here as a function")
+                                .with_secondary(global.span, "previously declared as a variable"));
+        }
+        let Some(previous) = context.functions.get_mut(name) else {
+            let definition = if is_definition { Some(span) } else { None };
+            let internal = storage == Some(StorageClass::Static);
+            context.functions.insert(name.to_string(), FunctionSymbol{return_type: return_type.clone(), params, span, definition, internal});
+            return;
+        };
+        // Unlike for variables, a function declared without a storage class
+        // keeps the linkage of an earlier declaration.
+        if storage == Some(StorageClass::Static) && !previous.internal
+        {
+            context.diagnostics.emit(linkage_conflict(name, span, true, previous.span));
+        }
+        // Parameters left unspecified agree with any, and a later prototype
+        // gives them to the calls that follow it.
+        let params_agree = previous.params.is_none() || params.is_none() || previous.params == params;
+        if !params_agree || previous.return_type != *return_type
+        {
+            context.diagnostics.emit(Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("conflicting types for `{}`", name))
+                                .with_primary(span, &format!("declared here as `{}`", signature(return_type, params.as_deref())))
+                                .with_secondary(previous.span, &format!("previously declared as `{}`",
+                                                                        signature(&previous.return_type, previous.params.as_deref()))));
+        }
+        else if previous.params.is_none()
+        {
+            previous.params = params;
+        }
+        if is_definition
+        {
+            match previous.definition
+            {
+                Some(definition) => context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redefinition of `{}`", name))
+                                                        .with_primary(span, "redefined here")
+                                                        .with_secondary(definition, "previous definition is here")),
+                None => previous.definition = Some(span),
+            };
+        }
+    }
+}
+
+impl Parameter
+{
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Parameter>
+    {
+        let specifiers = DeclSpecifiers::new(tokens, context)?;
+        let start = specifiers.span;
+        let declarator = Declarator::new(true, tokens, context)?;
+        // A parameter declared as an array is a pointer to its first element,
+        // since that is what an array argument decays to.
+        let ty = match declarator.ty(specifiers.ty, context)
+        {
+            Type::Array(element, _) => Type::pointer_to(*element),
+            ty => ty,
+        };
+        if specifiers.storage.is_some()
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, "storage class specified for a parameter")
+                                .with_primary(start, ""));
+        }
+        if ty == Type::Void
+        {
+            context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, "parameter declared `void`")
+                                .with_primary(start, "parameters cannot have type `void`"));
+        }
+        let Some((name, name_span)) = declarator.name() else {
+            return Some(Parameter{name: None, id: None, ty, span: start});
+        };
+        let id = match context.symbols.declare(&name, ty.clone(), name_span)
+        {
+            Ok(id) => id,
+            Err(previous) => {
+                context.diagnostics.emit(Diagnostic::error(E_REDECLARED, &format!("redefinition of parameter `{}`", name))
+                                    .with_primary(name_span, "redeclared here")
+                                    .with_secondary(previous.span, "previous declaration is here"));
+                previous.id().unwrap_or_else(|| context.symbols.new_id())
+            },
+        };
+        return Some(Parameter{name: Some(name), id: Some(id), ty, span: start.to(name_span)});
+    }
+}
+
+impl ExternalDeclaration
+{
+    /// Parses a function, or a declaration with an external declaration for
+    /// each of its declarators.
+    pub fn new(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Vec<ExternalDeclaration>>
+    {
+        let specifiers = DeclSpecifiers::new(tokens, context)?;
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::Semicolon)
+        {
+            let end = validate_rule(LexToken::Semicolon, tokens, context.diagnostics)?;
+            return Some(vec!(ExternalDeclaration::TypeDeclaration(specifiers.span.to(end))));
+        }
+        if specifiers.storage == Some(StorageClass::Typedef)
+        {
+            return Some(vec!(ExternalDeclaration::TypeDeclaration(Declaration::typedef(specifiers, tokens, context)?)));
+        }
+        let declarator = Declarator::new(false, tokens, context)?;
+        let ty = declarator.ty(specifiers.ty.clone(), context);
+        let (name, name_span) = declarator.name()?;
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenParenth)
+        {
+            return Some(vec!(ExternalDeclaration::Function(FunctionDecl::new(specifiers, ty, name, name_span, tokens, context)?)));
+        }
+        let first = Declaration::file_scope(specifiers.clone(), ty, name, name_span, tokens, context)?;
+        let declarations = Declaration::rest_of_list(first, specifiers, tokens, context)?;
+        return Some(declarations.into_iter().map(ExternalDeclaration::Variable).collect());
+    }
+
+    pub fn span(&self) -> Span
+    {
+        match self
+        {
+            ExternalDeclaration::Function(function) => return function.span,
+            ExternalDeclaration::Variable(declaration) => return declaration.span,
+            ExternalDeclaration::TypeDeclaration(span) => return *span,
+        };
+    }
+}
+
+impl Program 
+{
+    pub fn new(tokens : &mut VecDeque<Token>, diagnostics : &mut Diagnostics) -> Option<Program>
+    {
+        let mut context = ParseContext::new(diagnostics);
+        let context = &mut context;
+        let mut declarations = Vec::new();
+        while let Some(token) = tokens.front()
+        {
+            if !starts_declaration(Some(&token.kind), &context.symbols)
+            {
+                context.diagnostics.emit(Diagnostic::error(E_TRAILING_TOKENS,
+                                    &format!("expected a declaration, found {}", describe_token(Some(&token.kind))))
+                                    .with_primary(token.span, "unexpected token"));
+                return None;
+            }
+            declarations.extend(ExternalDeclaration::new(tokens, context)?);
+        }
+        return Some(Program{declarations});
+    }
+
+    /// The definition of the function `name`, if the program has one.
+    pub fn function(&self, name : &str) -> Option<&FunctionDecl>
+    {
+        return self.declarations.iter().find_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Function(function) if function.name == name && function.body.is_some() => Some(function),
+            _ => None,
+        });
+    }
+}
+
+
+/// Whether a declaration, rather than a statement, starts with `token`.
+fn starts_declaration(token : Option<&LexToken>, symbols : &SymbolTable) -> bool
+{
+    return is_type_specifier(token, symbols) || matches!(token, Some(LexToken::Static | LexToken::Extern | LexToken::Typedef));
+}
+
+/// Whether `token` is a type specifier, which an identifier is when it is a
+/// typedef name in scope.
+fn is_type_specifier(token : Option<&LexToken>, symbols : &SymbolTable) -> bool
+{
+    match token
+    {
+        Some(LexToken::Identifier(name)) => return symbols.typedef(name).is_some(),
+        _ => return matches!(token, Some(LexToken::Int | LexToken::Char | LexToken::Short | LexToken::Long | LexToken::Signed
+                                        | LexToken::Unsigned | LexToken::Bool | LexToken::Void | LexToken::Struct | LexToken::Union
+                                        | LexToken::Enum)),
+    };
+}
+
+/// What a symbol is, as diagnostics say it.
+fn symbol_kind(symbol : &Symbol) -> &'static str
+{
+    match symbol.kind
+    {
+        SymbolKind::Variable(_) => return "a variable",
+        SymbolKind::Enumerator(_) => return "an enumerator",
+        SymbolKind::Typedef => return "a type",
+    };
+}
+
+/// How the type declared with the tag `tag` is spelled, where enum tags
+/// declare `int`.
+fn tag_name(ty : &Type, tag : &str) -> String
+{
+    if ty.is_record()
+    {
+        return ty.name();
+    }
+    return format!("enum {}", tag);
+}
+
+/// Parses the type in a cast, which has no storage class.
+fn type_name(tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Type>
+{
+    let specifiers = DeclSpecifiers::new(tokens, context)?;
+    if specifiers.storage.is_some()
+    {
+        context.diagnostics.emit(Diagnostic::error(E_INVALID_SPECIFIERS, "storage class in a type name")
+                            .with_primary(specifiers.span, ""));
+    }
+    let declarator = Declarator::new(true, tokens, context)?;
+    let ty = declarator.ty(specifiers.ty, context);
+    if matches!(ty, Type::Array(_, 0))
+    {
+        context.diagnostics.emit(Diagnostic::error(E_INVALID_ARRAY_SIZE, "array length missing")
+                            .with_primary(specifiers.span, "a type name has to give every length"));
+    }
+    return Some(ty);
+}
+
+/// `[index]` or `.member` in front of an initializer in a list, naming the
+/// element or member it initializes.
+enum Designator
+{
+    Index(usize, Span),
+    Member(String, Span),
+}
+
+/// The first initializer of a list whose braces are left out, when it has
+/// been parsed in part already.
+enum Started
+{
+    /// The designators that remain to reach the part it initializes.
+    Designated(Vec<Designator>),
+    Value(Expression),
+}
+
+/// What a declaration says about one name besides its specifiers, which C
+/// writes inside out: `*a[3]` declares an array of three pointers, and
+/// `(*a)[3]` a pointer to an array of three.
+enum Declarator
+{
+    /// The name declared, which type names and parameters of a prototype
+    /// leave out.
+    Name(Option<(String, Span)>),
+    Pointer(Box<Declarator>),
+    /// An array of the given length, which is 0 when the declaration leaves
+    /// it for the initializer to give.
+    Array(Box<Declarator>, usize, Span),
+}
+
+impl Declarator
+{
+    /// Parses a declarator, whose name may be left out if `is_abstract`.
+    fn new(is_abstract : bool, tokens : &mut VecDeque<Token>, context : &mut ParseContext) -> Option<Declarator>
+    {
+        if tokens.front().map(|token| &token.kind) == Some(&LexToken::Multiplication)
+        {
+            tokens.pop_front();
+            return Some(Declarator::Pointer(Box::new(Declarator::new(is_abstract, tokens, context)?)));
+        }
+        let mut declarator = match tokens.front().map(|token| &token.kind)
+        {
+            Some(LexToken::OpenParenth) if tokens.get(1).map(|token| &token.kind) == Some(&LexToken::Multiplication) => {
+                tokens.pop_front();
+                let declarator = Declarator::new(is_abstract, tokens, context)?;
+                validate_rule(LexToken::CloseParenth, tokens, context.diagnostics)?;
+                declarator
+            },
+            Some(LexToken::Identifier(_)) => {
+                let Some(Token{kind: LexToken::Identifier(name), span}) = tokens.pop_front() else { unreachable!() };
+                Declarator::Name(Some((name, span)))
+            },
+            _ if is_abstract => Declarator::Name(None),
+            _ => {
+                report_unexpected("identifier", tokens.front(), context.diagnostics);
+                return None;
+            },
+        };
+        while tokens.front().map(|token| &token.kind) == Some(&LexToken::OpenBracket)
+        {
+            let start = validate_rule(LexToken::OpenBracket, tokens, context.diagnostics)?;
+            let length = match tokens.front().map(|token| &token.kind)
+            {
+                Some(LexToken::CloseBracket) => None,
+                _ => Some(Expression::conditional(tokens, context)?),
+            };
+            let end = validate_rule(LexToken::CloseBracket, tokens, context.diagnostics)?;
+            let span = start.to(end);
+            let length = match length
+            {
+                Some(length) => array_length(&length, context),
+                // Only the outermost length may be left out, since the
+                // elements need a size.
+                None if matches!(declarator, Declarator::Name(_)) => 0,
+                None => {
+                    context.diagnostics.emit(Diagnostic::error(E_INVALID_ARRAY_SIZE, "array length missing")
+                                        .with_primary(span, "only the first length of an array may be left out"));
+                    1
+                },
+            };
+            declarator = Declarator::Array(Box::new(declarator), length, span);
+        }
+        return Some(declarator);
+    }
+
+    fn name(&self) -> Option<(String, Span)>
+    {
+        match self
+        {
+            Declarator::Name(name) => return name.clone(),
+            Declarator::Pointer(declarator) | Declarator::Array(declarator, _, _) => return declarator.name(),
+        };
+    }
+
+    /// The type declared for the name, given the type of the specifiers.
+    fn ty(&self, base : Type, context : &mut ParseContext) -> Type
+    {
+        match self
+        {
+            Declarator::Name(_) => return base,
+            Declarator::Pointer(declarator) => return declarator.ty(Type::pointer_to(base), context),
+            Declarator::Array(declarator, length, span) => {
+                if !base.is_complete()
+                {
+                    context.diagnostics.emit(Diagnostic::error(E_INCOMPLETE_TYPE, &format!("array of incomplete type `{}`", base.name()))
+                                        .with_primary(*span, "the elements need a size"));
+                }
+                let mut ty = Type::array_of(base, *length);
+                if ty.checked_size().is_none()
+                {
+                    context.diagnostics.emit(too_large("size of array is too large", *span));
+                    let Type::Array(element, _) = ty else { unreachable!() };
+                    ty = Type::Array(element, 1);
+                }
+                return declarator.ty(ty, context);
+            },
+        };
+    }
+}
+
+/// The length of an array given by `length`, which has to be a positive
+/// integer constant. An invalid length is reported and taken as 1.
+fn array_length(length : &Expression, context : &mut ParseContext) -> usize
+{
+    let value = if length.ty().is_integer() { length.constant_value() } else { None };
+    match value
+    {
+        Some(value) if value > 0 && value as usize <= MAX_OBJECT_SIZE => return value as usize,
+        Some(value) if value > 0 => context.diagnostics.emit(too_large("size of array is too large", length.span())),
+        Some(value) => {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_ARRAY_SIZE, &format!("invalid array length `{}`", value))
+                                .with_primary(length.span(), "the length of an array must be positive"));
+        },
+        None => {
+            context.diagnostics.emit(Diagnostic::error(E_INVALID_ARRAY_SIZE, "array length is not an integer constant")
+                                .with_primary(length.span(), "not a constant"));
+        },
+    };
+    return 1;
+}
+
+/// Largest total size in bytes of the automatic variables of a function, as
+/// in gcc, which leaves room in the frame for what the code keeps there.
+const MAX_LOCALS_SIZE : usize = MAX_OBJECT_SIZE - 255;
+
+/// The error for a type whose objects would be larger than `MAX_OBJECT_SIZE`.
+fn too_large(message : &str, span : Span) -> Diagnostic
+{
+    return Diagnostic::error(E_TOO_LARGE, message)
+            .with_primary(span, &format!("objects may be at most {} bytes", MAX_OBJECT_SIZE));
+}
+
+/// Spelling of a function type in diagnostics, such as `int(char, long)`.
+fn signature(return_type : &Type, params : Option<&[Type]>) -> String
+{
+    match params
+    {
+        None => return format!("{}()", return_type.name()),
+        Some([]) => return format!("{}(void)", return_type.name()),
+        Some(params) => {
+            let params : Vec<String> = params.iter().map(|param| param.name()).collect();
+            return format!("{}({})", return_type.name(), params.join(", "));
+        },
+    };
+}
+
+/// Converts an array to the address of its first element, which is what C
+/// makes of an array used as a value.
+fn decay(expression : Expression) -> Expression
+{
+    match expression.ty()
+    {
+        Type::Array(element, _) => return Expression::Convert(Type::pointer_to(*element), Box::new(expression)),
+        _ => return expression,
+    };
+}
+
+/// `decay` for the operand of a unary operator.
+fn decay_factor(factor : Factor) -> Factor
+{
+    if !factor.ty().is_array()
+    {
+        return factor;
+    }
+    let span = factor.span();
+    return Factor::Expr(Box::new(decay(Expression::Factor(factor))), span);
+}
+
+/// Converts `expression` to `ty`, unless it has that type already.
+fn convert(expression : Expression, ty : &Type) -> Expression
+{
+    if expression.ty() == *ty
+    {
+        return expression;
+    }
+    return Expression::Convert(ty.clone(), Box::new(expression));
+}
+
+/// Converts `value` to `ty` as if by assignment, which C only allows between
+/// arithmetic types, between pointers to the same type or to `void`, from a
+/// null pointer constant to a pointer and from a pointer to `_Bool`.
+fn convert_for_assignment(value : Expression, ty : &Type, context : &mut ParseContext) -> Expression
+{
+    let from = value.ty();
+    let allowed = match (from.pointee(), ty.pointee())
+    {
+        // A struct or union is only assigned from one of its own type.
+        _ if from.is_record() || ty.is_record() => from == *ty,
+        (None, None) => true,
+        (Some(_), None) => *ty == Type::Bool,
+        (None, Some(_)) => value.is_null_pointer_constant() || from == Type::Void,
+        (Some(from), Some(to)) => from == to || *from == Type::Void || *to == Type::Void,
+    };
+    if !allowed
+    {
+        // A cast cannot help with a struct or union.
+        let remedy = if from.is_record() || ty.is_record() { "" } else { " without a cast" };
+        context.diagnostics.emit(Diagnostic::error(E_INCOMPATIBLE_TYPES, &format!("cannot convert `{}` to `{}`{}", from.name(), ty.name(), remedy))
+                            .with_primary(value.span(), &format!("has type `{}`", from.name())));
+    }
+    return convert(value, ty);
+}
+
+/// Whether the next token is a string literal initializing `ty`, being an
+/// array of characters.
+fn is_string_for(ty : &Type, tokens : &VecDeque<Token>) -> bool
+{
+    return matches!(ty.element(), Some(Type::Char | Type::SignedChar | Type::UnsignedChar))
+            && matches!(tokens.front().map(|token| &token.kind), Some(LexToken::StringLiteral(_)));
+}
+
+/// Whether `value` is known before the program runs, as the initializer of a
+/// variable with static storage has to be: an integer constant or the address
+/// of an object with static storage.
+fn is_static_constant(value : &Expression, context : &ParseContext) -> bool
+{
+    if value.constant_value().is_some()
+    {
+        return true;
+    }
+    match value.address_constant()
+    {
+        Some((Factor::Variable(variable, _), _)) => return context.statics.contains(&variable.id),
+        Some((Factor::StringLiteral(..), _)) => return true,
+        _ => return false,
+    };
+}
+
+/// Reports the use of a value where the expression has none, being a call
+/// of a function that returns `void`.
+fn check_value(ty : &Type, span : Span, context : &mut ParseContext)
+{
+    if *ty == Type::Void
+    {
+        context.diagnostics.emit(Diagnostic::error(E_VOID_VALUE, "void value not ignored as it ought to be")
+                            .with_primary(span, "this expression has type `void`"));
+    }
+}
+
+/// Reports a struct or union used where a single value is needed, as a
+/// condition is.
+fn check_scalar(ty : &Type, span : Span, context : &mut ParseContext)
+{
+    if ty.is_record()
+    {
+        context.diagnostics.emit(Diagnostic::error(E_INVALID_OPERANDS, &format!("used `{}` where a scalar is required", ty.name()))
+                            .with_primary(span, "not a number or a pointer"));
+    }
+}
+
+fn linkage_conflict(name : &str, span : Span, internal : bool, previous : Span) -> Diagnostic
+{
+    let (linkage, previous_linkage) = if internal { ("internal", "external") } else { ("external", "internal") };
+    return Diagnostic::error(E_CONFLICTING_DECLARATION, &format!("`{}` declared with {} linkage after {} linkage", name, linkage, previous_linkage))
+                .with_primary(span, &format!("{} linkage here", linkage))
+                .with_secondary(previous, "previous declaration is here");
+}
+
+/// Checks that the operand of the assignment, increment or address-of
+/// operator `oper` is an lvalue, reporting it otherwise. Only the address of
+/// an array can be taken.
+fn lvalue(target : Expression, oper : &Token, context : &mut ParseContext) -> Option<Factor>
+{
+    let address_of = oper.kind == LexToken::BitwiseAnd;
+    match target
+    {
+        Expression::Factor(factor) if factor.is_lvalue() && (address_of || !factor.ty().is_array()) => return Some(factor),
+        _ => {
+            let spelling = oper.kind.to_str().unwrap_or_default();
+            let is_array = matches!(&target, Expression::Convert(_, array) if array.ty().is_array()) || target.ty().is_array();
+            let label = if is_array { "arrays cannot be assigned" } else { "expression is not an lvalue" };
+            context.diagnostics.emit(Diagnostic::error(E_NOT_LVALUE, &format!("invalid operand of `{}`", spelling))
+                                .with_primary(target.span(), label));
+            return None;
+        },
+    };
+}
+
+fn invalid_operands(oper : &Token, lhs : &Type, rhs : &Type, span : Span) -> Diagnostic
+{
+    let spelling = oper.kind.to_str().unwrap_or_default();
+    return Diagnostic::error(E_INVALID_OPERANDS, &format!("invalid operands to binary `{}` (have `{}` and `{}`)", spelling, lhs.name(), rhs.name()))
+                .with_primary(span, "");
+}
+
+fn describe_token(token : Option<&LexToken>) -> String
+{
+    match token
+    {
+        Some(token) => match token.to_str()
+        {
+            Some(text) => return format!("`{}`", text),
+            None => return String::from("unrecognised token"),
+        },
+        None => return String::from("end of file"),
+    };
+}
+
+fn report_literal_warnings(warnings : &[LiteralWarning], span : Span, diagnostics : &mut Diagnostics)
+{
+    for warning in warnings
+    {
+        diagnostics.emit(Diagnostic::warning(W_LITERAL, &warning.message()).with_primary(span, ""));
+    }
+}
+
+fn report_unexpected(expected : &str, found : Option<&Token>, diagnostics : &mut Diagnostics)
+{
+    match found
+    {
+        Some(token) => diagnostics.emit(Diagnostic::error(E_EXPECTED_TOKEN,
+                            &format!("expected {}, found {}", expected, describe_token(Some(&token.kind))))
+                            .with_primary(token.span, &format!("expected {}", expected))),
+        None => diagnostics.emit(Diagnostic::error(E_UNEXPECTED_EOF,
+                            &format!("expected {}, found end of file", expected))),
+    };
+}
+
+fn validate_rules(req_tokens : &Vec<LexToken>, tokens : &mut VecDeque<Token>, diagnostics : &mut Diagnostics) -> Option<Span>
+{
+    let available = tokens.len().min(req_tokens.len());
+    let mut found = tokens.drain(0..available);
+    let mut span : Option<Span> = None;
+    for req_token in req_tokens
+    {
+        let token = found.next();
+        match &token
+        {
+            Some(token) if token.kind == *req_token => {
+                span = Some(span.map_or(token.span, |span| span.to(token.span)));
+            },
+            _ => {
+                report_unexpected(&describe_token(Some(req_token)), token.as_ref(), diagnostics);
+                return None;
+            },
+        }
+    }
+    return span;
+}
+
+/// Consumes the next token, returning its span if it is `req_token`.
+fn validate_rule(req_token : LexToken, tokens : &mut VecDeque<Token>, diagnostics : &mut Diagnostics) -> Option<Span>
+{
+    let token = tokens.pop_front();
+    match &token
+    {
+        Some(token) if token.kind == req_token => return Some(token.span),
+        _ => {
+            report_unexpected(&describe_token(Some(&req_token)), token.as_ref(), diagnostics);
+            return None;
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source_map::{FileId, SourceMap};
+
+    fn parse(source : &str) -> (Option<Program>, Diagnostics)
+    {
+        let mut diagnostics = Diagnostics::new();
+        let mut tokens = lex_str("test.c", source, &mut SourceMap::new(), &mut diagnostics);
+        let program = Program::new(&mut tokens, &mut diagnostics);
+        return (program, diagnostics);
+    }
+
+    fn main_body(program : Option<Program>) -> Vec<BlockItem>
+    {
+        return program.unwrap().declarations.into_iter()
+                    .find_map(|declaration| match declaration
+                    {
+                        ExternalDeclaration::Function(function) if function.name == "main" => function.body,
+                        _ => None,
+                    })
+                    .unwrap();
+    }
+
+    fn error_codes(source : &str) -> Vec<&'static str>
+    {
+        let (_, diagnostics) = parse(source);
+        return diagnostics.iter().map(|diag| diag.code).collect();
+    }
+
+    fn variable(factor : &Factor) -> &Variable
+    {
+        let Factor::Variable(variable, _) = factor else { panic!("expected a variable, got {:?}", factor) };
+        return variable;
+    }
+
+    fn make_deq(tokens : Vec<LexToken>) -> VecDeque<Token>{
+        tokens.into_iter().enumerate()
+            .map(|(i, kind)| Token{kind, span: Span::new(FileId(0), i, i + 1)})
+            .collect()
+    }
+
+    #[test]
+    fn validate_rule_true()
+    {
+        let mut deq = make_deq(vec!(LexToken::Semicolon));
+        assert!(validate_rule(LexToken::Semicolon, &mut deq, &mut Diagnostics::new()).is_some());
+        assert_eq!(0,deq.len());
+    }
+
+    #[test]
+    fn validate_rule_false()
+    {
+        let mut deq = make_deq(vec!(LexToken::CloseBrace));
+        let mut diagnostics = Diagnostics::new();
+        assert!(validate_rule(LexToken::Semicolon, &mut deq, &mut diagnostics).is_none());
+        assert_eq!(0,deq.len());
+        assert_eq!(diagnostics.iter().next().unwrap().message, "expected `;`, found `}`");
+    }
+
+    #[test]
+    fn validate_rules_true()
+    {
+        let mut deq = make_deq(vec!(LexToken::Int
+                        , LexToken::Identifier(String::from("main"))
+                        , LexToken::OpenParenth
+                        , LexToken::CloseParenth));
         let test = vec!(LexToken::Int
         , LexToken::Identifier(String::from("main"))
         , LexToken::OpenParenth
         , LexToken::CloseParenth);
-        assert_eq!(true, validate_rules(&test, &mut deq));
+        assert_eq!(validate_rules(&test, &mut deq, &mut Diagnostics::new()), Some(Span::new(FileId(0), 0, 4)));
         assert_eq!(0,deq.len());
     }
 
@@ -384,7 +3408,669 @@ mod tests {
         , LexToken::OpenParenth
         , LexToken::CloseParenth);
 
-        assert_eq!(false, validate_rules(&test, &mut deq));
+        assert!(validate_rules(&test, &mut deq, &mut Diagnostics::new()).is_none());
         assert_eq!(0,deq.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn validate_rule_end_of_file()
+    {
+        let mut deq = make_deq(vec!());
+        let mut diagnostics = Diagnostics::new();
+        assert!(validate_rule(LexToken::Semicolon, &mut deq, &mut diagnostics).is_none());
+        let diag = diagnostics.iter().next().unwrap();
+        assert_eq!(diag.code, E_UNEXPECTED_EOF);
+        assert_eq!(diag.message, "expected `;`, found end of file");
+    }
+
+    #[test]
+    fn program_reports_trailing_tokens()
+    {
+        let mut deq = make_deq(vec!(LexToken::Int
+            , LexToken::Identifier(String::from("main"))
+            , LexToken::OpenParenth
+            , LexToken::CloseParenth
+            , LexToken::OpenBrace
+            , LexToken::Return
+            , LexToken::IntLiteral(String::from("2"))
+            , LexToken::Semicolon
+            , LexToken::CloseBrace
+            , LexToken::CloseBrace));
+        let mut diagnostics = Diagnostics::new();
+        assert!(Program::new(&mut deq, &mut diagnostics).is_none());
+        assert_eq!(diagnostics.error_count(), 1);
+        assert_eq!(diagnostics.iter().next().unwrap().code, E_TRAILING_TOKENS);
+    }
+
+    #[test]
+    fn spans_cover_children()
+    {
+        // return ( 1 + 2 ) * 3 ;
+        let mut deq = make_deq(vec!(LexToken::Return
+            , LexToken::OpenParenth
+            , LexToken::IntLiteral(String::from("1"))
+            , LexToken::Addition
+            , LexToken::IntLiteral(String::from("2"))
+            , LexToken::CloseParenth
+            , LexToken::Multiplication
+            , LexToken::IntLiteral(String::from("3"))
+            , LexToken::Semicolon));
+        let statement = Statement::new(&mut deq, &mut ParseContext::new(&mut Diagnostics::new())).unwrap();
+        assert_eq!(statement.span(), Span::new(FileId(0), 0, 9));
+        let Statement::Return(Some(expression), _) = &statement else { panic!("expected a return statement") };
+        assert_eq!(expression.span(), Span::new(FileId(0), 1, 8));
+        let Expression::BinOp(BiOperator::Multiplication, lhs, _, _, _) = expression else { panic!("expected a product, got {:?}", expression) };
+        assert_eq!(lhs.span(), Span::new(FileId(0), 1, 6));
+    }
+
+    #[test]
+    fn adjacent_string_literals_concatenate()
+    {
+        let mut deq = make_deq(vec!(LexToken::StringLiteral(String::from(r#""a\x4""#))
+            , LexToken::StringLiteral(String::from(r#""1\n""#))
+            , LexToken::Semicolon));
+        let factor = Factor::new(&mut deq, &mut ParseContext::new(&mut Diagnostics::new())).unwrap();
+        match factor
+        {
+            Factor::StringLiteral(bytes, span) => {
+                assert_eq!(bytes, b"a\x041\n");
+                assert_eq!(span, Span::new(FileId(0), 0, 2));
+            },
+            _ => panic!("expected a string literal, got {:?}", factor),
+        }
+        assert_eq!(deq.len(), 1);
+    }
+
+    #[test]
+    fn multi_character_constant_warns()
+    {
+        let mut deq = make_deq(vec!(LexToken::CharLiteral(String::from("'ab'"))));
+        let mut diagnostics = Diagnostics::new();
+        assert!(matches!(Factor::new(&mut deq, &mut ParseContext::new(&mut diagnostics)), Some(Factor::Constant(Constant::Character(0x6162), _))));
+        assert!(!diagnostics.has_errors());
+        assert_eq!(diagnostics.iter().next().unwrap().message, "multi-character character constant");
+    }
+
+    #[test]
+    fn declarations_and_assignments()
+    {
+        let (program, diagnostics) = parse("int main() { int a; int b = 2; a = b = 3; return a + b; }");
+        assert!(diagnostics.is_empty());
+        let body = main_body(program);
+        assert_eq!(body.len(), 4);
+        match &body[2]
+        {
+            BlockItem::Statement(Statement::Expression(Expression::Assign(target, value, _), _)) => {
+                assert_eq!(variable(target).name, "a");
+                assert!(matches!(value.as_ref(), Expression::Assign(target, _, _) if variable(target).name == "b"));
+            },
+            item => panic!("expected an assignment, got {:?}", item),
+        }
+    }
+
+    #[test]
+    fn use_before_declaration()
+    {
+        assert_eq!(error_codes("int main() { a = 1; int a; return a; }"), vec!(E_UNDECLARED));
+        assert_eq!(error_codes("int main() { return b; }"), vec!(E_UNDECLARED));
+        assert!(error_codes("int main() { int c = c; return c; }").is_empty());
+    }
+
+    #[test]
+    fn redeclaration_in_same_scope()
+    {
+        let (_, diagnostics) = parse("int main() { int a = 1; int a = 2; return a; }");
+        assert!(diagnostics.has_errors());
+        let diag = diagnostics.iter().next().unwrap();
+        assert_eq!(diag.code, E_REDECLARED);
+        assert_eq!(diag.secondary[0].message, "previous declaration is here");
+    }
+
+    #[test]
+    fn declarations_list_declarators()
+    {
+        let (program, diagnostics) = parse("int a, *b = &a; struct S { int x; } s, *p; typedef struct { int y; } P, *PP; \
+                                            int main() { int i, j = 1; for (int k = 0, l = 1; k < l; k = k + 1) ; PP q = 0; return i + j; }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let program = program.unwrap();
+        let names : Vec<&str> = program.declarations.iter().filter_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Variable(variable) => Some(variable.name.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(names, vec!("a", "b", "s", "p"));
+        assert_eq!(program.declarations.len(), 6);
+        let ExternalDeclaration::Variable(pointer) = &program.declarations[3] else { panic!("expected a variable") };
+        assert!(matches!(&pointer.ty, Type::Pointer(record) if matches!(record.as_ref(), Type::Record(_))));
+        let body = main_body(Some(program));
+        let (BlockItem::Declaration(i), BlockItem::Declaration(j)) = (&body[0], &body[1]) else { panic!("expected declarations, got {:?}", body) };
+        assert_eq!((i.name.as_str(), j.name.as_str()), ("i", "j"));
+        assert!(i.init.is_none() && j.init.is_some());
+        let BlockItem::Statement(Statement::For(Some(ForInit::Declaration(declarations)), ..)) = &body[2] else {
+            panic!("expected a for loop, got {:?}", body[2]);
+        };
+        assert_eq!(declarations.len(), 2);
+        assert_eq!(error_codes("int main() { int i, i; for (static int j, k;;) ; }"), vec!(E_REDECLARED, E_INVALID_SPECIFIERS));
+        assert_eq!(error_codes("int a, ; int main() { int b c; }"), vec!(E_EXPECTED_TOKEN));
+    }
+
+    #[test]
+    fn dangling_else_binds_to_inner_if()
+    {
+        let (program, diagnostics) = parse("int main() { if (1) if (2) return 3; else return 4; }");
+        assert!(diagnostics.is_empty());
+        let body = main_body(program);
+        let BlockItem::Statement(Statement::If(_, then, None, _)) = &body[0] else {
+            panic!("expected an if without else, got {:?}", body[0]);
+        };
+        assert!(matches!(then.as_ref(), Statement::If(_, _, Some(_), _)));
+        assert_eq!(error_codes("int main() { else return 1; }"), vec!(E_EXPECTED_EXPRESSION));
+    }
+
+    #[test]
+    fn for_clauses_are_optional()
+    {
+        let (program, diagnostics) = parse("int main() { for (;;) break; for (int i = 0; i < 3;) { continue; } int i; }");
+        assert!(diagnostics.is_empty());
+        let body = main_body(program);
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::For(None, None, None, _, _))));
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::For(Some(ForInit::Declaration(_)), Some(_), None, _, _))));
+        assert_eq!(error_codes("int main() { for (int i = 0; i < 3; i = i + 1); return i; }"), vec!(E_UNDECLARED));
+        assert!(error_codes("int main() { do ; while (0); while (1) { if (1) break; } }").is_empty());
+    }
+
+    #[test]
+    fn break_and_continue_outside_loop()
+    {
+        assert_eq!(error_codes("int main() { break; continue; }"), vec!(E_OUTSIDE_LOOP, E_OUTSIDE_LOOP));
+        assert_eq!(error_codes("int main() { while (1) ; { continue; } }"), vec!(E_OUTSIDE_LOOP));
+    }
+
+    #[test]
+    fn switch_collects_case_labels()
+    {
+        let (program, diagnostics) = parse("int main() { int a; switch (a) { case 1 + 2: a = 1; default: case 'a': break; case -1: ; } }");
+        assert!(diagnostics.is_empty());
+        let body = main_body(program);
+        let BlockItem::Statement(Statement::Switch(_, _, cases, _)) = &body[1] else {
+            panic!("expected a switch, got {:?}", body[1]);
+        };
+        assert_eq!(cases.values.iter().map(|(value, _)| *value).collect::<Vec<i64>>(), vec!(3, 97, -1));
+        assert!(cases.default.is_some());
+    }
+
+    #[test]
+    fn switch_label_errors()
+    {
+        assert_eq!(error_codes("int main() { switch (1) { case 1: case 2 - 1: ; } }"), vec!(E_DUPLICATE_CASE));
+        assert_eq!(error_codes("int main() { switch (1) { default: default: ; } }"), vec!(E_DUPLICATE_CASE));
+        assert_eq!(error_codes("int main() { int a; switch (1) { case a: ; } }"), vec!(E_NOT_CONSTANT));
+        assert_eq!(error_codes("int main() { case 1: ; default: ; }"), vec!(E_OUTSIDE_SWITCH, E_OUTSIDE_SWITCH));
+        // Only `break` may leave a switch.
+        assert_eq!(error_codes("int main() { switch (1) { case 1: break; continue; } }"), vec!(E_OUTSIDE_LOOP));
+        // A case belongs to the innermost switch, even inside a loop.
+        assert!(error_codes("int main() { switch (1) { case 1: while (0) { case 2: ; } switch (2) { case 1: ; } } }").is_empty());
+    }
+
+    #[test]
+    fn constant_folding()
+    {
+        let value = |source : &str| return_expression(&format!("int main() {{ return {}; }}", source)).constant_value();
+        assert_eq!(value("1 + 2 * 3 - -4"), Some(11));
+        assert_eq!(value("7 / 2 == 3 ? ~0 : 5"), Some(-1));
+        assert_eq!(value("-1 >> 1"), Some(-1));
+        assert_eq!(value("0xffffffff >> 28"), Some(15));
+        assert_eq!(value("-1 < 0u"), Some(0));
+        assert_eq!(value("0 && 1 / 0"), Some(0));
+        assert_eq!(value("1 / 0"), None);
+        assert_eq!(value("1 << 32"), None);
+    }
+
+    #[test]
+    fn labels_have_function_scope()
+    {
+        // Labels are visible before their definition and from outer blocks.
+        assert!(error_codes("int main() { goto end; { inner: goto inner; } end: return 0; }").is_empty());
+        // Labels and variables live in different namespaces.
+        assert!(error_codes("int main() { int a; a: a = 1; goto a; }").is_empty());
+        assert_eq!(error_codes("int main() { goto nowhere; }"), vec!(E_UNDEFINED_LABEL));
+        assert_eq!(error_codes("int main() { a: ; { a: ; } }"), vec!(E_DUPLICATE_LABEL));
+    }
+
+    #[test]
+    fn compound_assignment_and_comma()
+    {
+        let expression = return_expression("int main() { int a; int b; return a += b <<= 2, a--, -++b; }");
+        let Expression::Comma(lhs, negated, _) = &expression else { panic!("expected a comma, got {:?}", expression) };
+        let Expression::Comma(assignment, decrement, _) = lhs.as_ref() else { panic!("expected a comma, got {:?}", lhs) };
+        let Expression::CompoundAssign(BiOperator::Addition, target, value, _) = assignment.as_ref() else {
+            panic!("expected `+=`, got {:?}", assignment);
+        };
+        assert_eq!(variable(target).name, "a");
+        assert!(matches!(value.as_ref(), Expression::CompoundAssign(BiOperator::ShiftLeft, target, _, _) if variable(target).name == "b"));
+        assert!(matches!(decrement.as_ref(), Expression::Factor(Factor::IncDec(IncDecOperator::PostDecrement, _, _))));
+        let Expression::Factor(Factor::UnOp(UniOperator::Negation, operand, _)) = negated.as_ref() else { panic!("expected a negation, got {:?}", negated) };
+        assert!(matches!(operand.as_ref(), Factor::IncDec(IncDecOperator::PreIncrement, _, _)));
+    }
+
+    #[test]
+    fn assignment_requires_lvalue()
+    {
+        assert_eq!(error_codes("int main() { 3 += 1; }"), vec!(E_NOT_LVALUE));
+        assert_eq!(error_codes("int main() { int a; int b; a + b = 1; }"), vec!(E_NOT_LVALUE));
+        assert_eq!(error_codes("int main() { int a; a++++; }"), vec!(E_NOT_LVALUE));
+        assert_eq!(error_codes("int main() { int a; ++(a + 1); }"), vec!(E_NOT_LVALUE));
+        assert!(error_codes("int main() { int a; (a) = 1; ++(a); }").is_empty());
+        // An initializer is a single assignment expression.
+        assert_eq!(error_codes("int main() { int a = 1, 2; }"), vec!(E_EXPECTED_TOKEN));
+    }
+
+    #[test]
+    fn functions_and_prototypes()
+    {
+        let (program, diagnostics) = parse("int putchar(int); int twice(int a) { return a + a; } int main() { return twice(putchar(65)); }");
+        assert!(diagnostics.is_empty());
+        let program = program.unwrap();
+        assert_eq!(program.declarations.len(), 3);
+        let ExternalDeclaration::Function(prototype) = &program.declarations[0] else { panic!("expected a function") };
+        assert!(prototype.body.is_none());
+        assert!(prototype.params[0].name.is_none());
+        let twice = program.function("twice").unwrap();
+        assert_eq!(twice.params[0].name.as_deref(), Some("a"));
+        assert!(program.function("putchar").is_none());
+        // Recursion, and calls to functions only defined later in the file.
+        assert!(error_codes("int g(); int f(int n) { return n ? f(n - 1) : g(); } int g() { return 0; }").is_empty());
+    }
+
+    #[test]
+    fn empty_parameter_lists_leave_parameters_unspecified()
+    {
+        let (program, diagnostics) = parse("int puts(); int main() { char c = 'a'; puts(\"hi\"); return puts(c, 1); }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let Some(BlockItem::Statement(Statement::Return(Some(Expression::Factor(Factor::Call(_, args, _, _))), _))) = main_body(program).pop() else {
+            panic!("expected a returned call");
+        };
+        // The arguments only go through the default argument promotions.
+        assert_eq!(args[0].ty(), Type::Int);
+        assert_eq!(error_codes("int f(); int f(int a); int main() { return f(); }"), vec!(E_ARGUMENT_COUNT));
+        assert_eq!(error_codes("int f(void); int main() { return f(1); }"), vec!(E_ARGUMENT_COUNT));
+        assert_eq!(error_codes("int f(int a); int f(); long f();"), vec!(E_CONFLICTING_DECLARATION));
+    }
+
+    #[test]
+    fn function_errors()
+    {
+        assert_eq!(error_codes("int f(int a, int b); int main() { return f(1); }"), vec!(E_ARGUMENT_COUNT));
+        assert_eq!(error_codes("int f(int a); int f(int a, int b) { return a; }"), vec!(E_CONFLICTING_DECLARATION));
+        assert_eq!(error_codes("int f() { return 1; } int f() { return 2; }"), vec!(E_REDECLARED));
+        assert_eq!(error_codes("int f(int a) { int a; return a; }"), vec!(E_REDECLARED));
+        assert_eq!(error_codes("int f(int) { return 0; }"), vec!(E_EXPECTED_TOKEN));
+        assert_eq!(error_codes("int main() { int f; return f(); }"), vec!(E_NOT_FUNCTION));
+        assert_eq!(error_codes("int main() { return f(); }"), vec!(W_IMPLICIT_DECLARATION));
+    }
+
+    #[test]
+    fn globals_share_ids_across_declarations()
+    {
+        let (program, diagnostics) = parse("int a; extern int a; int main() { extern int a; return a; } int a = 1;");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let program = program.unwrap();
+        let ExternalDeclaration::Variable(first) = &program.declarations[0] else { panic!("expected a variable") };
+        let ExternalDeclaration::Variable(last) = &program.declarations[3] else { panic!("expected a variable") };
+        assert_eq!(first.id, last.id);
+        let Some(Initializer::Single(init)) = &last.init else { panic!("expected a single value") };
+        assert_eq!(init.constant_value(), Some(1));
+        let id = first.id;
+        let body = main_body(Some(program));
+        let BlockItem::Declaration(local) = &body[0] else { panic!("expected a declaration") };
+        assert_eq!(local.storage, Some(StorageClass::Extern));
+        assert_eq!(local.id, id);
+    }
+
+    #[test]
+    fn global_declaration_errors()
+    {
+        assert_eq!(error_codes("static int a; int a;"), vec!(E_CONFLICTING_DECLARATION));
+        assert_eq!(error_codes("static int f(); int f() { return 0; } extern int a; static int a;"),
+                    vec!(E_CONFLICTING_DECLARATION));
+        assert_eq!(error_codes("int a = 1; int a = 2;"), vec!(E_REDECLARED));
+        assert_eq!(error_codes("int f(); int f;"), vec!(E_REDECLARED));
+        assert_eq!(error_codes("int a; int b = a;"), vec!(E_NOT_CONSTANT));
+        assert_eq!(error_codes("int main() { int a; static int b = a; return b; }"), vec!(E_NOT_CONSTANT));
+        assert_eq!(error_codes("int main() { extern int a = 1; return a; }"), vec!(E_INVALID_SPECIFIERS));
+        assert_eq!(error_codes("extern static int a;"), vec!(E_INVALID_SPECIFIERS));
+        assert_eq!(error_codes("int main() { for (static int i = 0; ; ) {} }"), vec!(E_INVALID_SPECIFIERS));
+        assert!(error_codes("static int a; extern int a; static int f(); int f() { return a; }").is_empty());
+    }
+
+    #[test]
+    fn type_specifiers_combine_in_any_order()
+    {
+        let (program, diagnostics) = parse("long unsigned a; int long long signed b; char c; unsigned char d; _Bool e; short int f;");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let types : Vec<Type> = program.unwrap().declarations.iter().filter_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Variable(variable) => Some(variable.ty.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(types, vec!(Type::UnsignedLong, Type::LongLong, Type::Char, Type::UnsignedChar, Type::Bool, Type::Short));
+        assert_eq!(error_codes("long long long a; short char b; unsigned signed c;"),
+                    vec!(E_INVALID_SPECIFIERS, E_INVALID_SPECIFIERS, E_INVALID_SPECIFIERS));
+    }
+
+    #[test]
+    fn operands_are_converted_to_a_common_type()
+    {
+        let expression = return_expression("int main() { char c; unsigned u; return c + u; }");
+        let Expression::BinOp(_, lhs, rhs, _, _) = &expression else { panic!("expected a sum, got {:?}", expression) };
+        assert!(matches!(lhs.as_ref(), Expression::Convert(Type::UnsignedInt, _)));
+        assert!(matches!(rhs.as_ref(), Expression::Factor(Factor::Variable(..))));
+        assert_eq!(return_expression("int main() { short s; return s << 40LL; }").ty(), Type::Int);
+        assert_eq!(return_expression("int main() { return (unsigned char)-1; }").constant_value(), Some(255));
+        assert_eq!(return_expression("int main() { return (_Bool)2 == 1; }").constant_value(), Some(1));
+    }
+
+    #[test]
+    fn void_has_no_value()
+    {
+        assert!(error_codes("void f(void); void g(void) { f(); (void)1; return; }").is_empty());
+        assert_eq!(error_codes("void f(void); int main(void) { int a = f(); return f() + 1; }"), vec!(E_VOID_VALUE, E_VOID_VALUE));
+        assert_eq!(error_codes("int main(void) { void a; return 0; }"), vec!(E_INCOMPLETE_TYPE));
+        assert_eq!(error_codes("void f(void) { return 1; } int g(void) { return; }"), vec!(E_RETURN_MISMATCH, E_RETURN_MISMATCH));
+        assert_eq!(error_codes("int f(void); int f(int a) { return a; }"), vec!(E_CONFLICTING_DECLARATION));
+    }
+
+    #[test]
+    fn pointer_declarators_and_operators()
+    {
+        let (program, diagnostics) = parse("int **p; char *s; unsigned *f(long *a);");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let types : Vec<Type> = program.unwrap().declarations.iter().filter_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Variable(variable) => Some(variable.ty.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(types, vec!(Type::pointer_to(Type::pointer_to(Type::Int)), Type::pointer_to(Type::Char)));
+        assert_eq!(return_expression("int main() { int *p; return *p + 1; }").ty(), Type::Int);
+        assert_eq!(return_expression("int main() { int a; return &a == 0; }").ty(), Type::Int);
+        assert_eq!(return_expression("char *main() { return \"text\" + 1; }").ty(), Type::pointer_to(Type::Char));
+        assert_eq!(return_expression("long long *main() { long long *p; return 2 + p; }").ty(), Type::pointer_to(Type::LongLong));
+        assert_eq!(return_expression("int main() { int *p; int *q; return p - q; }").ty(), Type::Int);
+        assert!(error_codes("int main() { int a; int *p = &a; *p = 2; (*p)++; p++; *&a += 1; return p != 0; }").is_empty());
+    }
+
+    #[test]
+    fn pointers_and_integers_do_not_mix()
+    {
+        assert_eq!(error_codes("int main() { int *p; return p * 2; }"), vec!(E_INVALID_OPERANDS));
+        assert_eq!(error_codes("int main() { int *p; int *q; return p + q; }"), vec!(E_INVALID_OPERANDS));
+        assert_eq!(error_codes("int main() { return *1; }"), vec!(E_INVALID_OPERANDS));
+        assert_eq!(error_codes("int main() { int *p = 1; return 0; }"), vec!(E_INCOMPATIBLE_TYPES));
+        assert_eq!(error_codes("int main() { int *p; char *q = p; return 0; }"), vec!(E_INCOMPATIBLE_TYPES));
+        assert_eq!(error_codes("int main() { int *p; long *q; return p < q; }"), vec!(E_INCOMPATIBLE_TYPES));
+        assert_eq!(error_codes("int main() { return &1 == 0; }"), vec!(E_NOT_LVALUE));
+        assert!(error_codes("int main() { int a; void *v = &a; int *p = v; char *c = 0; return p == v && !c; }").is_empty());
+    }
+
+    #[test]
+    fn array_declarators_and_sizeof()
+    {
+        let (program, diagnostics) = parse("int a[2][3]; char *names[4]; int (*row)[3]; int primes[] = {2, 3, 5}; char s[] = \"hi\";");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let types : Vec<Type> = program.unwrap().declarations.iter().filter_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Variable(variable) => Some(variable.ty.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(types, vec!(Type::array_of(Type::array_of(Type::Int, 3), 2), Type::array_of(Type::pointer_to(Type::Char), 4),
+                               Type::pointer_to(Type::array_of(Type::Int, 3)), Type::array_of(Type::Int, 3), Type::array_of(Type::Char, 3)));
+        assert_eq!(return_expression("int main() { int a[2][3]; return sizeof a + sizeof a[1] + sizeof(char *[4]); }").constant_value(), Some(52));
+        assert_eq!(return_expression("int main() { int a[4]; return a[1]; }").ty(), Type::Int);
+        assert_eq!(return_expression("int *main() { int a[2][3]; return a[1] + 1; }").ty(), Type::pointer_to(Type::Int));
+        assert!(error_codes("int f(int a[], int n) { return a[n - 1]; } int main() { int a[3]; a[0] = 1; return f(a, 3) + 1[a]; }").is_empty());
+    }
+
+    #[test]
+    fn initializer_lists_are_flattened()
+    {
+        let (program, diagnostics) = parse("short a[][2] = {{1, 2}, 3, 4, {5}};");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let ExternalDeclaration::Variable(variable) = &program.unwrap().declarations[0] else { panic!("expected a variable") };
+        assert_eq!(variable.ty, Type::array_of(Type::array_of(Type::Short, 2), 3));
+        let Some(Initializer::List(values)) = &variable.init else { panic!("expected a list") };
+        let values : Vec<(usize, Option<i64>)> = values.iter().map(|(offset, value)| (*offset, value.constant_value())).collect();
+        assert_eq!(values, vec!((0, Some(1)), (2, Some(2)), (4, Some(3)), (6, Some(4)), (8, Some(5))));
+    }
+
+    #[test]
+    fn arrays_are_checked()
+    {
+        assert_eq!(error_codes("int a[0]; int b[-1]; int main() { int n = 2; int c[n]; return 0; }"),
+                   vec!(E_INVALID_ARRAY_SIZE, E_INVALID_ARRAY_SIZE, E_INVALID_ARRAY_SIZE));
+        assert_eq!(error_codes("int a[]; int b[][] = {1};"), vec!(E_INVALID_ARRAY_SIZE, E_INVALID_ARRAY_SIZE));
+        assert_eq!(error_codes("int a[2] = {1, 2, 3}; char s[2] = \"abc\"; int b[2] = 1; int c = {1, 2};"),
+                   vec!(E_INVALID_INITIALIZER, E_INVALID_INITIALIZER, E_INVALID_INITIALIZER, E_INVALID_INITIALIZER));
+        assert_eq!(error_codes("int main() { int a[2]; int b[2]; a = b; return 0; }"), vec!(E_NOT_LVALUE));
+        assert_eq!(error_codes("int main() { int a; return a[0]; }"), vec!(E_INVALID_OPERANDS));
+        assert_eq!(error_codes("int main() { void a[2]; return sizeof(void); }"), vec!(E_INCOMPLETE_TYPE, E_INCOMPLETE_TYPE));
+        // Objects are limited to what a signed 32 bit size can hold.
+        assert!(error_codes("char a[2147483647]; int b[536870911];").is_empty());
+        assert_eq!(error_codes("char a[2147483647][2147483647][2147483647]; int b[2147483648]; int c[] = {[536870911] = 1};"),
+                   vec!(E_TOO_LARGE, E_TOO_LARGE, E_TOO_LARGE, E_TOO_LARGE));
+        assert_eq!(error_codes("struct s { char a[1 << 30]; char b[1 << 30]; }; int main() { char c[1 << 30]; char d[1 << 30]; return 0; }"),
+                   vec!(E_TOO_LARGE, E_TOO_LARGE));
+    }
+
+    #[test]
+    fn struct_members_and_access()
+    {
+        let (program, diagnostics) = parse("struct point { int x; char tag; struct point *next; }; \
+                                            int main() { struct point p; struct point *q = &p; p.tag = 1; return q->next->x; }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let body = main_body(program);
+        let BlockItem::Statement(Statement::Expression(Expression::Assign(target, _, _), _)) = &body[2] else {
+            panic!("expected an assignment, got {:?}", body[2]);
+        };
+        let Factor::Member(object, name, offset, ty, _) = target.as_ref() else { panic!("expected a member, got {:?}", target) };
+        assert_eq!((name.as_str(), *offset, ty), ("tag", 4, &Type::Char));
+        assert_eq!(variable(object).name, "p");
+        let BlockItem::Statement(Statement::Return(Some(Expression::Factor(Factor::Member(object, _, _, _, _))), _)) = &body[3] else {
+            panic!("expected a member, got {:?}", body[3]);
+        };
+        // `q->next->x` is `(*(*q).next).x`.
+        let Factor::UnOp(UniOperator::Dereference, pointer, _) = object.as_ref() else { panic!("expected a dereference, got {:?}", object) };
+        assert!(matches!(pointer.as_ref(), Factor::Member(_, name, 8, _, _) if name == "next"));
+    }
+
+    #[test]
+    fn designated_initializers_and_elided_braces()
+    {
+        let (program, diagnostics) = parse("struct p { char c; int x, y; }; struct p a[] = {[1] = {.x = 2, 3}, 4, [0].x = 5, 6}; \
+                                            int b[3] = {1, 2, [0] = 7};");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let offsets : Vec<Vec<(usize, Option<i64>)>> = program.unwrap().declarations.iter().filter_map(|declaration| match declaration
+        {
+            ExternalDeclaration::Variable(Declaration{init: Some(Initializer::List(values)), ..}) => {
+                Some(values.iter().map(|(offset, value)| (*offset, value.constant_value())).collect())
+            },
+            _ => None,
+        }).collect();
+        assert_eq!(offsets, vec!(vec!((4, Some(5)), (8, Some(6)), (16, Some(2)), (20, Some(3)), (24, Some(4))),
+                                 vec!((0, Some(7)), (4, Some(2)))));
+        let (program, _) = parse("struct p { char c; int x, y; }; struct p a[] = {[2].y = 1};");
+        let ExternalDeclaration::Variable(variable) = &program.unwrap().declarations[1] else { panic!("expected a variable") };
+        assert_eq!(variable.ty.size(), 36);
+    }
+
+    #[test]
+    fn structs_are_checked()
+    {
+        assert_eq!(error_codes("struct s { int a; int a; struct t inner; };"), vec!(E_REDECLARED, E_INCOMPLETE_TYPE));
+        assert_eq!(error_codes("struct s { int a; }; union s *u; struct s { int b; };"), vec!(E_CONFLICTING_DECLARATION, E_REDECLARED));
+        assert_eq!(error_codes("struct s; struct s v; int main() { return sizeof(struct s); }"), vec!(E_INCOMPLETE_TYPE, E_INCOMPLETE_TYPE));
+        assert_eq!(error_codes("struct s { int a; } v; int main() { return v.b; }"), vec!(E_NO_MEMBER));
+        assert_eq!(error_codes("struct s { int a; } v; int main() { if (v) return v + 1; return !v; }"),
+                   vec!(E_INVALID_OPERANDS, E_INVALID_OPERANDS, E_INVALID_OPERANDS));
+        assert_eq!(error_codes("struct s { int a; } v; struct t { int a; } w; int main() { v = w; return (int)v; }"),
+                   vec!(E_INCOMPATIBLE_TYPES, E_INVALID_OPERANDS));
+        assert_eq!(error_codes("struct s { int a; } v = {1, 2}; struct s w = {.b = 1};"), vec!(E_INVALID_INITIALIZER, E_NO_MEMBER));
+        // A tag declared in a block hides the one outside.
+        assert!(error_codes("struct s { int a; }; int main() { struct s { char b; } v; v.b = 1; { struct s; } return 0; }").is_empty());
+    }
+
+    #[test]
+    fn enumerators_are_constants()
+    {
+        let (program, diagnostics) = parse("enum e { A, B = 5, C, D = C * 2 }; int a[D]; \
+                                            int main() { int B = A; switch (B) { case C: return 1; } enum e x = C; return x; }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let program = program.unwrap();
+        let ExternalDeclaration::Variable(array) = &program.declarations[1] else { panic!("expected a variable") };
+        assert_eq!(array.ty, Type::Array(Box::new(Type::Int), 12));
+        let body = main_body(Some(program));
+        let BlockItem::Declaration(Declaration{name, init: Some(Initializer::Single(init)), ..}) = &body[0] else {
+            panic!("expected a declaration, got {:?}", body[0]);
+        };
+        // The variable only hides the enumerator once it is declared.
+        assert_eq!(name, "B");
+        assert!(matches!(init, Expression::Factor(Factor::Constant(Constant::Enumerator(name, 0), _)) if name == "A"));
+        let BlockItem::Statement(Statement::Switch(_, _, cases, _)) = &body[1] else { panic!("expected a switch, got {:?}", body[1]) };
+        assert_eq!(cases.values[0].0, 6);
+    }
+
+    #[test]
+    fn enums_are_checked()
+    {
+        assert_eq!(error_codes("enum e { A, A }; enum e { B };"), vec!(E_REDECLARED, E_REDECLARED));
+        assert_eq!(error_codes("struct s { int a; }; enum s { A }; enum t x;"), vec!(E_CONFLICTING_DECLARATION, E_UNDECLARED));
+        assert_eq!(error_codes("enum { v }; int v; int f(void); enum { f };"), vec!(E_REDECLARED, E_REDECLARED));
+        assert_eq!(error_codes("int n; enum { A = n, B = 2147483647, C };"), vec!(E_NOT_CONSTANT, E_INVALID_CONSTANT));
+        assert_eq!(error_codes("enum { A }; int main() { A = 1; return 0; }"), vec!(E_NOT_LVALUE));
+        // An enumerator in a block hides a variable outside.
+        assert!(error_codes("int A; int main() { enum { A = 3 }; int a[A]; return A; }").is_empty());
+    }
+
+    #[test]
+    fn typedef_names_start_declarations()
+    {
+        let (program, diagnostics) = parse("typedef int T; int main() { T * x; { int T = 2; T * 3; } T T; return T; }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let body = main_body(program);
+        let BlockItem::Declaration(declaration) = &body[0] else { panic!("expected a declaration, got {:?}", body[0]) };
+        assert_eq!((declaration.name.as_str(), &declaration.ty), ("x", &Type::pointer_to(Type::Int)));
+        let BlockItem::Statement(Statement::Compound(inner, _)) = &body[1] else { panic!("expected a block, got {:?}", body[1]) };
+        assert!(matches!(&inner[1], BlockItem::Statement(Statement::Expression(Expression::BinOp(BiOperator::Multiplication, ..), _))));
+        // `T T;` declares a variable hiding the typedef name.
+        let BlockItem::Declaration(declaration) = &body[2] else { panic!("expected a declaration, got {:?}", body[2]) };
+        assert_eq!((declaration.name.as_str(), &declaration.ty), ("T", &Type::Int));
+    }
+
+    #[test]
+    fn typedefs_are_checked()
+    {
+        assert!(error_codes("typedef struct s { int a; } S; typedef S S; S v; int main() { return sizeof(S) + (S *)0 - &v; }").is_empty());
+        assert_eq!(error_codes("typedef int T; typedef long T; int T;"), vec!(E_CONFLICTING_DECLARATION, E_REDECLARED));
+        assert_eq!(error_codes("typedef int T; int main() { return T; }"), vec!(E_EXPECTED_EXPRESSION));
+        assert_eq!(error_codes("typedef int T; unsigned T x;"), vec!(E_REDECLARED, E_EXPECTED_TOKEN));
+        assert_eq!(error_codes("typedef int T; struct s { typedef int U; };"), vec!(E_INVALID_SPECIFIERS));
+    }
+
+    #[test]
+    fn labels_may_share_typedef_names()
+    {
+        let (program, diagnostics) = parse("typedef int T; int main() { goto T; T: return 0; }");
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let body = main_body(program);
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::Labeled(label, _, _)) if label == "T"), "{:?}", body[1]);
+        assert!(error_codes("typedef int T; int main() { T x = 1; { T: x = 2; } T y = x; return y; }").is_empty());
+    }
+
+    #[test]
+    fn static_initializers_take_addresses()
+    {
+        assert!(error_codes("int a[4]; int *p = &a[2]; int *q = a + 1; char *s = \"text\" + 1; int (*r)[4] = &a;").is_empty());
+        assert!(error_codes("int main() { static int n; static int *p = &n; return *p; }").is_empty());
+        assert_eq!(error_codes("int main() { int n; static int *p = &n; return 0; }"), vec!(E_NOT_CONSTANT));
+        assert_eq!(error_codes("int a[4]; int *p = &a[a[0]];"), vec!(E_NOT_CONSTANT));
+    }
+
+    #[test]
+    fn conditional_is_right_associative()
+    {
+        let expression = return_expression("int main() { int a; return a ? 1 : a || 2 ? 3 : 4; }");
+        let Expression::Conditional(condition, _, otherwise, _) = &expression else { panic!("expected a conditional, got {:?}", expression) };
+        assert!(matches!(condition.as_ref(), Expression::Factor(Factor::Variable(..))));
+        assert!(matches!(otherwise.as_ref(), Expression::Conditional(..)));
+        assert_eq!(return_expression("int main() { return 1 ? 2 : 3u; }").ty(), Type::UnsignedInt);
+    }
+
+    #[test]
+    fn unsigned_constants_make_expressions_unsigned()
+    {
+        assert_eq!(return_expression("int main() { return -(1u + 2) >> 1; }").ty(), Type::UnsignedInt);
+        assert_eq!(return_expression("int main() { return 1 >> 2u; }").ty(), Type::Int);
+        assert_eq!(return_expression("int main() { return 1u < 2; }").ty(), Type::Int);
+        assert_eq!(return_expression("int main() { return 4000000000; }").ty(), Type::LongLong);
+    }
+
+    #[test]
+    fn inner_blocks_shadow_and_release_names()
+    {
+        let (program, diagnostics) = parse("int main() { int a = 1; { int a = 2; a = 3; } return a; }");
+        assert!(diagnostics.is_empty());
+        let body = main_body(program);
+        let (BlockItem::Declaration(outer), BlockItem::Statement(Statement::Compound(inner, _))) = (&body[0], &body[1]) else {
+            panic!("expected a declaration and a block, got {:?}", body);
+        };
+        let BlockItem::Declaration(shadow) = &inner[0] else { panic!("expected a declaration, got {:?}", inner[0]) };
+        assert_ne!(shadow.id, outer.id);
+        let BlockItem::Statement(Statement::Expression(Expression::Assign(target, _, _), _)) = &inner[1] else {
+            panic!("expected an assignment, got {:?}", inner[1]);
+        };
+        assert_eq!(variable(target).id, shadow.id);
+
+        assert_eq!(error_codes("int main() { { int b; } return b; }"), vec!(E_UNDECLARED));
+        assert_eq!(error_codes("int main() { { return 0; }"), vec!(E_UNEXPECTED_EOF));
+    }
+
+    fn return_expression(source : &str) -> Expression
+    {
+        let (program, diagnostics) = parse(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let body = main_body(program);
+        match body.into_iter().last()
+        {
+            // The value is converted to the return type of `main`.
+            Some(BlockItem::Statement(Statement::Return(Some(Expression::Convert(_, expression)), _))) => return *expression,
+            Some(BlockItem::Statement(Statement::Return(Some(expression), _))) => return expression,
+            item => panic!("expected a return statement, got {:?}", item),
+        };
+    }
+
+    // Writes the operator tree with explicit parentheses.
+    fn grouping(expression : &Expression) -> String
+    {
+        match expression
+        {
+            Expression::BinOp(oper, lhs, rhs, _, _) => return format!("({} {:?} {})", grouping(lhs), oper, grouping(rhs)),
+            Expression::Factor(Factor::Constant(Constant::Integer(literal), _)) => return literal.value.to_string(),
+            Expression::Factor(Factor::Variable(variable, _)) => return variable.name.clone(),
+            _ => panic!("unexpected expression {:?}", expression),
+        };
+    }
+
+    #[test]
+    fn binary_operator_precedence()
+    {
+        assert_eq!(grouping(&return_expression("int main() { return 1 | 2 ^ 3 & 4 == 5; }")),
+                    "(1 BitwiseOr (2 BitwiseXor (3 BitwiseAnd (4 Equal 5))))");
+        assert_eq!(grouping(&return_expression("int main() { return 1 < 2 << 3 + 4 % 5; }")),
+                    "(1 LessThan (2 ShiftLeft (3 Addition (4 Modulo 5))))");
+        assert_eq!(grouping(&return_expression("int main() { return 1 - 2 - 3; }")),
+                    "((1 Subtraction 2) Subtraction 3)");
+        assert_eq!(grouping(&return_expression("int main() { return 1 || 2 && 3 == 4 < 5 + 6 * 7; }")),
+                    "(1 LogicalOr (2 LogicalAnd (3 Equal (4 LessThan (5 Addition (6 Multiplication 7))))))");
+        assert_eq!(grouping(&return_expression("int main() { int a; return a * 2 >= 3 != a && 1 || 0; }")),
+                    "(((((a Multiplication 2) GreaterThanOrEqual 3) NotEqual a) LogicalAnd 1) LogicalOr 0)");
+    }
+}
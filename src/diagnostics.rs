@@ -0,0 +1,348 @@
+use std::fmt;
+
+use crate::source_map::*;
+
+pub const E_EXPECTED_TOKEN : &str = "E0001";
+pub const E_UNEXPECTED_EOF : &str = "E0002";
+pub const E_INVALID_CONSTANT : &str = "E0003";
+pub const E_EXPECTED_EXPRESSION : &str = "E0004";
+pub const E_TRAILING_TOKENS : &str = "E0005";
+pub const E_IO : &str = "E0006";
+pub const E_UNTERMINATED_COMMENT : &str = "E0007";
+pub const E_UNTERMINATED_LITERAL : &str = "E0008";
+pub const E_INVALID_LITERAL : &str = "E0009";
+pub const E_UNDECLARED : &str = "E0010";
+pub const E_REDECLARED : &str = "E0011";
+pub const E_OUTSIDE_LOOP : &str = "E0012";
+pub const E_NOT_CONSTANT : &str = "E0013";
+pub const E_DUPLICATE_CASE : &str = "E0014";
+pub const E_OUTSIDE_SWITCH : &str = "E0015";
+pub const E_UNDEFINED_LABEL : &str = "E0016";
+pub const E_DUPLICATE_LABEL : &str = "E0017";
+pub const E_NOT_LVALUE : &str = "E0018";
+pub const E_CONFLICTING_DECLARATION : &str = "E0019";
+pub const E_ARGUMENT_COUNT : &str = "E0020";
+pub const E_NOT_FUNCTION : &str = "E0021";
+pub const E_INVALID_SPECIFIERS : &str = "E0022";
+pub const E_VOID_VALUE : &str = "E0023";
+pub const E_INCOMPLETE_TYPE : &str = "E0024";
+pub const E_RETURN_MISMATCH : &str = "E0025";
+pub const E_INVALID_OPERANDS : &str = "E0026";
+pub const E_INCOMPATIBLE_TYPES : &str = "E0027";
+pub const E_INVALID_ARRAY_SIZE : &str = "E0028";
+pub const E_INVALID_INITIALIZER : &str = "E0029";
+pub const E_NO_MEMBER : &str = "E0030";
+pub const E_TOO_LARGE : &str = "E0031";
+pub const E_LINK : &str = "E0032";
+
+pub const W_LARGE_DECIMAL_CONSTANT : &str = "W0001";
+pub const W_LITERAL : &str = "W0002";
+pub const W_IMPLICIT_DECLARATION : &str = "W0003";
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum Severity
+{
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity
+{
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Severity::Error => return write!(f, "error"),
+            Severity::Warning => return write!(f, "warning"),
+            Severity::Note => return write!(f, "note"),
+        };
+    }
+}
+
+/// A message attached to a span of the source being compiled.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Label
+{
+    pub span : Span,
+    pub message : String,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Diagnostic
+{
+    pub severity : Severity,
+    pub code : &'static str,
+    pub message : String,
+    pub primary : Option<Label>,
+    pub secondary : Vec<Label>,
+    pub notes : Vec<String>,
+}
+
+impl Diagnostic
+{
+    pub fn new(severity : Severity, code : &'static str, message : &str) -> Diagnostic
+    {
+        return Diagnostic{severity, code, message: message.to_string(), primary: None, secondary: Vec::new(), notes: Vec::new()};
+    }
+
+    pub fn error(code : &'static str, message : &str) -> Diagnostic
+    {
+        return Diagnostic::new(Severity::Error, code, message);
+    }
+
+    pub fn warning(code : &'static str, message : &str) -> Diagnostic
+    {
+        return Diagnostic::new(Severity::Warning, code, message);
+    }
+
+    pub fn with_primary(mut self, span : Span, message : &str) -> Diagnostic
+    {
+        self.primary = Some(Label{span, message: message.to_string()});
+        return self;
+    }
+
+    pub fn with_secondary(mut self, span : Span, message : &str) -> Diagnostic
+    {
+        self.secondary.push(Label{span, message: message.to_string()});
+        return self;
+    }
+
+    pub fn with_note(mut self, note : &str) -> Diagnostic
+    {
+        self.notes.push(note.to_string());
+        return self;
+    }
+
+    /// Renders the diagnostic the way rustc does: a header, the location of the
+    /// primary label, every labelled source line with a caret underline for
+    /// each of its labels and finally the notes.
+    pub fn render(&self, source_map : &SourceMap) -> String
+    {
+        let mut out = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+
+        let labels : Vec<(&Label, char)> = self.primary.iter().map(|label| (label, '^'))
+                        .chain(self.secondary.iter().map(|label| (label, '-')))
+                        .collect();
+        if labels.is_empty()
+        {
+            for note in &self.notes
+            {
+                out += &format!("  = note: {}\n", note);
+            }
+            return out;
+        }
+
+        let gutter = labels.iter().map(|(label, _)| source_map.location(label.span).line.to_string().len()).max().unwrap_or(1);
+        let blank = " ".repeat(gutter);
+
+        // Every labelled line is printed once, with the underlines of all its
+        // labels below it. Files come in the order of their first label, which
+        // gives the location in their header, and lines in source order.
+        let mut files : Vec<(FileId, Location)> = Vec::new();
+        let mut lines : Vec<(usize, usize, Vec<_>)> = Vec::new();
+        for (label, marker) in labels
+        {
+            let location = source_map.location(label.span);
+            let file = match files.iter().position(|(file, _)| *file == label.span.file)
+            {
+                Some(file) => file,
+                None => {
+                    files.push((label.span.file, location));
+                    files.len() - 1
+                },
+            };
+            match lines.iter_mut().find(|(other, line, _)| *other == file && *line == location.line)
+            {
+                Some((_, _, group)) => group.push((label, marker)),
+                None => lines.push((file, location.line, vec!((label, marker)))),
+            };
+        }
+        lines.sort_by_key(|(file, line, _)| (*file, *line));
+
+        let mut previous : Option<(usize, usize)> = None;
+        for (index, line, group) in &lines
+        {
+            let (id, location) = files[*index];
+            let file = source_map.file(id);
+            match previous
+            {
+                Some((previous_file, previous_line)) if previous_file == *index => {
+                    if *line > previous_line + 1
+                    {
+                        out += "...\n";
+                    }
+                },
+                _ => {
+                    let arrow = if previous.is_none() { "-->" } else { ":::" };
+                    out += &format!("{}{} {}:{}:{}\n", blank, arrow, file.name, location.line, location.column);
+                    out += &format!("{} |\n", blank);
+                },
+            };
+            previous = Some((*index, *line));
+            let (line_start, line_end) = file.line_range(*line);
+            out += &format!("{:>width$} | {}\n", line, &file.source[line_start..line_end], width = gutter);
+            for (label, marker) in group
+            {
+                let lo = label.span.lo.min(line_end);
+                let hi = label.span.hi.min(line_end).max(lo);
+                let width = file.source[lo..hi].chars().count().max(1);
+                let column = file.location(label.span.lo).column;
+                let underline = format!("{}{}", " ".repeat(column - 1), marker.to_string().repeat(width));
+                if label.message.is_empty()
+                {
+                    out += &format!("{} | {}\n", blank, underline);
+                }
+                else
+                {
+                    out += &format!("{} | {} {}\n", blank, underline, label.message);
+                }
+            }
+        }
+        if !self.notes.is_empty()
+        {
+            out += &format!("{} |\n", blank);
+        }
+        for note in &self.notes
+        {
+            out += &format!("{} = note: {}\n", blank, note);
+        }
+        return out;
+    }
+}
+
+/// Sink that every stage of the compiler reports its diagnostics into.
+#[derive(Debug,Default)]
+pub struct Diagnostics
+{
+    diagnostics : Vec<Diagnostic>,
+}
+
+impl Diagnostics
+{
+    pub fn new() -> Diagnostics
+    {
+        return Diagnostics{diagnostics: Vec::new()};
+    }
+
+    pub fn emit(&mut self, diagnostic : Diagnostic)
+    {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn has_errors(&self) -> bool
+    {
+        return self.error_count() > 0;
+    }
+
+    pub fn error_count(&self) -> usize
+    {
+        return self.diagnostics.iter().filter(|diag| diag.severity == Severity::Error).count();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic>
+    {
+        return self.diagnostics.iter();
+    }
+
+    pub fn len(&self) -> usize
+    {
+        return self.diagnostics.len();
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        return self.diagnostics.is_empty();
+    }
+
+    pub fn render(&self, source_map : &SourceMap) -> String
+    {
+        return self.diagnostics.iter().map(|diag| diag.render(source_map)).collect::<Vec<String>>().join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn source_map(source : &str) -> (SourceMap, FileId)
+    {
+        let mut source_map = SourceMap::new();
+        let id = source_map.add_file("main.c", source.to_string());
+        return (source_map, id);
+    }
+
+    #[test]
+    fn render_primary_label()
+    {
+        let (source_map, id) = source_map("int main()\n{\n    return 2 }\n");
+        let diag = Diagnostic::error(E_EXPECTED_TOKEN, "expected `;`, found `}`")
+                        .with_primary(Span::new(id, 26, 27), "expected `;`");
+        assert_eq!(diag.render(&source_map),
+                    "error[E0001]: expected `;`, found `}`\n \
+                    --> main.c:3:14\n  \
+                    |\n\
+                    3 |     return 2 }\n  \
+                    |              ^ expected `;`\n");
+    }
+
+    #[test]
+    fn render_secondary_label_and_note()
+    {
+        let (source_map, id) = source_map("int main(\n{");
+        let diag = Diagnostic::error(E_EXPECTED_TOKEN, "expected `)`, found `{`")
+                        .with_primary(Span::new(id, 10, 11), "")
+                        .with_secondary(Span::new(id, 4, 8), "function declared here")
+                        .with_note("function parameters must be closed");
+        assert_eq!(diag.render(&source_map),
+                    "error[E0001]: expected `)`, found `{`\n \
+                    --> main.c:2:1\n  \
+                    |\n\
+                    1 | int main(\n  \
+                    |     ---- function declared here\n\
+                    2 | {\n  \
+                    | ^\n  \
+                    |\n  \
+                    = note: function parameters must be closed\n");
+    }
+
+    #[test]
+    fn render_labels_grouped_by_line()
+    {
+        let (source_map, id) = source_map("int x;\nint y;\n\nint z = x + y;");
+        let diag = Diagnostic::error(E_INVALID_OPERANDS, "invalid operands")
+                        .with_primary(Span::new(id, 25, 26), "the operator")
+                        .with_secondary(Span::new(id, 23, 24), "")
+                        .with_secondary(Span::new(id, 4, 5), "declared here");
+        assert_eq!(diag.render(&source_map),
+                    "error[E0026]: invalid operands\n \
+                    --> main.c:4:11\n  \
+                    |\n\
+                    1 | int x;\n  \
+                    |     - declared here\n\
+                    ...\n\
+                    4 | int z = x + y;\n  \
+                    |           ^ the operator\n  \
+                    |         -\n");
+    }
+
+    #[test]
+    fn render_without_labels()
+    {
+        let (source_map, _) = source_map("");
+        let diag = Diagnostic::error(E_UNEXPECTED_EOF, "unexpected end of file").with_note("while parsing `main`");
+        assert_eq!(diag.render(&source_map), "error[E0002]: unexpected end of file\n  = note: while parsing `main`\n");
+    }
+
+    #[test]
+    fn counts_only_errors()
+    {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.emit(Diagnostic::warning(E_INVALID_CONSTANT, "warn"));
+        assert!(!diagnostics.has_errors());
+        diagnostics.emit(Diagnostic::error(E_INVALID_CONSTANT, "err"));
+        assert_eq!(diagnostics.error_count(), 1);
+        assert_eq!(diagnostics.len(), 2);
+    }
+}
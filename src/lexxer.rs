@@ -1,28 +1,21 @@
-
-use regex::Regex;
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::Path;
+use std::fs;
+use std::io::{self, Read};
 use std::collections::VecDeque;
+use std::iter::Peekable;
+use std::str::CharIndices;
 
-const TOKENS_NEED_SPACE_REGEX : &str = r"[/+\*\{\}\(\);!~-]";
-
-
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where P: AsRef<Path>, {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
-}
+use crate::diagnostics::*;
+use crate::source_map::*;
 
-fn format_spacing(string : &str) -> String
-{
-    let re = Regex::new(TOKENS_NEED_SPACE_REGEX).unwrap();
-    return re.replace_all(&string," $0 ").into_owned();
-}
+/// Path that makes `lex` read the program from standard input.
+pub const STDIN_PATH : &str = "-";
 
-fn from_line(line : &str) -> VecDeque<LexToken>
+/// A lexed token together with the source it was read from.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct Token
 {
-    return format_spacing(line).split_whitespace().map(|word| LexToken::from_str(word)).collect();
+    pub kind : LexToken,
+    pub span : Span,
 }
 
 #[derive(Debug,PartialEq,Eq,Clone)]
@@ -32,91 +25,490 @@ pub enum LexToken
     CloseBrace,
     OpenParenth,
     CloseParenth,
+    OpenBracket,
+    CloseBracket,
     Semicolon,
+    Comma,
+    Dot,
+    Arrow,
+    QuestionMark,
+    Colon,
     Int,
+    Char,
+    Short,
+    Long,
+    Signed,
+    Unsigned,
+    Bool,
+    Void,
+    Struct,
+    Union,
+    Enum,
+    Static,
+    Extern,
+    Typedef,
+    Sizeof,
     Return,
+    If,
+    Else,
+    While,
+    Do,
+    For,
+    Break,
+    Continue,
+    Switch,
+    Case,
+    Default,
+    Goto,
     Identifier(String),
     IntLiteral(String),
+    CharLiteral(String),
+    StringLiteral(String),
     Negation,
     BitwiseComplement,
     LogicalNegation,
     Addition,
     Multiplication,
     Division,
+    Modulo,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    LessThan,
+    GreaterThan,
+    LessThanOrEqual,
+    GreaterThanOrEqual,
+    Equal,
+    NotEqual,
+    LogicalAnd,
+    LogicalOr,
+    Increment,
+    Decrement,
+    Assignment,
+    AdditionAssign,
+    SubtractionAssign,
+    MultiplicationAssign,
+    DivisionAssign,
+    ModuloAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+    BitwiseAndAssign,
+    BitwiseOrAssign,
+    BitwiseXorAssign,
     Undefined
 }
 
 impl LexToken
 {
+    /// Lexes `lex_str` as exactly one token, `Undefined` if it is anything else.
+    #[cfg(test)]
     fn from_str(lex_str : &str) -> LexToken
     {
-        match lex_str {
-            "{"         => return LexToken::OpenBrace,
-            "}"         => return LexToken::CloseBrace,
-            "("         => return LexToken::OpenParenth,
-            ")"         => return LexToken::CloseParenth,
-            ";"         => return LexToken::Semicolon,
+        let mut diagnostics = Diagnostics::new();
+        let mut scanner = Scanner::new(lex_str, FileId::default(), &mut diagnostics);
+        match (scanner.next(), scanner.next())
+        {
+            (Some(token), None) if token.span.len() == lex_str.len() => return token.kind,
+            _ => return LexToken::Undefined,
+        };
+    }
+
+    fn from_word(word : &str) -> LexToken
+    {
+        match word {
             "int"       => return LexToken::Int,
+            "char"      => return LexToken::Char,
+            "short"     => return LexToken::Short,
+            "long"      => return LexToken::Long,
+            "signed"    => return LexToken::Signed,
+            "unsigned"  => return LexToken::Unsigned,
+            "_Bool"     => return LexToken::Bool,
+            "void"      => return LexToken::Void,
+            "struct"    => return LexToken::Struct,
+            "union"     => return LexToken::Union,
+            "enum"      => return LexToken::Enum,
+            "typedef"   => return LexToken::Typedef,
+            "static"    => return LexToken::Static,
+            "extern"    => return LexToken::Extern,
+            "sizeof"    => return LexToken::Sizeof,
             "return"    => return LexToken::Return,
-            "-"         => return LexToken::Negation,
-            "~"         => return LexToken::BitwiseComplement,
-            "!"         => return LexToken::LogicalNegation,
-            "+"         => return LexToken::Addition,
-            "*"         => return LexToken::Multiplication,
-            "/"         => return LexToken::Division,
-            _           =>
-            {
-                if let Some(id) = Regex::new(r"[a-zA-Z]\w*").unwrap().captures(lex_str) 
-                {
-                    return LexToken::Identifier(id.get(0).unwrap().as_str().to_string());
-                }
-                else if let Some(int_literal) = Regex::new(r"[0-9]+").unwrap().captures(lex_str) 
-                {
-                    return LexToken::IntLiteral(int_literal.get(0).unwrap().as_str().to_string());
-                }
-            }
+            "if"        => return LexToken::If,
+            "else"      => return LexToken::Else,
+            "while"     => return LexToken::While,
+            "do"        => return LexToken::Do,
+            "for"       => return LexToken::For,
+            "break"     => return LexToken::Break,
+            "continue"  => return LexToken::Continue,
+            "switch"    => return LexToken::Switch,
+            "case"      => return LexToken::Case,
+            "default"   => return LexToken::Default,
+            "goto"      => return LexToken::Goto,
+            _           => return LexToken::Identifier(word.to_string()),
         }
-        return LexToken::Undefined;
     }
 
-    fn to_str(&self) -> Option<&str> {
+    pub fn to_str(&self) -> Option<&str> {
         match self {
             LexToken::OpenBrace                 => return Some("{"),
             LexToken::CloseBrace                => return Some("}"),
             LexToken::OpenParenth               => return Some("("),
             LexToken::CloseParenth              => return Some(")"),
+            LexToken::OpenBracket               => return Some("["),
+            LexToken::CloseBracket              => return Some("]"),
             LexToken::Semicolon                 => return Some(";"),
+            LexToken::Comma                     => return Some(","),
+            LexToken::Dot                       => return Some("."),
+            LexToken::Arrow                     => return Some("->"),
+            LexToken::QuestionMark              => return Some("?"),
+            LexToken::Colon                     => return Some(":"),
             LexToken::Int                       => return Some("int"),
+            LexToken::Char                      => return Some("char"),
+            LexToken::Short                     => return Some("short"),
+            LexToken::Long                      => return Some("long"),
+            LexToken::Signed                    => return Some("signed"),
+            LexToken::Unsigned                  => return Some("unsigned"),
+            LexToken::Bool                      => return Some("_Bool"),
+            LexToken::Void                      => return Some("void"),
+            LexToken::Struct                    => return Some("struct"),
+            LexToken::Union                     => return Some("union"),
+            LexToken::Enum                      => return Some("enum"),
+            LexToken::Typedef                   => return Some("typedef"),
+            LexToken::Static                    => return Some("static"),
+            LexToken::Extern                    => return Some("extern"),
+            LexToken::Sizeof                    => return Some("sizeof"),
             LexToken::Return                    => return Some("return"),
+            LexToken::If                        => return Some("if"),
+            LexToken::Else                      => return Some("else"),
+            LexToken::While                     => return Some("while"),
+            LexToken::Do                        => return Some("do"),
+            LexToken::For                       => return Some("for"),
+            LexToken::Break                     => return Some("break"),
+            LexToken::Continue                  => return Some("continue"),
+            LexToken::Switch                    => return Some("switch"),
+            LexToken::Case                      => return Some("case"),
+            LexToken::Default                   => return Some("default"),
+            LexToken::Goto                      => return Some("goto"),
             LexToken::Negation                  => return Some("-"),
             LexToken::BitwiseComplement         => return Some("~"),
             LexToken::LogicalNegation           => return Some("!"),
             LexToken::Identifier(id)    => return Some(id),
             LexToken::IntLiteral(int)   => return Some(int),
+            LexToken::CharLiteral(chr)  => return Some(chr),
+            LexToken::StringLiteral(string) => return Some(string),
             LexToken::Addition                  => return Some("+"),
             LexToken::Multiplication            => return Some("*"),
             LexToken::Division                  => return Some("/"),
+            LexToken::Modulo                    => return Some("%"),
+            LexToken::BitwiseAnd                => return Some("&"),
+            LexToken::BitwiseOr                 => return Some("|"),
+            LexToken::BitwiseXor                => return Some("^"),
+            LexToken::ShiftLeft                 => return Some("<<"),
+            LexToken::ShiftRight                => return Some(">>"),
+            LexToken::LessThan                  => return Some("<"),
+            LexToken::GreaterThan               => return Some(">"),
+            LexToken::LessThanOrEqual           => return Some("<="),
+            LexToken::GreaterThanOrEqual        => return Some(">="),
+            LexToken::Equal                     => return Some("=="),
+            LexToken::NotEqual                  => return Some("!="),
+            LexToken::LogicalAnd                => return Some("&&"),
+            LexToken::LogicalOr                 => return Some("||"),
+            LexToken::Increment                 => return Some("++"),
+            LexToken::Decrement                 => return Some("--"),
+            LexToken::Assignment                => return Some("="),
+            LexToken::AdditionAssign            => return Some("+="),
+            LexToken::SubtractionAssign         => return Some("-="),
+            LexToken::MultiplicationAssign      => return Some("*="),
+            LexToken::DivisionAssign            => return Some("/="),
+            LexToken::ModuloAssign              => return Some("%="),
+            LexToken::ShiftLeftAssign           => return Some("<<="),
+            LexToken::ShiftRightAssign          => return Some(">>="),
+            LexToken::BitwiseAndAssign          => return Some("&="),
+            LexToken::BitwiseOrAssign           => return Some("|="),
+            LexToken::BitwiseXorAssign          => return Some("^="),
             LexToken::Undefined => return None
         }
     }
 }
 
 
+/// Single pass scanner over the characters of one source file. Every token is
+/// recognised by looking at most one character ahead, always taking the
+/// longest operator that matches (`<<=` rather than `<<` then `=`).
+struct Scanner<'a>
+{
+    source : &'a str,
+    chars : Peekable<CharIndices<'a>>,
+    file : FileId,
+    diagnostics : &'a mut Diagnostics,
+}
 
-pub fn lex(file_path : &str) -> VecDeque<LexToken> 
+impl<'a> Scanner<'a>
 {
-    let mut lex_vec = VecDeque::new();
-    if let Ok(lines) = read_lines(file_path)
+    fn new(source : &'a str, file : FileId, diagnostics : &'a mut Diagnostics) -> Scanner<'a>
+    {
+        return Scanner{source, chars: source.char_indices().peekable(), file, diagnostics};
+    }
+
+    fn position(&mut self) -> usize
+    {
+        match self.chars.peek()
+        {
+            Some((pos, _)) => return *pos,
+            None => return self.source.len(),
+        };
+    }
+
+    fn peek(&mut self) -> Option<char>
+    {
+        return self.chars.peek().map(|(_, c)| *c);
+    }
+
+    fn eat(&mut self, expected : char) -> bool
+    {
+        if self.peek() == Some(expected)
+        {
+            self.chars.next();
+            return true;
+        }
+        return false;
+    }
+
+    fn eat_while(&mut self, predicate : fn(char) -> bool)
+    {
+        while self.peek().is_some_and(predicate)
+        {
+            self.chars.next();
+        }
+    }
+
+    /// Skips whitespace, `// line` comments and `/* block */` comments.
+    fn skip_trivia(&mut self)
+    {
+        loop
+        {
+            self.eat_while(|c| c.is_ascii_whitespace() || c == '\x0b');
+            let lo = self.position();
+            let rest = &self.source[lo..];
+            if rest.starts_with("//")
+            {
+                self.eat_while(|c| c != '\n');
+            }
+            else if let Some(body) = rest.strip_prefix("/*")
+            {
+                match body.find("*/")
+                {
+                    Some(end) => self.skip_to(lo + 2 + end + 2),
+                    None => {
+                        self.diagnostics.emit(Diagnostic::error(E_UNTERMINATED_COMMENT, "unterminated block comment")
+                                                .with_primary(Span::new(self.file, lo, lo + 2), "comment starts here")
+                                                .with_note("block comments end with `*/`"));
+                        self.skip_to(self.source.len());
+                    },
+                }
+            }
+            else
+            {
+                return;
+            }
+        }
+    }
+
+    fn skip_to(&mut self, offset : usize)
     {
-        for line in lines
+        while self.position() < offset
         {
-            if let Ok(l) = line
+            self.chars.next();
+        }
+    }
+
+    /// Consumes the rest of a character constant or string literal opened by
+    /// `quote` at `lo`. Escape sequences are skipped over here and decoded by
+    /// the parser; a literal may not continue past the end of its line.
+    fn quoted(&mut self, quote : char, lo : usize)
+    {
+        loop
+        {
+            match self.peek()
             {
-                lex_vec.append(&mut from_line(&l));
+                Some(c) if c == quote => {
+                    self.chars.next();
+                    return;
+                },
+                Some('\\') => {
+                    self.chars.next();
+                    if self.peek().is_some_and(|c| c != '\n')
+                    {
+                        self.chars.next();
+                    }
+                },
+                Some('\n') | None => {
+                    let what = if quote == '"' { "string literal" } else { "character constant" };
+                    let span = Span::new(self.file, lo, self.position());
+                    self.diagnostics.emit(Diagnostic::error(E_UNTERMINATED_LITERAL, &format!("unterminated {}", what))
+                                            .with_primary(span, &format!("missing closing {}", quote)));
+                    return;
+                },
+                Some(_) => {
+                    self.chars.next();
+                },
             }
         }
     }
-    return lex_vec
+
+    /// `single`, or `with_equal` when followed by `=`.
+    fn either(&mut self, with_equal : LexToken, single : LexToken) -> LexToken
+    {
+        if self.eat('=')
+        {
+            return with_equal;
+        }
+        return single;
+    }
+
+    fn punctuator(&mut self, first : char) -> LexToken
+    {
+        match first
+        {
+            '{' => return LexToken::OpenBrace,
+            '}' => return LexToken::CloseBrace,
+            '(' => return LexToken::OpenParenth,
+            ')' => return LexToken::CloseParenth,
+            '[' => return LexToken::OpenBracket,
+            ']' => return LexToken::CloseBracket,
+            ';' => return LexToken::Semicolon,
+            ',' => return LexToken::Comma,
+            '.' => return LexToken::Dot,
+            '?' => return LexToken::QuestionMark,
+            ':' => return LexToken::Colon,
+            '~' => return LexToken::BitwiseComplement,
+            '!' => return self.either(LexToken::NotEqual, LexToken::LogicalNegation),
+            '=' => return self.either(LexToken::Equal, LexToken::Assignment),
+            '*' => return self.either(LexToken::MultiplicationAssign, LexToken::Multiplication),
+            '/' => return self.either(LexToken::DivisionAssign, LexToken::Division),
+            '%' => return self.either(LexToken::ModuloAssign, LexToken::Modulo),
+            '^' => return self.either(LexToken::BitwiseXorAssign, LexToken::BitwiseXor),
+            '+' => {
+                if self.eat('+')
+                {
+                    return LexToken::Increment;
+                }
+                return self.either(LexToken::AdditionAssign, LexToken::Addition);
+            },
+            '-' => {
+                if self.eat('-')
+                {
+                    return LexToken::Decrement;
+                }
+                if self.eat('>')
+                {
+                    return LexToken::Arrow;
+                }
+                return self.either(LexToken::SubtractionAssign, LexToken::Negation);
+            },
+            '&' => {
+                if self.eat('&')
+                {
+                    return LexToken::LogicalAnd;
+                }
+                return self.either(LexToken::BitwiseAndAssign, LexToken::BitwiseAnd);
+            },
+            '|' => {
+                if self.eat('|')
+                {
+                    return LexToken::LogicalOr;
+                }
+                return self.either(LexToken::BitwiseOrAssign, LexToken::BitwiseOr);
+            },
+            '<' => {
+                if self.eat('<')
+                {
+                    return self.either(LexToken::ShiftLeftAssign, LexToken::ShiftLeft);
+                }
+                return self.either(LexToken::LessThanOrEqual, LexToken::LessThan);
+            },
+            '>' => {
+                if self.eat('>')
+                {
+                    return self.either(LexToken::ShiftRightAssign, LexToken::ShiftRight);
+                }
+                return self.either(LexToken::GreaterThanOrEqual, LexToken::GreaterThan);
+            },
+            _ => return LexToken::Undefined,
+        };
+    }
+}
+
+fn is_identifier_char(c : char) -> bool
+{
+    return c.is_ascii_alphanumeric() || c == '_';
+}
+
+impl Iterator for Scanner<'_>
+{
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token>
+    {
+        self.skip_trivia();
+        let (lo, first) = self.chars.next()?;
+        let kind = if first.is_ascii_alphabetic() || first == '_'
+        {
+            self.eat_while(is_identifier_char);
+            let hi = self.position();
+            LexToken::from_word(&self.source[lo..hi])
+        }
+        else if first.is_ascii_digit()
+        {
+            // Like a C preprocessing number, letters glued to the digits belong
+            // to the literal, so `3abc` is one (invalid) constant.
+            self.eat_while(is_identifier_char);
+            let hi = self.position();
+            LexToken::IntLiteral(self.source[lo..hi].to_string())
+        }
+        else if first == '\'' || first == '"'
+        {
+            self.quoted(first, lo);
+            let hi = self.position();
+            let spelling = self.source[lo..hi].to_string();
+            if first == '"' { LexToken::StringLiteral(spelling) } else { LexToken::CharLiteral(spelling) }
+        }
+        else
+        {
+            self.punctuator(first)
+        };
+        let hi = self.position();
+        return Some(Token{kind, span: Span::new(self.file, lo, hi)});
+    }
+}
+
+
+/// Lexes in-memory source text, registering it in `source_map` under `name`.
+pub fn lex_str(name : &str, source : &str, source_map : &mut SourceMap, diagnostics : &mut Diagnostics) -> VecDeque<Token>
+{
+    let file = source_map.add_file(name, source.to_string());
+    return Scanner::new(&source_map.file(file).source, file, diagnostics).collect();
+}
+
+/// Reads all of `reader` and lexes it. Fails if reading fails or the input is
+/// not valid UTF-8.
+pub fn lex_reader<R : Read>(name : &str, mut reader : R, source_map : &mut SourceMap, diagnostics : &mut Diagnostics) -> io::Result<VecDeque<Token>>
+{
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+    let file = source_map.add_file(name, source);
+    return Ok(Scanner::new(&source_map.file(file).source, file, diagnostics).collect());
+}
+
+/// Lexes the file at `file_path`, or standard input when the path is `-`.
+pub fn lex(file_path : &str, source_map : &mut SourceMap, diagnostics : &mut Diagnostics) -> io::Result<VecDeque<Token>>
+{
+    if file_path == STDIN_PATH
+    {
+        return lex_reader("<stdin>", io::stdin().lock(), source_map, diagnostics);
+    }
+    return lex_reader(file_path, fs::File::open(file_path)?, source_map, diagnostics);
 }
 
 
@@ -125,6 +517,16 @@ mod test
 {
     use super::*;
 
+    fn scan(source : &str, file : FileId) -> VecDeque<Token>
+    {
+        return Scanner::new(source, file, &mut Diagnostics::new()).collect();
+    }
+
+    fn kinds(tokens : VecDeque<Token>) -> VecDeque<LexToken>
+    {
+        return tokens.into_iter().map(|token| token.kind).collect();
+    }
+
     fn generate_stage_2_vec(un_oper : LexToken, int_literal: &str) -> VecDeque<LexToken>
     {
         let mut stage2_vec = VecDeque::new();
@@ -135,7 +537,7 @@ mod test
         stage2_vec.push_back(LexToken::OpenBrace);
         stage2_vec.push_back(LexToken::Return);
         stage2_vec.push_back(un_oper);
-        stage2_vec.push_back(LexToken::IntLiteral(String::from(int_literal.to_string())));
+        stage2_vec.push_back(LexToken::IntLiteral(String::from(int_literal)));
         stage2_vec.push_back(LexToken::Semicolon);
         stage2_vec.push_back(LexToken::CloseBrace);
         return stage2_vec;
@@ -150,39 +552,55 @@ mod test
         stage3_vec.push_back(LexToken::CloseParenth);
         stage3_vec.push_back(LexToken::OpenBrace);
         stage3_vec.push_back(LexToken::Return);
-        stage3_vec.push_back(LexToken::IntLiteral(String::from(int_literal.to_string())));
+        stage3_vec.push_back(LexToken::IntLiteral(String::from(int_literal)));
         stage3_vec.push_back(oper);
-        stage3_vec.push_back(LexToken::IntLiteral(String::from(int_literal.to_string())));
+        stage3_vec.push_back(LexToken::IntLiteral(String::from(int_literal)));
         stage3_vec.push_back(LexToken::Semicolon);
         stage3_vec.push_back(LexToken::CloseBrace);
         return stage3_vec;
     }
 
     #[test]
-    fn test_simple_format_spacing()
+    fn test_maximal_munch()
     {
-        assert_eq!(format_spacing("hello;"), "hello ; ");
-        assert_eq!(format_spacing("hello}"), "hello } ");
-        assert_eq!(format_spacing("hello{"), "hello { ");
-        assert_eq!(format_spacing("hello)"), "hello ) ");
-        assert_eq!(format_spacing("hello("), "hello ( ");
-        assert_eq!(format_spacing("!hello"), " ! hello");
-        assert_eq!(format_spacing("~hello"), " ~ hello");
-        assert_eq!(format_spacing("-hello"), " - hello");
+        assert_eq!(kinds(scan("a<<=b<=c<d", FileId(0))), vec!(LexToken::Identifier(String::from("a")),
+                    LexToken::ShiftLeftAssign, LexToken::Identifier(String::from("b")),
+                    LexToken::LessThanOrEqual, LexToken::Identifier(String::from("c")),
+                    LexToken::LessThan, LexToken::Identifier(String::from("d"))));
+        assert_eq!(kinds(scan("&&&|||", FileId(0))), vec!(LexToken::LogicalAnd, LexToken::BitwiseAnd,
+                    LexToken::LogicalOr, LexToken::BitwiseOr));
+        assert_eq!(kinds(scan("p->x--- -i++", FileId(0))), vec!(LexToken::Identifier(String::from("p")),
+                    LexToken::Arrow, LexToken::Identifier(String::from("x")), LexToken::Decrement,
+                    LexToken::Negation, LexToken::Negation, LexToken::Identifier(String::from("i")),
+                    LexToken::Increment));
+        assert_eq!(kinds(scan(">>= >> >= > == = != !", FileId(0))), vec!(LexToken::ShiftRightAssign,
+                    LexToken::ShiftRight, LexToken::GreaterThanOrEqual, LexToken::GreaterThan,
+                    LexToken::Equal, LexToken::Assignment, LexToken::NotEqual, LexToken::LogicalNegation));
     }
-    
+
     #[test]
-    fn test_format_spacing()
+    fn test_identifier_and_number_boundaries()
     {
-        assert_eq!(format_spacing("-!~{hello"), " -  !  ~  { hello");
-        assert_eq!(format_spacing("void main(){return 3;}"), "void main (  )  { return 3 ;  } ");
-        assert_eq!(format_spacing("int main(int argc) {return ~1; }"),
-                     "int main ( int argc )   { return  ~ 1 ;   } ");
+        assert_eq!(kinds(scan("3abc", FileId(0))), vec!(LexToken::IntLiteral(String::from("3abc"))));
+        assert_eq!(kinds(scan("a.b", FileId(0))), vec!(LexToken::Identifier(String::from("a")),
+                    LexToken::Dot, LexToken::Identifier(String::from("b"))));
+        assert_eq!(kinds(scan("_x1 returns int0 return", FileId(0))), vec!(LexToken::Identifier(String::from("_x1")),
+                    LexToken::Identifier(String::from("returns")), LexToken::Identifier(String::from("int0")),
+                    LexToken::Return));
+        assert_eq!(kinds(scan("1@2", FileId(0))), vec!(LexToken::IntLiteral(String::from("1")),
+                    LexToken::Undefined, LexToken::IntLiteral(String::from("2"))));
     }
-    
-    
+
+    #[test]
+    fn test_multi_line_spans()
+    {
+        let tokens = scan("int\n\tmain", FileId(0));
+        let spans : Vec<Span> = tokens.iter().map(|token| token.span).collect();
+        assert_eq!(spans, vec!(Span::new(FileId(0), 0, 3), Span::new(FileId(0), 5, 9)));
+    }
+
     #[test]
-    fn test_stage1_from_line()
+    fn test_stage1_scan()
     {
         let mut stage1_vec = VecDeque::new();
         stage1_vec.push_back(LexToken::Int);
@@ -194,29 +612,40 @@ mod test
         stage1_vec.push_back(LexToken::IntLiteral(String::from("3")));
         stage1_vec.push_back(LexToken::Semicolon);
         stage1_vec.push_back(LexToken::CloseBrace);
-        assert_eq!(from_line("int main(){return 3;}"), stage1_vec)
+        assert_eq!(kinds(scan("int main(){return 3;}", FileId(0))), stage1_vec)
     }
 
     #[test]
-    fn test_stage2_from_line()
+    fn test_stage2_scan()
     {
-        assert_eq!(from_line("int main(){return !3;}"), generate_stage_2_vec(LexToken::LogicalNegation, "3"));
-        assert_eq!(from_line("int main(){return -3;}"), generate_stage_2_vec(LexToken::Negation, "3"));
-        assert_eq!(from_line("int main(){return ~3;}"), generate_stage_2_vec(LexToken::BitwiseComplement, "3"));
+        assert_eq!(kinds(scan("int main(){return !3;}", FileId(0))), generate_stage_2_vec(LexToken::LogicalNegation, "3"));
+        assert_eq!(kinds(scan("int main(){return -3;}", FileId(0))), generate_stage_2_vec(LexToken::Negation, "3"));
+        assert_eq!(kinds(scan("int main(){return ~3;}", FileId(0))), generate_stage_2_vec(LexToken::BitwiseComplement, "3"));
     }
 
     #[test]
-    fn test_stage3_from_line()
+    fn test_stage3_scan()
     {
-        assert_eq!(from_line("int main(){return 2+2;}"), generate_stage_3_vec(LexToken::Addition,"2"));
-        assert_eq!(from_line("int main(){return 2 + 2;}"), generate_stage_3_vec(LexToken::Addition,"2"));
-        assert_eq!(from_line("int main(){return 2*2;}"), generate_stage_3_vec(LexToken::Multiplication,"2"));
-        assert_eq!(from_line("int main(){return 2 * 2;}"), generate_stage_3_vec(LexToken::Multiplication,"2"));
-        assert_eq!(from_line("int main(){return 2/2;}"), generate_stage_3_vec(LexToken::Division,"2"));
-        assert_eq!(from_line("int main(){return 2 / 2;}"), generate_stage_3_vec(LexToken::Division,"2"));
+        assert_eq!(kinds(scan("int main(){return 2+2;}", FileId(0))), generate_stage_3_vec(LexToken::Addition,"2"));
+        assert_eq!(kinds(scan("int main(){return 2 + 2;}", FileId(0))), generate_stage_3_vec(LexToken::Addition,"2"));
+        assert_eq!(kinds(scan("int main(){return 2*2;}", FileId(0))), generate_stage_3_vec(LexToken::Multiplication,"2"));
+        assert_eq!(kinds(scan("int main(){return 2 * 2;}", FileId(0))), generate_stage_3_vec(LexToken::Multiplication,"2"));
+        assert_eq!(kinds(scan("int main(){return 2/2;}", FileId(0))), generate_stage_3_vec(LexToken::Division,"2"));
+        assert_eq!(kinds(scan("int main(){return 2 / 2;}", FileId(0))), generate_stage_3_vec(LexToken::Division,"2"));
     }
 
 
+    #[test]
+    fn test_scan_spans()
+    {
+        let tokens = scan("  return -32;", FileId(1));
+        let spans : Vec<Span> = tokens.iter().map(|token| token.span).collect();
+        assert_eq!(spans, vec!(Span::new(FileId(1), 2, 8),
+                                Span::new(FileId(1), 9, 10),
+                                Span::new(FileId(1), 10, 12),
+                                Span::new(FileId(1), 12, 13)));
+    }
+
     #[test]
     fn test_from_str() 
     {
@@ -227,6 +656,19 @@ mod test
         assert_eq!(LexToken::Semicolon, LexToken::from_str(";"));
         assert_eq!(LexToken::Int, LexToken::from_str("int"));
         assert_eq!(LexToken::Return, LexToken::from_str("return"));
+        assert_eq!(LexToken::If, LexToken::from_str("if"));
+        assert_eq!(LexToken::Else, LexToken::from_str("else"));
+        assert_eq!(LexToken::Do, LexToken::from_str("do"));
+        assert_eq!(LexToken::Unsigned, LexToken::from_str("unsigned"));
+        assert_eq!(LexToken::Bool, LexToken::from_str("_Bool"));
+        assert_eq!(LexToken::Sizeof, LexToken::from_str("sizeof"));
+        assert_eq!(LexToken::Struct, LexToken::from_str("struct"));
+        assert_eq!(LexToken::Union, LexToken::from_str("union"));
+        assert_eq!(LexToken::Enum, LexToken::from_str("enum"));
+        assert_eq!(LexToken::Typedef, LexToken::from_str("typedef"));
+        assert_eq!(LexToken::Identifier(String::from("bool")), LexToken::from_str("bool"));
+        assert_eq!(LexToken::Identifier(String::from("double")), LexToken::from_str("double"));
+        assert_eq!(LexToken::Identifier(String::from("iffy")), LexToken::from_str("iffy"));
         assert_eq!(LexToken::Negation, LexToken::from_str("-"));
         assert_eq!(LexToken::BitwiseComplement, LexToken::from_str("~"));
         assert_eq!(LexToken::LogicalNegation, LexToken::from_str("!"));
@@ -245,6 +687,7 @@ mod test
         assert_eq!(LexToken::Semicolon.to_str(), Some(";"));
         assert_eq!(LexToken::Int.to_str(), Some("int"));
         assert_eq!(LexToken::Return.to_str(), Some("return"));
+        assert_eq!(LexToken::Bool.to_str(), Some("_Bool"));
         assert_eq!(LexToken::Negation.to_str(), Some("-"));
         assert_eq!(LexToken::BitwiseComplement.to_str(), Some("~"));
         assert_eq!(LexToken::LogicalNegation.to_str(), Some("!"));
@@ -254,4 +697,93 @@ mod test
         assert_eq!(LexToken::Undefined.to_str(),None);
     }
     
-}
\ No newline at end of file
+
+    #[test]
+    fn test_lex_str()
+    {
+        let mut source_map = SourceMap::new();
+        let tokens = lex_str("inline.c", "return 2;", &mut source_map, &mut Diagnostics::new());
+        assert_eq!(kinds(tokens.clone()), vec!(LexToken::Return, LexToken::IntLiteral(String::from("2")), LexToken::Semicolon));
+        assert_eq!(source_map.file(tokens[0].span.file).name, "inline.c");
+        assert_eq!(source_map.snippet(tokens[1].span), "2");
+    }
+
+    #[test]
+    fn test_lex_reader()
+    {
+        let mut source_map = SourceMap::new();
+        lex_str("first.c", "int", &mut source_map, &mut Diagnostics::new());
+        let tokens = lex_reader("second.c", io::Cursor::new("int main"), &mut source_map, &mut Diagnostics::new()).unwrap();
+        assert_eq!(kinds(tokens.clone()), vec!(LexToken::Int, LexToken::Identifier(String::from("main"))));
+        assert_eq!(tokens[1].span, Span::new(FileId(1), 4, 8));
+    }
+
+    #[test]
+    fn test_lex_reader_invalid_utf8()
+    {
+        let mut source_map = SourceMap::new();
+        let result = lex_reader("bad.c", io::Cursor::new(vec!(0x69, 0xff, 0x6e)), &mut source_map, &mut Diagnostics::new());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_lex_missing_file()
+    {
+        let mut source_map = SourceMap::new();
+        let result = lex("this/file/does/not/exist.c", &mut source_map, &mut Diagnostics::new());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_comments()
+    {
+        assert_eq!(kinds(scan("return // 1;\n 2; /* 3 */ /**/ 4 /* 5 \n * 6 */ / 7", FileId(0))),
+                    vec!(LexToken::Return, LexToken::IntLiteral(String::from("2")), LexToken::Semicolon,
+                        LexToken::IntLiteral(String::from("4")), LexToken::Division, LexToken::IntLiteral(String::from("7"))));
+        assert_eq!(kinds(scan("a/**/b//", FileId(0))), vec!(LexToken::Identifier(String::from("a")),
+                    LexToken::Identifier(String::from("b"))));
+        assert_eq!(kinds(scan("/*/ still a comment */x /= 2", FileId(0))), vec!(LexToken::Identifier(String::from("x")),
+                    LexToken::DivisionAssign, LexToken::IntLiteral(String::from("2"))));
+    }
+
+    #[test]
+    fn test_positions_after_multi_line_comment()
+    {
+        let mut source_map = SourceMap::new();
+        let tokens = lex_str("main.c", "/* one\ntwo\n */ int\n// x\nmain", &mut source_map, &mut Diagnostics::new());
+        let locations : Vec<Location> = tokens.iter().map(|token| source_map.location(token.span)).collect();
+        assert_eq!(locations, vec!(Location{line: 3, column: 5}, Location{line: 5, column: 1}));
+    }
+
+    #[test]
+    fn test_unterminated_block_comment()
+    {
+        let mut diagnostics = Diagnostics::new();
+        let tokens : Vec<Token> = Scanner::new("int /* never closed\n return", FileId(0), &mut diagnostics).collect();
+        assert_eq!(tokens.len(), 1);
+        let diag = diagnostics.iter().next().unwrap();
+        assert_eq!(diag.code, E_UNTERMINATED_COMMENT);
+        assert_eq!(diag.primary.as_ref().unwrap().span, Span::new(FileId(0), 4, 6));
+    }
+
+    #[test]
+    fn test_char_and_string_literals()
+    {
+        assert_eq!(kinds(scan(r#"'a' '\'' "x\"y" "" '\\'"#, FileId(0))), vec!(LexToken::CharLiteral(String::from("'a'")),
+                    LexToken::CharLiteral(String::from(r"'\''")), LexToken::StringLiteral(String::from(r#""x\"y""#)),
+                    LexToken::StringLiteral(String::from("\"\"")), LexToken::CharLiteral(String::from(r"'\\'"))));
+        assert_eq!(kinds(scan(r#""// not a comment" /* "*/ 'b'"#, FileId(0))), vec!(LexToken::StringLiteral(String::from("\"// not a comment\"")),
+                    LexToken::CharLiteral(String::from("'b'"))));
+    }
+
+    #[test]
+    fn test_unterminated_literals()
+    {
+        let mut diagnostics = Diagnostics::new();
+        let tokens : Vec<LexToken> = Scanner::new("\"abc\nreturn 'x", FileId(0), &mut diagnostics).map(|token| token.kind).collect();
+        assert_eq!(tokens, vec!(LexToken::StringLiteral(String::from("\"abc")), LexToken::Return,
+                                LexToken::CharLiteral(String::from("'x"))));
+        let messages : Vec<&str> = diagnostics.iter().map(|diag| diag.message.as_str()).collect();
+        assert_eq!(messages, vec!("unterminated string literal", "unterminated character constant"));
+    }
+}
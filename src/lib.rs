@@ -1,3 +1,10 @@
+#![allow(clippy::needless_return)]
+
 pub mod ast;
+pub mod diagnostics;
 pub mod lexxer;
-pub mod asm_generator;
\ No newline at end of file
+pub mod literal;
+pub mod source_map;
+pub mod symbol_table;
+pub mod types;
+pub mod asm_generator;
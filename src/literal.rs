@@ -0,0 +1,431 @@
+
+/// The C integer types an integer constant can have, sized for i386 where
+/// `int` and `long` are 32 bits and `long long` is 64 bits.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum IntegerType
+{
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+}
+
+impl IntegerType
+{
+    pub fn max_value(&self) -> u64
+    {
+        match self
+        {
+            IntegerType::Int | IntegerType::Long => return i32::MAX as u64,
+            IntegerType::UnsignedInt | IntegerType::UnsignedLong => return u32::MAX as u64,
+            IntegerType::LongLong => return i64::MAX as u64,
+            IntegerType::UnsignedLongLong => return u64::MAX,
+        };
+    }
+
+    pub fn is_unsigned(&self) -> bool
+    {
+        match self
+        {
+            IntegerType::UnsignedInt | IntegerType::UnsignedLong | IntegerType::UnsignedLongLong => return true,
+            _ => return false,
+        };
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            IntegerType::Int => return "int",
+            IntegerType::UnsignedInt => return "unsigned int",
+            IntegerType::Long => return "long",
+            IntegerType::UnsignedLong => return "unsigned long",
+            IntegerType::LongLong => return "long long",
+            IntegerType::UnsignedLongLong => return "unsigned long long",
+        };
+    }
+}
+
+/// A parsed integer constant such as `0x1Fu` or `42LL`.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct IntegerLiteral
+{
+    pub value : u64,
+    pub ty : IntegerType,
+    pub radix : u32,
+}
+
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub enum LiteralError
+{
+    /// The prefix (`0x`, `0b`) is not followed by any digit.
+    MissingDigits,
+    InvalidDigit(char, u32),
+    InvalidSuffix(String),
+    /// The value does not fit even in `unsigned long long`.
+    TooLarge,
+    EmptyCharacter,
+    /// `\x` not followed by a hexadecimal digit.
+    MissingHexDigits,
+    /// An octal or hexadecimal escape whose value does not fit in a `char`.
+    EscapeOutOfRange(String),
+}
+
+impl LiteralError
+{
+    pub fn message(&self, spelling : &str) -> String
+    {
+        match self
+        {
+            LiteralError::MissingDigits => return format!("integer constant `{}` has no digits", spelling),
+            LiteralError::InvalidDigit(digit, radix) => return format!("invalid digit `{}` in {} constant `{}`", digit, radix_name(*radix), spelling),
+            LiteralError::InvalidSuffix(suffix) => return format!("invalid suffix `{}` on integer constant `{}`", suffix, spelling),
+            LiteralError::TooLarge => return format!("integer constant `{}` is too large for any integer type", spelling),
+            LiteralError::EmptyCharacter => return String::from("empty character constant"),
+            LiteralError::MissingHexDigits => return format!("`\\x` used with no following hex digits in {}", spelling),
+            LiteralError::EscapeOutOfRange(escape) => return format!("escape sequence `{}` out of range", escape),
+        };
+    }
+}
+
+fn radix_name(radix : u32) -> &'static str
+{
+    match radix
+    {
+        2 => return "binary",
+        8 => return "octal",
+        16 => return "hexadecimal",
+        _ => return "decimal",
+    };
+}
+
+/// Parses the spelling of a C integer constant: decimal, octal (`017`),
+/// hexadecimal (`0x1F`) or binary (`0b101`, a GNU extension), followed by an
+/// optional `u`/`U` and `l`/`L`/`ll`/`LL` suffix in either order.
+///
+/// The type follows C11 6.4.4.1: the first type of the suffix's list that can
+/// represent the value, where octal, hexadecimal and binary constants may also
+/// become unsigned. A decimal constant too large for `long long` is given
+/// `unsigned long long`, like gcc does.
+pub fn parse_integer(spelling : &str) -> Result<IntegerLiteral, LiteralError>
+{
+    let lower = spelling.to_ascii_lowercase();
+    let (radix, body) = if lower.starts_with("0x")
+    {
+        (16, &spelling[2..])
+    }
+    else if lower.starts_with("0b")
+    {
+        (2, &spelling[2..])
+    }
+    else if spelling.starts_with('0')
+    {
+        (8, spelling)
+    }
+    else
+    {
+        (10, spelling)
+    };
+
+    // Everything up to the first character that is not a hex digit (or not a
+    // decimal digit for the other bases) is the number, the rest the suffix.
+    let digits_end = body.find(|c : char| if radix == 16 { !c.is_ascii_hexdigit() } else { !c.is_ascii_digit() })
+                        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(digits_end);
+    if digits.is_empty()
+    {
+        return Err(LiteralError::MissingDigits);
+    }
+
+    let mut value : u64 = 0;
+    let mut overflow = false;
+    for digit in digits.chars()
+    {
+        let digit_value = digit.to_digit(16).unwrap();
+        if digit_value >= radix
+        {
+            return Err(LiteralError::InvalidDigit(digit, radix));
+        }
+        match value.checked_mul(radix as u64).and_then(|value| value.checked_add(digit_value as u64))
+        {
+            Some(next) => value = next,
+            None => overflow = true,
+        }
+    }
+
+    let (unsigned, length) = parse_suffix(suffix).ok_or_else(|| LiteralError::InvalidSuffix(suffix.to_string()))?;
+    if overflow
+    {
+        return Err(LiteralError::TooLarge);
+    }
+
+    let candidates : &[IntegerType] = match (unsigned, length, radix == 10)
+    {
+        (false, 0, true) => &[IntegerType::Int, IntegerType::Long, IntegerType::LongLong],
+        (false, 0, false) => &[IntegerType::Int, IntegerType::UnsignedInt, IntegerType::Long, IntegerType::UnsignedLong,
+                                IntegerType::LongLong, IntegerType::UnsignedLongLong],
+        (true, 0, _) => &[IntegerType::UnsignedInt, IntegerType::UnsignedLong, IntegerType::UnsignedLongLong],
+        (false, 1, true) => &[IntegerType::Long, IntegerType::LongLong],
+        (false, 1, false) => &[IntegerType::Long, IntegerType::UnsignedLong, IntegerType::LongLong, IntegerType::UnsignedLongLong],
+        (true, 1, _) => &[IntegerType::UnsignedLong, IntegerType::UnsignedLongLong],
+        (false, _, true) => &[IntegerType::LongLong],
+        (false, _, false) => &[IntegerType::LongLong, IntegerType::UnsignedLongLong],
+        (true, _, _) => &[IntegerType::UnsignedLongLong],
+    };
+    let ty = candidates.iter().copied().find(|ty| value <= ty.max_value()).unwrap_or(IntegerType::UnsignedLongLong);
+    return Ok(IntegerLiteral{value, ty, radix});
+}
+
+/// Returns whether the suffix makes the constant unsigned and how many `l`s it
+/// has, or `None` if it is not a valid suffix.
+fn parse_suffix(suffix : &str) -> Option<(bool, usize)>
+{
+    let (unsigned, length) = match suffix
+    {
+        "" => (false, ""),
+        _ if suffix.starts_with(['u', 'U']) => (true, &suffix[1..]),
+        _ if suffix.ends_with(['u', 'U']) => (true, &suffix[..suffix.len() - 1]),
+        _ => (false, suffix),
+    };
+    match length
+    {
+        "" => return Some((unsigned, 0)),
+        "l" | "L" => return Some((unsigned, 1)),
+        "ll" | "LL" => return Some((unsigned, 2)),
+        _ => return None,
+    };
+}
+
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub enum LiteralWarning
+{
+    UnknownEscape(char),
+    MultiCharacter,
+    /// A multi-character constant with more characters than fit in an `int`.
+    CharacterTooLong,
+}
+
+impl LiteralWarning
+{
+    pub fn message(&self) -> String
+    {
+        match self
+        {
+            LiteralWarning::UnknownEscape(c) => return format!("unknown escape sequence `\\{}`", c),
+            LiteralWarning::MultiCharacter => return String::from("multi-character character constant"),
+            LiteralWarning::CharacterTooLong => return String::from("character constant too long for its type"),
+        };
+    }
+}
+
+/// The bytes a string literal or character constant stands for, once escape
+/// sequences are replaced by the characters they denote.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub struct DecodedText
+{
+    pub bytes : Vec<u8>,
+    pub warnings : Vec<LiteralWarning>,
+}
+
+/// Removes the quotes around the spelling of a literal. The closing quote may
+/// be missing when the literal was unterminated, which is reported by the lexer.
+pub fn literal_body(spelling : &str) -> &str
+{
+    let quote = &spelling[..1];
+    let body = &spelling[1..];
+    return body.strip_suffix(quote).unwrap_or(body);
+}
+
+/// Decodes the escape sequences of a literal's body (without the quotes).
+/// Characters outside ASCII are kept as their UTF-8 bytes.
+pub fn decode_escapes(body : &str) -> Result<DecodedText, LiteralError>
+{
+    let mut bytes = Vec::new();
+    let mut warnings = Vec::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next()
+    {
+        if c != '\\'
+        {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let escape = match chars.next()
+        {
+            Some(escape) => escape,
+            None => {
+                bytes.push(b'\\');
+                break;
+            },
+        };
+        match escape
+        {
+            'n' => bytes.push(b'\n'),
+            't' => bytes.push(b'\t'),
+            'r' => bytes.push(b'\r'),
+            'a' => bytes.push(0x07),
+            'b' => bytes.push(0x08),
+            'f' => bytes.push(0x0c),
+            'v' => bytes.push(0x0b),
+            'e' => bytes.push(0x1b),
+            '\\' | '\'' | '"' | '?' => bytes.push(escape as u8),
+            '0'..='7' => {
+                let mut digits = escape.to_string();
+                while digits.len() < 3 && chars.peek().is_some_and(|c| ('0'..='7').contains(c))
+                {
+                    digits.push(chars.next().unwrap());
+                }
+                let value = u32::from_str_radix(&digits, 8).unwrap();
+                if value > 0xff
+                {
+                    return Err(LiteralError::EscapeOutOfRange(format!("\\{}", digits)));
+                }
+                bytes.push(value as u8);
+            },
+            'x' => {
+                let mut digits = String::new();
+                while chars.peek().is_some_and(|c| c.is_ascii_hexdigit())
+                {
+                    digits.push(chars.next().unwrap());
+                }
+                if digits.is_empty()
+                {
+                    return Err(LiteralError::MissingHexDigits);
+                }
+                match u8::from_str_radix(&digits, 16)
+                {
+                    Ok(value) => bytes.push(value),
+                    Err(_) => return Err(LiteralError::EscapeOutOfRange(format!("\\x{}", digits))),
+                }
+            },
+            _ => {
+                warnings.push(LiteralWarning::UnknownEscape(escape));
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(escape.encode_utf8(&mut buffer).as_bytes());
+            },
+        }
+    }
+    return Ok(DecodedText{bytes, warnings});
+}
+
+/// Value of a character constant such as `'a'` or `'\n'`, which has type
+/// `int`. A single character is sign extended from a (signed) `char` as gcc
+/// does on i386; multi-character constants pack their characters big-endian
+/// into the `int`, keeping the last four.
+pub fn parse_char(spelling : &str) -> Result<(i32, Vec<LiteralWarning>), LiteralError>
+{
+    let mut decoded = decode_escapes(literal_body(spelling))?;
+    match decoded.bytes.len()
+    {
+        0 => return Err(LiteralError::EmptyCharacter),
+        1 => return Ok((decoded.bytes[0] as i8 as i32, decoded.warnings)),
+        len => {
+            decoded.warnings.push(if len > 4 { LiteralWarning::CharacterTooLong } else { LiteralWarning::MultiCharacter });
+            let value = decoded.bytes.iter().fold(0u32, |value, byte| value.wrapping_shl(8) | *byte as u32);
+            return Ok((value as i32, decoded.warnings));
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parsed(spelling : &str) -> (u64, IntegerType)
+    {
+        let literal = parse_integer(spelling).unwrap();
+        return (literal.value, literal.ty);
+    }
+
+    #[test]
+    fn radixes()
+    {
+        assert_eq!(parsed("0"), (0, IntegerType::Int));
+        assert_eq!(parsed("42"), (42, IntegerType::Int));
+        assert_eq!(parsed("017"), (15, IntegerType::Int));
+        assert_eq!(parsed("0x1F"), (31, IntegerType::Int));
+        assert_eq!(parsed("0XfF"), (255, IntegerType::Int));
+        assert_eq!(parsed("0b101"), (5, IntegerType::Int));
+        assert_eq!(parsed("0B11"), (3, IntegerType::Int));
+    }
+
+    #[test]
+    fn suffixes()
+    {
+        assert_eq!(parsed("1u"), (1, IntegerType::UnsignedInt));
+        assert_eq!(parsed("1L"), (1, IntegerType::Long));
+        assert_eq!(parsed("1ul"), (1, IntegerType::UnsignedLong));
+        assert_eq!(parsed("1LU"), (1, IntegerType::UnsignedLong));
+        assert_eq!(parsed("1ll"), (1, IntegerType::LongLong));
+        assert_eq!(parsed("1uLL"), (1, IntegerType::UnsignedLongLong));
+        assert_eq!(parsed("0x10ULL"), (16, IntegerType::UnsignedLongLong));
+    }
+
+    #[test]
+    fn types_follow_value()
+    {
+        assert_eq!(parsed("2147483647"), (2147483647, IntegerType::Int));
+        assert_eq!(parsed("2147483648"), (2147483648, IntegerType::LongLong));
+        assert_eq!(parsed("0x80000000"), (0x80000000, IntegerType::UnsignedInt));
+        assert_eq!(parsed("0x100000000"), (0x100000000, IntegerType::LongLong));
+        assert_eq!(parsed("4294967295u"), (4294967295, IntegerType::UnsignedInt));
+        assert_eq!(parsed("4294967296u"), (4294967296, IntegerType::UnsignedLongLong));
+        assert_eq!(parsed("2147483648L"), (2147483648, IntegerType::LongLong));
+        assert_eq!(parsed("0xFFFFFFFFl"), (0xFFFFFFFF, IntegerType::UnsignedLong));
+        assert_eq!(parsed("0x8000000000000000"), (0x8000000000000000, IntegerType::UnsignedLongLong));
+        assert_eq!(parsed("9223372036854775808"), (9223372036854775808, IntegerType::UnsignedLongLong));
+        assert_eq!(parsed("18446744073709551615"), (u64::MAX, IntegerType::UnsignedLongLong));
+    }
+
+    #[test]
+    fn errors()
+    {
+        assert_eq!(parse_integer("0x"), Err(LiteralError::MissingDigits));
+        assert_eq!(parse_integer("0b"), Err(LiteralError::MissingDigits));
+        assert_eq!(parse_integer("09"), Err(LiteralError::InvalidDigit('9', 8)));
+        assert_eq!(parse_integer("0b102"), Err(LiteralError::InvalidDigit('2', 2)));
+        assert_eq!(parse_integer("3abc"), Err(LiteralError::InvalidSuffix(String::from("abc"))));
+        assert_eq!(parse_integer("1lL"), Err(LiteralError::InvalidSuffix(String::from("lL"))));
+        assert_eq!(parse_integer("1uu"), Err(LiteralError::InvalidSuffix(String::from("uu"))));
+        assert_eq!(parse_integer("1lul"), Err(LiteralError::InvalidSuffix(String::from("lul"))));
+        assert_eq!(parse_integer("18446744073709551616"), Err(LiteralError::TooLarge));
+    }
+
+    fn decoded(body : &str) -> Vec<u8>
+    {
+        return decode_escapes(body).unwrap().bytes;
+    }
+
+    #[test]
+    fn escapes()
+    {
+        assert_eq!(decoded(r"a\nb"), b"a\nb");
+        assert_eq!(decoded(r#"\t\r\a\b\f\v\\\'\"\?"#), b"\t\r\x07\x08\x0c\x0b\\'\"?");
+        assert_eq!(decoded(r"\0"), b"\0");
+        assert_eq!(decoded(r"\101\1012"), b"AA2");
+        assert_eq!(decoded(r"\x41\x4a"), b"AJ");
+        assert_eq!(decoded("é"), "é".as_bytes());
+        assert_eq!(decode_escapes(r"\q").unwrap().warnings, vec!(LiteralWarning::UnknownEscape('q')));
+        assert_eq!(decode_escapes(r"\x"), Err(LiteralError::MissingHexDigits));
+        assert_eq!(decode_escapes(r"\x100"), Err(LiteralError::EscapeOutOfRange(String::from(r"\x100"))));
+        assert_eq!(decode_escapes(r"\777"), Err(LiteralError::EscapeOutOfRange(String::from(r"\777"))));
+    }
+
+    #[test]
+    fn characters()
+    {
+        assert_eq!(parse_char("'a'"), Ok((97, vec!())));
+        assert_eq!(parse_char(r"'\n'"), Ok((10, vec!())));
+        assert_eq!(parse_char(r"'\0'"), Ok((0, vec!())));
+        assert_eq!(parse_char(r"'\x41'"), Ok((65, vec!())));
+        assert_eq!(parse_char(r"'\377'"), Ok((-1, vec!())));
+        assert_eq!(parse_char("'ab'"), Ok((0x6162, vec!(LiteralWarning::MultiCharacter))));
+        assert_eq!(parse_char("'abcde'"), Ok((0x62636465, vec!(LiteralWarning::CharacterTooLong))));
+        assert_eq!(parse_char("''"), Err(LiteralError::EmptyCharacter));
+        assert_eq!(parse_char("'a"), Ok((97, vec!())));
+    }
+}
@@ -1,41 +1,85 @@
 use std::env;
 use std::fs;
 use std::io::prelude::*;
-use std::process::Command;
+use std::process::{self, Command};
 
-use crate::asm_generator::generate_asm;
-
-mod ast;
-mod lexxer;
-mod asm_generator;
+use tcc::asm_generator::generate_asm;
+use tcc::ast;
+use tcc::diagnostics::*;
+use tcc::lexxer;
+use tcc::source_map::SourceMap;
 
 
 
 
 fn main(){
     let args: Vec<String> = env::args().collect();
-    let mut lexxed_file = lexxer::lex(&args[1]);
-    
-    let ast_program = match ast::Program::new(&mut lexxed_file)
+    if args.len() < 2
+    {
+        eprintln!("usage: {} <file.c | ->", args[0]);
+        process::exit(2);
+    }
+    let mut diagnostics = Diagnostics::new();
+    let mut source_map = SourceMap::new();
+    let mut lexxed_file = match lexxer::lex(&args[1], &mut source_map, &mut diagnostics)
     {
-        Some(it) => it,
-        _ => return,
+        Ok(tokens) => tokens,
+        Err(err) => {
+            eprint!("{}", Diagnostic::error(E_IO, &format!("could not read `{}`: {}", args[1], err)).render(&source_map));
+            process::exit(1);
+        },
+    };
+
+    let ast_program = ast::Program::new(&mut lexxed_file, &mut diagnostics);
+    if !diagnostics.is_empty()
+    {
+        eprint!("{}", diagnostics.render(&source_map));
+    }
+    let ast_program = match ast_program
+    {
+        Some(it) if !diagnostics.has_errors() => it,
+        _ => {
+            eprintln!("error: aborting due to {} previous error(s)", diagnostics.error_count());
+            process::exit(1);
+        },
     };
 
     let asm_string = generate_asm(&ast_program);
-    let last_dot_pos = args[1].rfind('.').unwrap();
-    let mut out_file_path = args[1][..last_dot_pos].to_string();
-    out_file_path += ".s";
+    // Like gcc, a program read from stdin is written to `a.s` and `a.out`.
+    let (out_base, out_binary) = if args[1] == lexxer::STDIN_PATH
+    {
+        (String::from("a"), String::from("a.out"))
+    }
+    else
+    {
+        let base = args[1].rfind('.').map_or(args[1].as_str(), |last_dot_pos| &args[1][..last_dot_pos]).to_string();
+        (base.clone(), base)
+    };
+    let out_file_path = out_base + ".s";
     let mut out_file = fs::File::create(&out_file_path).unwrap();
     out_file.write_all(asm_string.as_bytes()).unwrap();
 
-    Command::new("gcc")
+    // The generated code uses absolute addresses, so it can't be position independent.
+    let output = Command::new("gcc")
                     .arg("-m32")
+                    .arg("-no-pie")
                     .arg(&out_file_path)
                     .arg("-o")
-                    .arg(&args[1][..last_dot_pos])
-                    .output()
-                    .expect("Failed to execute gcc command");
+                    .arg(&out_binary)
+                    .output();
+    let output = match output
+    {
+        Ok(output) => output,
+        Err(err) => {
+            eprint!("{}", Diagnostic::error(E_IO, &format!("could not run `gcc`: {}", err)).render(&source_map));
+            process::exit(1);
+        },
+    };
+    if !output.status.success()
+    {
+        std::io::stderr().write_all(&output.stderr).unwrap();
+        eprint!("{}", Diagnostic::error(E_LINK, &format!("assembling and linking `{}` with `gcc` failed ({})", out_file_path, output.status))
+                        .render(&source_map));
+        process::exit(1);
+    }
 }
-
-
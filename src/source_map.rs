@@ -0,0 +1,174 @@
+/// Identifies a file registered in a `SourceMap`.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Default,Hash)]
+pub struct FileId(pub usize);
+
+/// A byte range `[lo, hi)` inside one source file.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Default,Hash)]
+pub struct Span
+{
+    pub file : FileId,
+    pub lo : usize,
+    pub hi : usize,
+}
+
+impl Span
+{
+    pub fn new(file : FileId, lo : usize, hi : usize) -> Span
+    {
+        return Span{file, lo, hi};
+    }
+
+    /// Span covering everything from the start of `self` to the end of `other`.
+    pub fn to(&self, other : Span) -> Span
+    {
+        return Span{file: self.file, lo: self.lo.min(other.lo), hi: self.hi.max(other.hi)};
+    }
+
+    pub fn len(&self) -> usize
+    {
+        return self.hi - self.lo;
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        return self.hi == self.lo;
+    }
+}
+
+/// A resolved, human readable position. Lines and columns start at 1 and
+/// columns count characters rather than bytes.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub struct Location
+{
+    pub line : usize,
+    pub column : usize,
+}
+
+#[derive(Debug)]
+pub struct SourceFile
+{
+    pub name : String,
+    pub source : String,
+    line_starts : Vec<usize>,
+}
+
+impl SourceFile
+{
+    fn new(name : &str, source : String) -> SourceFile
+    {
+        let line_starts = std::iter::once(0)
+                            .chain(source.match_indices('\n').map(|(pos, _)| pos + 1))
+                            .collect();
+        return SourceFile{name: name.to_string(), source, line_starts};
+    }
+
+    /// 0-based index of the line containing `offset`.
+    fn line_index(&self, offset : usize) -> usize
+    {
+        match self.line_starts.binary_search(&offset)
+        {
+            Ok(line) => return line,
+            Err(line) => return line - 1,
+        };
+    }
+
+    pub fn location(&self, offset : usize) -> Location
+    {
+        let offset = offset.min(self.source.len());
+        let line = self.line_index(offset);
+        let column = self.source[self.line_starts[line]..offset].chars().count() + 1;
+        return Location{line: line + 1, column};
+    }
+
+    /// Byte range of the 1-based line `line`, without its line terminator.
+    pub fn line_range(&self, line : usize) -> (usize, usize)
+    {
+        let start = self.line_starts[line - 1];
+        let mut end = self.line_starts.get(line).map_or(self.source.len(), |next| next - 1);
+        if end > start && self.source.as_bytes()[end - 1] == b'\r'
+        {
+            end -= 1;
+        }
+        return (start, end);
+    }
+
+    pub fn line_text(&self, line : usize) -> &str
+    {
+        let (start, end) = self.line_range(line);
+        return &self.source[start..end];
+    }
+}
+
+/// Owns the text of every file taking part in a compilation so spans can be
+/// turned back into file names, lines and columns.
+#[derive(Debug,Default)]
+pub struct SourceMap
+{
+    files : Vec<SourceFile>,
+}
+
+impl SourceMap
+{
+    pub fn new() -> SourceMap
+    {
+        return SourceMap{files: Vec::new()};
+    }
+
+    pub fn add_file(&mut self, name : &str, source : String) -> FileId
+    {
+        self.files.push(SourceFile::new(name, source));
+        return FileId(self.files.len() - 1);
+    }
+
+    pub fn file(&self, id : FileId) -> &SourceFile
+    {
+        return &self.files[id.0];
+    }
+
+    pub fn location(&self, span : Span) -> Location
+    {
+        return self.file(span.file).location(span.lo);
+    }
+
+    pub fn snippet(&self, span : Span) -> &str
+    {
+        return &self.file(span.file).source[span.lo..span.hi];
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn locations()
+    {
+        let mut source_map = SourceMap::new();
+        let id = source_map.add_file("main.c", String::from("int main()\n{\n    return 2;\n}"));
+        assert_eq!(source_map.location(Span::new(id, 0, 3)), Location{line: 1, column: 1});
+        assert_eq!(source_map.location(Span::new(id, 11, 12)), Location{line: 2, column: 1});
+        assert_eq!(source_map.location(Span::new(id, 24, 25)), Location{line: 3, column: 12});
+        assert_eq!(source_map.snippet(Span::new(id, 17, 23)), "return");
+        assert_eq!(source_map.file(id).line_text(3), "    return 2;");
+        assert_eq!(source_map.file(id).line_text(4), "}");
+    }
+
+    #[test]
+    fn crlf_line_text()
+    {
+        let mut source_map = SourceMap::new();
+        let id = source_map.add_file("main.c", String::from("int\r\nmain"));
+        assert_eq!(source_map.file(id).line_text(1), "int");
+        assert_eq!(source_map.location(Span::new(id, 5, 9)), Location{line: 2, column: 1});
+    }
+
+    #[test]
+    fn join_spans()
+    {
+        let a = Span::new(FileId(0), 4, 6);
+        let b = Span::new(FileId(0), 10, 12);
+        assert_eq!(a.to(b), Span::new(FileId(0), 4, 12));
+        assert_eq!(a.to(b).len(), 8);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use crate::source_map::Span;
+use crate::types::Type;
+
+/// Identifies one declared variable. Two variables with the same name in
+/// different scopes get different ids, so later stages never need to redo
+/// name lookup.
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Hash)]
+pub struct VarId(pub usize);
+
+/// What an ordinary identifier refers to.
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum SymbolKind
+{
+    Variable(VarId),
+    /// An enumeration constant, with its value.
+    Enumerator(i32),
+    /// A typedef name, standing for the type of the symbol.
+    Typedef,
+}
+
+#[derive(Debug,Clone)]
+pub struct Symbol
+{
+    pub kind : SymbolKind,
+    pub ty : Type,
+    /// Where the identifier was declared.
+    pub span : Span,
+}
+
+impl Symbol
+{
+    /// The variable the identifier refers to, unless it names a constant or
+    /// a type.
+    pub fn id(&self) -> Option<VarId>
+    {
+        match self.kind
+        {
+            SymbolKind::Variable(id) => return Some(id),
+            SymbolKind::Enumerator(_) | SymbolKind::Typedef => return None,
+        };
+    }
+}
+
+/// What the parser knows about a declared function.
+#[derive(Debug,Clone)]
+pub struct FunctionSymbol
+{
+    pub return_type : Type,
+    /// The types of the parameters, unless every declaration so far has left
+    /// them unspecified with `()`.
+    pub params : Option<Vec<Type>>,
+    /// Where the function was first declared.
+    pub span : Span,
+    /// Where its body is, once it has been defined.
+    pub definition : Option<Span>,
+    /// Whether it has internal linkage, being declared `static`.
+    pub internal : bool,
+}
+
+/// What the parser knows about a variable declared at file scope or with
+/// `extern`. Every declaration of it refers to the same `VarId`.
+#[derive(Debug,Clone)]
+pub struct GlobalSymbol
+{
+    pub id : VarId,
+    pub ty : Type,
+    /// Where the variable was first declared.
+    pub span : Span,
+    pub definition : Option<Span>,
+    pub internal : bool,
+}
+
+/// A declared struct, union or enum tag.
+#[derive(Debug,Clone)]
+pub struct TagSymbol
+{
+    pub ty : Type,
+    /// Where the tag was first declared in its scope.
+    pub span : Span,
+}
+
+/// Stack of lexical scopes mapping names to the variables, enumeration
+/// constants and typedef names they refer to.
+/// Tags have scopes of their own, opened and closed together with them.
+#[derive(Debug)]
+pub struct SymbolTable
+{
+    scopes : Vec<HashMap<String, Symbol>>,
+    tags : Vec<HashMap<String, TagSymbol>>,
+    next_id : usize,
+}
+
+impl Default for SymbolTable
+{
+    fn default() -> SymbolTable
+    {
+        return SymbolTable::new();
+    }
+}
+
+impl SymbolTable
+{
+    pub fn new() -> SymbolTable
+    {
+        return SymbolTable{scopes: vec!(HashMap::new()), tags: vec!(HashMap::new()), next_id: 0};
+    }
+
+    pub fn push_scope(&mut self)
+    {
+        self.scopes.push(HashMap::new());
+        self.tags.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self)
+    {
+        self.scopes.pop();
+        self.tags.pop();
+    }
+
+    /// Declares `name` in the innermost scope. If it is already declared there
+    /// the previous symbol is returned as the error.
+    pub fn declare(&mut self, name : &str, ty : Type, span : Span) -> Result<VarId, Symbol>
+    {
+        if let Some(previous) = self.scopes.last().unwrap().get(name)
+        {
+            return Err(previous.clone());
+        }
+        let id = self.new_id();
+        self.declare_id(name, ty, span, id)?;
+        return Ok(id);
+    }
+
+    /// Makes `name` refer to the existing variable `id` in the innermost
+    /// scope, as a redeclaration of a global does.
+    pub fn declare_id(&mut self, name : &str, ty : Type, span : Span, id : VarId) -> Result<(), Symbol>
+    {
+        return self.insert(name, Symbol{kind: SymbolKind::Variable(id), ty, span});
+    }
+
+    /// Declares `name` in the innermost scope as an enumeration constant,
+    /// which has type `int`.
+    pub fn declare_enumerator(&mut self, name : &str, value : i32, span : Span) -> Result<(), Symbol>
+    {
+        return self.insert(name, Symbol{kind: SymbolKind::Enumerator(value), ty: Type::Int, span});
+    }
+
+    /// Declares `name` in the innermost scope as a typedef name for `ty`.
+    pub fn declare_typedef(&mut self, name : &str, ty : Type, span : Span) -> Result<(), Symbol>
+    {
+        return self.insert(name, Symbol{kind: SymbolKind::Typedef, ty, span});
+    }
+
+    fn insert(&mut self, name : &str, symbol : Symbol) -> Result<(), Symbol>
+    {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(previous) = scope.get(name)
+        {
+            return Err(previous.clone());
+        }
+        scope.insert(name.to_string(), symbol);
+        return Ok(());
+    }
+
+    /// Gives `name` in the innermost scope its complete type, once the
+    /// initializer of an array declared without a length has given one.
+    pub fn complete(&mut self, name : &str, ty : Type)
+    {
+        if let Some(symbol) = self.scopes.last_mut().unwrap().get_mut(name)
+        {
+            symbol.ty = ty;
+        }
+    }
+
+    /// A fresh id for a variable that is not in scope yet.
+    pub fn new_id(&mut self) -> VarId
+    {
+        self.next_id += 1;
+        return VarId(self.next_id - 1);
+    }
+
+    /// Whether no block or function scope is open.
+    pub fn is_file_scope(&self) -> bool
+    {
+        return self.scopes.len() == 1;
+    }
+
+    /// Finds the innermost declaration of `name`.
+    pub fn lookup(&self, name : &str) -> Option<&Symbol>
+    {
+        return self.scopes.iter().rev().find_map(|scope| scope.get(name));
+    }
+
+    /// The type `name` stands for, if it is a typedef name in scope. The
+    /// parser asks this to tell declarations from expressions, since `T * x;`
+    /// only declares `x` when `T` names a type.
+    pub fn typedef(&self, name : &str) -> Option<&Type>
+    {
+        return self.lookup(name).filter(|symbol| symbol.kind == SymbolKind::Typedef).map(|symbol| &symbol.ty);
+    }
+
+    /// Declares the tag `name` in the innermost scope, where it must not be
+    /// declared yet.
+    pub fn declare_tag(&mut self, name : &str, ty : Type, span : Span)
+    {
+        self.tags.last_mut().unwrap().insert(name.to_string(), TagSymbol{ty, span});
+    }
+
+    /// Finds the innermost declaration of the tag `name`.
+    pub fn lookup_tag(&self, name : &str) -> Option<&TagSymbol>
+    {
+        return self.tags.iter().rev().find_map(|scope| scope.get(name));
+    }
+
+    /// Finds the tag `name` if it is declared in the innermost scope.
+    pub fn local_tag(&self, name : &str) -> Option<&TagSymbol>
+    {
+        return self.tags.last().unwrap().get(name);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn redeclaration_in_same_scope()
+    {
+        let mut table = SymbolTable::new();
+        let first = table.declare("x", Type::Int, Span::default()).unwrap();
+        let previous = table.declare("x", Type::Int, Span::default()).unwrap_err();
+        assert_eq!(previous.id(), Some(first));
+    }
+
+    #[test]
+    fn inner_scope_shadows_outer()
+    {
+        let mut table = SymbolTable::new();
+        let outer = table.declare("x", Type::Int, Span::default()).unwrap();
+        table.push_scope();
+        let inner = table.declare("x", Type::Int, Span::default()).unwrap();
+        assert_ne!(inner, outer);
+        assert_eq!(table.lookup("x").unwrap().id(), Some(inner));
+        table.pop_scope();
+        assert_eq!(table.lookup("x").unwrap().id(), Some(outer));
+        assert!(table.lookup("y").is_none());
+    }
+
+    #[test]
+    fn enumerators_share_the_ordinary_namespace()
+    {
+        let mut table = SymbolTable::new();
+        table.declare_enumerator("red", 0, Span::default()).unwrap();
+        let previous = table.declare("red", Type::Int, Span::default()).unwrap_err();
+        assert_eq!(previous.kind, SymbolKind::Enumerator(0));
+        table.push_scope();
+        let id = table.declare("red", Type::Char, Span::default()).unwrap();
+        assert_eq!(table.lookup("red").unwrap().id(), Some(id));
+        assert!(table.declare_enumerator("red", 1, Span::default()).is_err());
+        table.pop_scope();
+        assert_eq!(table.lookup("red").unwrap().id(), None);
+    }
+
+    #[test]
+    fn typedef_names_can_be_hidden()
+    {
+        let mut table = SymbolTable::new();
+        table.declare_typedef("T", Type::Long, Span::default()).unwrap();
+        assert_eq!(table.typedef("T"), Some(&Type::Long));
+        table.push_scope();
+        table.declare("T", Type::Int, Span::default()).unwrap();
+        assert_eq!(table.typedef("T"), None);
+        table.pop_scope();
+        assert_eq!(table.typedef("T"), Some(&Type::Long));
+        assert_eq!(table.declare("T", Type::Int, Span::default()).unwrap_err().kind, SymbolKind::Typedef);
+    }
+
+    #[test]
+    fn tags_have_their_own_namespace()
+    {
+        let mut table = SymbolTable::new();
+        table.declare("s", Type::Int, Span::default()).unwrap();
+        table.declare_tag("s", Type::Char, Span::default());
+        table.push_scope();
+        assert!(table.local_tag("s").is_none());
+        assert_eq!(table.lookup_tag("s").unwrap().ty, Type::Char);
+        table.declare_tag("s", Type::Short, Span::default());
+        assert_eq!(table.lookup_tag("s").unwrap().ty, Type::Short);
+        table.pop_scope();
+        assert_eq!(table.lookup_tag("s").unwrap().ty, Type::Char);
+        assert_eq!(table.lookup("s").unwrap().ty, Type::Int);
+    }
+}
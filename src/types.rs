@@ -0,0 +1,568 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::literal::IntegerType;
+
+/// Largest size in bytes of an object, as on i386, where sizes and offsets
+/// into the frame are signed 32 bit numbers.
+pub const MAX_OBJECT_SIZE : usize = i32::MAX as usize;
+
+/// The type of an object or expression, laid out as on i386: `short` is 16
+/// bits, `int`, `long` and pointers are 32 bits and `long long` is 64 bits.
+/// Plain `char` is signed, as in the i386 System V ABI.
+#[derive(Debug,PartialEq,Eq,Clone)]
+pub enum Type
+{
+    Void,
+    Bool,
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    /// A pointer to a value of the type.
+    Pointer(Box<Type>),
+    /// A fixed number of values of the type, one after the other.
+    Array(Box<Type>, usize),
+    /// A struct or union.
+    Record(RecordType),
+}
+
+#[derive(Debug,PartialEq,Eq,Clone,Copy)]
+pub enum RecordKind
+{
+    Struct,
+    Union,
+}
+
+/// A struct or union type. Every use of a tag shares the type its
+/// declaration created, so a pointer to a struct declared before its members
+/// sees them once they are defined, and two types are the same exactly when
+/// they come from the same declaration.
+#[derive(Clone)]
+pub struct RecordType(Rc<RefCell<Record>>);
+
+struct Record
+{
+    kind : RecordKind,
+    tag : Option<String>,
+    /// The members, once the definition has been seen. Until then the type
+    /// is incomplete.
+    members : Option<Vec<Member>>,
+    size : usize,
+    align : usize,
+}
+
+/// A member of a struct or union.
+#[derive(Debug,Clone)]
+pub struct Member
+{
+    /// Anonymous structs and unions nested in another one have no name,
+    /// their members are found as members of the outer one.
+    pub name : Option<String>,
+    pub ty : Type,
+    /// Offset in bytes from the start of the struct or union.
+    pub offset : usize,
+}
+
+impl Type
+{
+    pub fn pointer_to(pointee : Type) -> Type
+    {
+        return Type::Pointer(Box::new(pointee));
+    }
+
+    pub fn array_of(element : Type, length : usize) -> Type
+    {
+        return Type::Array(Box::new(element), length);
+    }
+
+    /// Size in bytes, which is also the alignment of every scalar type.
+    pub fn size(&self) -> usize
+    {
+        match self
+        {
+            Type::Void => return 0,
+            Type::Bool | Type::Char | Type::SignedChar | Type::UnsignedChar => return 1,
+            Type::Short | Type::UnsignedShort => return 2,
+            Type::Int | Type::UnsignedInt | Type::Long | Type::UnsignedLong | Type::Pointer(_) => return 4,
+            Type::LongLong | Type::UnsignedLongLong => return 8,
+            Type::Array(element, length) => return element.size() * length,
+            Type::Record(record) => return record.0.borrow().size,
+        };
+    }
+
+    /// Size in bytes, or `None` if an object of the type would be larger than
+    /// `MAX_OBJECT_SIZE`. Only types of that size or less are ever declared,
+    /// so `size` cannot overflow.
+    pub fn checked_size(&self) -> Option<usize>
+    {
+        let size = match self
+        {
+            Type::Array(element, length) => element.checked_size()?.checked_mul(*length)?,
+            _ => self.size(),
+        };
+        return Some(size).filter(|size| *size <= MAX_OBJECT_SIZE);
+    }
+
+    /// Alignment in bytes. An array is aligned like its elements.
+    pub fn align(&self) -> usize
+    {
+        match self
+        {
+            Type::Array(element, _) => return element.align(),
+            Type::Record(record) => return record.0.borrow().align,
+            _ => return self.size().max(1),
+        };
+    }
+
+    /// Alignment in bytes of a member of the type in a struct or union. The
+    /// i386 System V ABI only aligns `long long` to 4 bytes, although gcc
+    /// still gives variables of it 8.
+    pub fn member_align(&self) -> usize
+    {
+        match self
+        {
+            Type::LongLong | Type::UnsignedLongLong => return 4,
+            Type::Array(element, _) => return element.member_align(),
+            _ => return self.align(),
+        };
+    }
+
+    pub fn is_integer(&self) -> bool
+    {
+        return !matches!(self, Type::Void | Type::Pointer(_) | Type::Array(..) | Type::Record(_));
+    }
+
+    pub fn is_pointer(&self) -> bool
+    {
+        return matches!(self, Type::Pointer(_));
+    }
+
+    pub fn is_array(&self) -> bool
+    {
+        return matches!(self, Type::Array(..));
+    }
+
+    pub fn is_record(&self) -> bool
+    {
+        return matches!(self, Type::Record(_));
+    }
+
+    /// Whether the size of the type is known: `void` and structs and unions
+    /// whose members have not been defined yet have none.
+    pub fn is_complete(&self) -> bool
+    {
+        match self
+        {
+            Type::Void => return false,
+            Type::Record(record) => return record.is_complete(),
+            _ => return true,
+        };
+    }
+
+    /// Whether a value of the type is a single number or address, rather
+    /// than made of several values.
+    pub fn is_scalar(&self) -> bool
+    {
+        return self.is_integer() || self.is_pointer();
+    }
+
+    /// The type of the elements of an array.
+    pub fn element(&self) -> Option<&Type>
+    {
+        match self
+        {
+            Type::Array(element, _) => return Some(element),
+            _ => return None,
+        };
+    }
+
+    /// The type a pointer points to.
+    pub fn pointee(&self) -> Option<&Type>
+    {
+        match self
+        {
+            Type::Pointer(pointee) => return Some(pointee),
+            _ => return None,
+        };
+    }
+
+    pub fn is_unsigned(&self) -> bool
+    {
+        match self
+        {
+            Type::Bool | Type::UnsignedChar | Type::UnsignedShort | Type::UnsignedInt
+                | Type::UnsignedLong | Type::UnsignedLongLong => return true,
+            _ => return false,
+        };
+    }
+
+    /// Integer conversion rank (C11 6.3.1.1): types of a higher rank can hold
+    /// at least the values of those of a lower one.
+    fn rank(&self) -> u8
+    {
+        match self
+        {
+            Type::Void | Type::Pointer(_) | Type::Array(..) | Type::Record(_) => return 0,
+            Type::Bool => return 1,
+            Type::Char | Type::SignedChar | Type::UnsignedChar => return 2,
+            Type::Short | Type::UnsignedShort => return 3,
+            Type::Int | Type::UnsignedInt => return 4,
+            Type::Long | Type::UnsignedLong => return 5,
+            Type::LongLong | Type::UnsignedLongLong => return 6,
+        };
+    }
+
+    /// The unsigned type of the same rank.
+    fn to_unsigned(&self) -> Type
+    {
+        match self
+        {
+            Type::Char | Type::SignedChar => return Type::UnsignedChar,
+            Type::Short => return Type::UnsignedShort,
+            Type::Int => return Type::UnsignedInt,
+            Type::Long => return Type::UnsignedLong,
+            Type::LongLong => return Type::UnsignedLongLong,
+            _ => return self.clone(),
+        };
+    }
+
+    /// The integer promotions: every type narrower than `int` becomes `int`,
+    /// which can represent all of their values.
+    pub fn promote(&self) -> Type
+    {
+        if self.is_integer() && self.rank() < Type::Int.rank()
+        {
+            return Type::Int;
+        }
+        return self.clone();
+    }
+
+    /// The type both operands of an arithmetic operator are converted to by
+    /// the usual arithmetic conversions (C11 6.3.1.8).
+    pub fn common(lhs : &Type, rhs : &Type) -> Type
+    {
+        let (lhs, rhs) = (lhs.promote(), rhs.promote());
+        if lhs == rhs
+        {
+            return lhs;
+        }
+        let (higher, lower) = if lhs.rank() >= rhs.rank() { (lhs, rhs) } else { (rhs, lhs) };
+        if higher.is_unsigned() == lower.is_unsigned() || higher.is_unsigned()
+        {
+            return higher;
+        }
+        // The signed type has the higher rank. It wins if it can represent
+        // every value of the unsigned one, which takes a larger size.
+        if higher.size() > lower.size()
+        {
+            return higher;
+        }
+        return higher.to_unsigned();
+    }
+
+    /// Whether every value of `self` is also a value of `other`.
+    pub fn fits_in(&self, other : &Type) -> bool
+    {
+        let (min, max) = self.range();
+        let (other_min, other_max) = other.range();
+        return other_min <= min && max <= other_max;
+    }
+
+    fn range(&self) -> (i128, i128)
+    {
+        if *self == Type::Bool
+        {
+            return (0, 1);
+        }
+        let bits = self.size() as u32 * 8;
+        if self.is_unsigned() || self.is_pointer()
+        {
+            return (0, (1 << bits) - 1);
+        }
+        return (-(1 << (bits - 1)), (1 << (bits - 1)) - 1);
+    }
+
+    /// Converts `value` to this type. Values are kept in an `i64` holding
+    /// their mathematical value, so unsigned types are zero extended and
+    /// signed ones sign extended, except for `unsigned long long` which
+    /// keeps its bit pattern. Addresses are unsigned.
+    pub fn convert(&self, value : i64) -> i64
+    {
+        match self.size()
+        {
+            _ if *self == Type::Bool => return (value != 0) as i64,
+            1 if self.is_unsigned() => return value as u8 as i64,
+            1 => return value as i8 as i64,
+            2 if self.is_unsigned() => return value as u16 as i64,
+            2 => return value as i16 as i64,
+            4 if self.is_unsigned() || self.is_pointer() => return value as u32 as i64,
+            4 => return value as i32 as i64,
+            _ => return value,
+        };
+    }
+
+    /// Spelling of the type in diagnostics, such as `char **`, `int (*)[3]`
+    /// or `struct point`.
+    pub fn name(&self) -> String
+    {
+        return self.declare("");
+    }
+
+    /// Spelling of a declaration of `declarator` with the type, which C
+    /// writes inside out: the pointers on the left and the array lengths on
+    /// the right of the name, with parentheses where a pointer to an array
+    /// would otherwise read as an array of pointers.
+    fn declare(&self, declarator : &str) -> String
+    {
+        let name = match self
+        {
+            Type::Void => "void",
+            Type::Bool => "_Bool",
+            Type::Char => "char",
+            Type::SignedChar => "signed char",
+            Type::UnsignedChar => "unsigned char",
+            Type::Short => "short",
+            Type::UnsignedShort => "unsigned short",
+            Type::Int => "int",
+            Type::UnsignedInt => "unsigned int",
+            Type::Long => "long",
+            Type::UnsignedLong => "unsigned long",
+            Type::LongLong => "long long",
+            Type::UnsignedLongLong => "unsigned long long",
+            Type::Record(record) => &record.name(),
+            Type::Pointer(pointee) => return pointee.declare(&format!("*{}", declarator)),
+            Type::Array(element, length) if declarator.starts_with('*') => return element.declare(&format!("({})[{}]", declarator, length)),
+            Type::Array(element, length) => return element.declare(&format!("{}[{}]", declarator, length)),
+        };
+        if declarator.is_empty() || declarator.starts_with('[')
+        {
+            return format!("{}{}", name, declarator);
+        }
+        return format!("{} {}", name, declarator);
+    }
+}
+
+impl RecordType
+{
+    /// A new struct or union type, incomplete until `define` gives it its
+    /// members.
+    pub fn new(kind : RecordKind, tag : Option<String>) -> RecordType
+    {
+        return RecordType(Rc::new(RefCell::new(Record{kind, tag, members: None, size: 0, align: 1})));
+    }
+
+    pub fn kind(&self) -> RecordKind
+    {
+        return self.0.borrow().kind;
+    }
+
+    pub fn is_complete(&self) -> bool
+    {
+        return self.0.borrow().members.is_some();
+    }
+
+    pub fn tag(&self) -> Option<String>
+    {
+        return self.0.borrow().tag.clone();
+    }
+
+    /// Lays out the members in order, as gcc does for i386: each member of a
+    /// struct at the next offset aligned for it, every member of a union at
+    /// offset 0, and the size rounded up to the largest alignment so that
+    /// the members stay aligned in an array.
+    pub fn define(&self, members : Vec<(Option<String>, Type)>)
+    {
+        let mut record = self.0.borrow_mut();
+        let (mut size, mut align) : (usize, usize) = (0, 1);
+        let mut laid_out = Vec::new();
+        for (name, ty) in members
+        {
+            let member_align = ty.member_align();
+            let offset = match record.kind
+            {
+                RecordKind::Struct => size.next_multiple_of(member_align),
+                RecordKind::Union => 0,
+            };
+            size = size.max(offset + ty.size());
+            align = align.max(member_align);
+            laid_out.push(Member{name, ty, offset});
+        }
+        record.size = size.next_multiple_of(align);
+        record.align = align;
+        record.members = Some(laid_out);
+    }
+
+    /// The members in order, none while the type is incomplete.
+    pub fn members(&self) -> Vec<Member>
+    {
+        return self.0.borrow().members.clone().unwrap_or_default();
+    }
+
+    /// Finds the member `name`, looking into anonymous members too, and
+    /// gives its type and offset.
+    pub fn member(&self, name : &str) -> Option<(Type, usize)>
+    {
+        for member in self.members()
+        {
+            match (&member.name, &member.ty)
+            {
+                (Some(member_name), _) if member_name == name => return Some((member.ty, member.offset)),
+                (None, Type::Record(inner)) => {
+                    if let Some((ty, offset)) = inner.member(name)
+                    {
+                        return Some((ty, member.offset + offset));
+                    }
+                },
+                _ => {},
+            };
+        }
+        return None;
+    }
+
+    /// Spelling of the type, such as `struct point` or `union <anonymous>`.
+    pub fn name(&self) -> String
+    {
+        let record = self.0.borrow();
+        let keyword = if record.kind == RecordKind::Struct { "struct" } else { "union" };
+        return format!("{} {}", keyword, record.tag.as_deref().unwrap_or("<anonymous>"));
+    }
+}
+
+impl PartialEq for RecordType
+{
+    fn eq(&self, other : &RecordType) -> bool
+    {
+        return Rc::ptr_eq(&self.0, &other.0);
+    }
+}
+
+impl Eq for RecordType {}
+
+// Members may point back to the type, so only its name is written.
+impl fmt::Debug for RecordType
+{
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+    {
+        return write!(f, "{}", self.name());
+    }
+}
+
+impl From<IntegerType> for Type
+{
+    fn from(ty : IntegerType) -> Type
+    {
+        match ty
+        {
+            IntegerType::Int => return Type::Int,
+            IntegerType::UnsignedInt => return Type::UnsignedInt,
+            IntegerType::Long => return Type::Long,
+            IntegerType::UnsignedLong => return Type::UnsignedLong,
+            IntegerType::LongLong => return Type::LongLong,
+            IntegerType::UnsignedLongLong => return Type::UnsignedLongLong,
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn integer_promotions()
+    {
+        assert_eq!(Type::Bool.promote(), Type::Int);
+        assert_eq!(Type::UnsignedChar.promote(), Type::Int);
+        assert_eq!(Type::UnsignedShort.promote(), Type::Int);
+        assert_eq!(Type::UnsignedInt.promote(), Type::UnsignedInt);
+        assert_eq!(Type::LongLong.promote(), Type::LongLong);
+    }
+
+    #[test]
+    fn records_are_laid_out_like_gcc()
+    {
+        let record = RecordType::new(RecordKind::Struct, Some("s".to_string()));
+        assert!(!Type::Record(record.clone()).is_complete());
+        record.define(vec!((Some("c".to_string()), Type::Char), (Some("l".to_string()), Type::LongLong),
+                            (Some("h".to_string()), Type::Short)));
+        let ty = Type::Record(record.clone());
+        // A `long long` member is only aligned to 4 bytes on i386.
+        assert_eq!((ty.size(), ty.align()), (16, 4));
+        assert_eq!(record.member("h"), Some((Type::Short, 12)));
+        let union = RecordType::new(RecordKind::Union, None);
+        union.define(vec!((Some("bytes".to_string()), Type::array_of(Type::Char, 5)), (None, ty.clone())));
+        let union_type = Type::Record(union.clone());
+        assert_eq!((union_type.size(), union_type.align()), (16, 4));
+        assert_eq!(union.member("l"), Some((Type::LongLong, 4)));
+        assert_eq!(union_type.name(), "union <anonymous>");
+        assert_eq!(Type::pointer_to(ty.clone()).name(), "struct s *");
+        // Types are the same only when they come from the same declaration.
+        assert_ne!(ty, Type::Record(RecordType::new(RecordKind::Struct, Some("s".to_string()))));
+    }
+
+    #[test]
+    fn sizes_are_checked_against_the_largest_object()
+    {
+        assert_eq!(Type::array_of(Type::Char, MAX_OBJECT_SIZE).checked_size(), Some(MAX_OBJECT_SIZE));
+        assert_eq!(Type::array_of(Type::Short, MAX_OBJECT_SIZE / 2 + 1).checked_size(), None);
+        let huge = Type::array_of(Type::Char, MAX_OBJECT_SIZE);
+        assert_eq!(Type::array_of(Type::array_of(huge, MAX_OBJECT_SIZE), MAX_OBJECT_SIZE).checked_size(), None);
+    }
+
+    #[test]
+    fn usual_arithmetic_conversions()
+    {
+        assert_eq!(Type::common(&Type::Char, &Type::Short), Type::Int);
+        assert_eq!(Type::common(&Type::Int, &Type::UnsignedInt), Type::UnsignedInt);
+        assert_eq!(Type::common(&Type::UnsignedShort, &Type::Long), Type::Long);
+        // `long` is no wider than `unsigned int` on i386.
+        assert_eq!(Type::common(&Type::Long, &Type::UnsignedInt), Type::UnsignedLong);
+        assert_eq!(Type::common(&Type::LongLong, &Type::UnsignedLong), Type::LongLong);
+        assert_eq!(Type::common(&Type::UnsignedLongLong, &Type::LongLong), Type::UnsignedLongLong);
+    }
+
+    #[test]
+    fn conversions_wrap_to_the_type()
+    {
+        assert_eq!(Type::Char.convert(200), -56);
+        assert_eq!(Type::UnsignedChar.convert(-1), 255);
+        assert_eq!(Type::Short.convert(0x18000), -0x8000);
+        assert_eq!(Type::UnsignedInt.convert(-1), 0xffffffff);
+        assert_eq!(Type::Int.convert(0xffffffff), -1);
+        assert_eq!(Type::Bool.convert(256), 1);
+        assert!(Type::UnsignedChar.fits_in(&Type::Short));
+        assert!(!Type::Char.fits_in(&Type::UnsignedInt));
+        assert!(Type::Bool.fits_in(&Type::UnsignedChar));
+        assert_eq!(Type::pointer_to(Type::Int).convert(-4), 0xfffffffc);
+    }
+
+    #[test]
+    fn pointer_names()
+    {
+        let pointer = Type::pointer_to(Type::UnsignedChar);
+        assert_eq!(pointer.name(), "unsigned char *");
+        assert_eq!(Type::pointer_to(pointer).name(), "unsigned char **");
+        assert_eq!(Type::pointer_to(Type::Void).size(), 4);
+    }
+
+    #[test]
+    fn array_layout_and_names()
+    {
+        let matrix = Type::array_of(Type::array_of(Type::Short, 3), 2);
+        assert_eq!((matrix.size(), matrix.align()), (12, 2));
+        assert_eq!(matrix.name(), "short[2][3]");
+        assert_eq!(Type::array_of(Type::pointer_to(Type::Char), 4).name(), "char *[4]");
+        assert_eq!(Type::pointer_to(Type::array_of(Type::Int, 3)).name(), "int (*)[3]");
+        assert!(!matrix.is_scalar() && Type::pointer_to(Type::Void).is_scalar());
+    }
+}
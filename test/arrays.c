@@ -0,0 +1,68 @@
+int putchar(int c);
+
+int primes[] = {2, 3, 5, 7, 11};
+short grid[2][3] = {{1, 2, 3}, {4, 5}};
+char greeting[] = "hi\n";
+char *message = "arrays\n";
+int zeros[8];
+int *third = &primes[2];
+static char letters[4] = {'a', 'b'};
+
+void print(char *s)
+{
+    while (*s)
+        putchar(*s++);
+}
+
+int sum(int values[], int count)
+{
+    int total = 0;
+    for (int i = 0; i < count; i++)
+        total += values[i];
+    return total;
+}
+
+int trace(int (*rows)[3], int n)
+{
+    int total = 0;
+    for (int i = 0; i < n; i++)
+        total += rows[i][i];
+    return total;
+}
+
+int main(void)
+{
+    int matrix[3][3] = {1, 2, 3, 4, 5, 6, 7, 8, 9};
+    char word[8] = "tcc";
+    long long wide[3] = {1, 2};
+    int big[20] = {1};
+    char *names[] = {"zero", "one", "two"};
+
+    print(greeting);
+    print(message);
+    print(names[2]);
+    putchar('\n');
+    print(word);
+    putchar('\n');
+
+    if (sizeof primes != 20 || sizeof grid != 12 || sizeof word != 8 || sizeof(int[4][2]) != 32)
+        return 1;
+    if (sizeof names / sizeof names[0] != 3 || sizeof "abc" != 4 || sizeof wide != 24)
+        return 2;
+    if (grid[1][2] != 0 || grid[1][1] != 5 || *third != 5 || zeros[7] != 0)
+        return 3;
+    if (big[0] != 1 || big[19] != 0 || word[3] != 0 || word[7] != 0 || wide[2] != 0)
+        return 4;
+    if (letters[1] != 'b' || letters[3] != 0 || 2[primes] != 5)
+        return 5;
+
+    int *p = matrix[1];
+    p[2] = 60;
+    int (*row)[3] = matrix;
+    row++;
+    if ((*row)[2] != 60 || &matrix[2][0] - &matrix[0][0] != 6)
+        return 6;
+
+    zeros[3] = 4;
+    return sum(primes, 5) + trace(matrix, 3) + zeros[3] + sizeof matrix[0];
+}
@@ -0,0 +1,8 @@
+int main()
+{
+    int a = 0x5a;
+    int b = (a & 0x0f) | (a ^ 0xff) << 4;   /* 0xa | 0xa50 */
+    int c = -16 >> 2;                       /* arithmetic shift: -4 */
+    int d = 0xffffffffu >> 28;              /* logical shift: 15 */
+    return b % 256 + c + d;                 /* 0x5a + -4 + 15 */
+}
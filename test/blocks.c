@@ -0,0 +1,14 @@
+int main()
+{
+    int a = 1;
+    {
+        int a = 2;
+        int b = a * 10;
+        a = b + a;
+    }
+    {
+        int c = 5;
+        a = a + c;
+    }
+    int d = a + 1;
+}
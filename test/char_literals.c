@@ -0,0 +1,5 @@
+int main()
+{
+    // 10 + 65 + 0 + 65 - 65 = 75
+    return '\n' + '\x41' + '\0' + '\101' - 'A';
+}
@@ -0,0 +1,7 @@
+/*
+ * Block comments may span several lines.
+ */
+int main() // the entry point
+{
+    return /* inline */ 2 * 3; // 6
+}
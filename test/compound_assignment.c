@@ -0,0 +1,21 @@
+int main()
+{
+    int a = 5;
+    int b = 3;
+    a += b *= 2;            /* b = 6, a = 11 */
+    a -= 1;                 /* 10 */
+    a *= 3;                 /* 30 */
+    a /= 4;                 /* 7 */
+    a %= 5;                 /* 2 */
+    a <<= 4;                /* 32 */
+    a >>= 1;                /* 16 */
+    a |= 3;                 /* 19 */
+    a &= ~1;                /* 18 */
+    a ^= 8;                 /* 26 */
+    int c = a++ + ++b;      /* 26 + 7, a = 27 */
+    int d = (b--, --b);     /* 5 */
+    int j = 0;
+    for (int i = 0; i < 3; i++, j--)
+        c += 1;             /* 36 */
+    return c + d + a + j;   /* 36 + 5 + 27 + -3 */
+}
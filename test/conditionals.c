@@ -0,0 +1,18 @@
+int main()
+{
+    int a = 7;
+    int b = 0;
+    if (a > 5)
+        if (a > 10)
+            b = 1;
+        else
+            b = 2;
+    if (b == 2)
+    {
+        int c = a % 2 ? 10 : 20;
+        b = b + c;
+    }
+    else
+        b = 100;
+    return a < 0 ? 1 : a < 5 ? 2 : b;
+}
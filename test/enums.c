@@ -0,0 +1,66 @@
+int putchar(int c);
+
+enum color
+{
+    RED,
+    GREEN = 5,
+    BLUE,
+    LAST = BLUE * 2 + 1,
+};
+
+enum { SMALL = -2, NEXT, HUGE = 2147483647 };
+
+enum color favourite = BLUE;
+int counts[LAST];
+char names[][6] = {[RED] = "red", [GREEN] = "green", [BLUE] = "blue"};
+
+int shade(enum color color)
+{
+    switch (color)
+    {
+        case RED:
+            return 1;
+        case GREEN:
+            return 2;
+        case BLUE:
+            return 3;
+        default:
+            return 0;
+    }
+}
+
+void print(char *s)
+{
+    while (*s)
+        putchar(*s++);
+}
+
+int main(void)
+{
+    if (RED != 0 || GREEN != 5 || BLUE != 6 || LAST != 13 || NEXT != -1 || HUGE != 2147483647)
+        return 1;
+    if (sizeof(enum color) != 4 || sizeof counts != 52 || sizeof favourite != 4)
+        return 2;
+
+    enum color local = GREEN;
+    if (shade(local) + shade(favourite) != 5 || shade(LAST) != 0)
+        return 3;
+
+    int GREEN = 7;
+    {
+        enum { GREEN = 40, TEAL };
+        if (TEAL != 41)
+            return 4;
+        local = GREEN;
+    }
+    if (local != 40 || GREEN != 7)
+        return 5;
+
+    enum color *pointer = &favourite;
+    *pointer = RED;
+    counts[BLUE]++;
+    print(names[GREEN]);
+    print(names[BLUE]);
+    putchar('\n');
+    return favourite + counts[BLUE] + (enum color)LAST + SMALL;
+}
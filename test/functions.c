@@ -0,0 +1,34 @@
+int putchar(int c);
+int fib(int n);
+int difference();
+
+int print_number(int n)
+{
+    if (n >= 10)
+        print_number(n / 10);
+    return putchar('0' + n % 10);
+}
+
+int weigh(int a, int b, int c)
+{
+    return a * 100 + b * 10 + c;
+}
+
+int main()
+{
+    int result = weigh(1, 2, 3) + fib(10);      /* 123 + 55 */
+    print_number(result);
+    char one = 1;
+    putchar('\n');
+    return result - weigh(1, 7, 7) * difference(2, one);   /* 178 - 177 */
+}
+
+int fib(int n)
+{
+    return n < 2 ? n : fib(n - 1) + fib(n - 2);
+}
+
+int difference(int a, int b)
+{
+    return a - b;
+}
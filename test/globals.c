@@ -0,0 +1,36 @@
+int counter;
+int limit = 5;
+static int hidden = 0;
+int shared;
+int shared;
+int first, second = 2, *third = &second;
+
+static int next();
+
+int bump()
+{
+    static int calls;
+    calls++;
+    counter += calls;
+    return calls;
+}
+
+static int next()
+{
+    extern int later;
+    return later++;
+}
+
+int later = 40;
+
+int main()
+{
+    int i;
+    for (i = 0; i < limit; i++)
+    {
+        bump();
+    }
+    hidden = next();
+    shared = next();
+    return counter + hidden + shared + first + *third - 17;
+}
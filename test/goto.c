@@ -0,0 +1,38 @@
+int main()
+{
+    int state = 0;
+    int steps = 0;
+    goto dispatch;
+
+start:
+    {
+        int scratch = 3;
+        state = scratch;
+        goto count;
+    }
+middle:
+    state = state * 2;
+    goto count;
+finish:
+    return steps * 10 + state;      /* 3 * 10 + 12 */
+
+count:
+    steps = steps + 1;
+dispatch:
+    if (state == 0)
+        goto start;
+    if (state < 10)
+    {
+        int doubled = state;
+        if (doubled == 6)
+            goto inside;
+        goto middle;
+    }
+    goto finish;
+    {
+        int unused;
+    inside:
+        state = state + 6;
+        goto count;
+    }
+}
@@ -0,0 +1,5 @@
+int main()
+{
+    // 31 + 15 + 5 + 1 = 52
+    return 0x1F + 017 + 0b101 + 1u;
+}
@@ -0,0 +1,10 @@
+int main()
+{
+    int a = 2;
+    int b;
+    int c, d = a + 1;
+    b = a * 3;
+    a = b = b + 1;
+    c = d;
+    return a + b + c - 6;
+}
@@ -0,0 +1,26 @@
+int main()
+{
+    int sum = 0;
+    for (int i = 0; i < 10; i = i + 1)
+    {
+        if (i % 2)
+            continue;
+        sum = sum + i;          /* 0 + 2 + 4 + 6 + 8 */
+    }
+    for (int i = 0, j = 3; i < j; i = i + 1, j = j - 1)
+        sum = sum + j - i;      /* 3 + 1 */
+    int n = 0;
+    while (1)
+    {
+        n = n + 1;
+        if (n == 5)
+            break;
+    }
+    int k = 10;
+    do
+        k = k - 3;
+    while (k > 0);
+    for (;;)
+        break;
+    return sum + n + k;         /* 20 + 5 + -2 */
+}
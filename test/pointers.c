@@ -0,0 +1,72 @@
+int putchar(int c);
+
+int counter;
+
+void swap(int *a, int *b)
+{
+    int tmp = *a;
+    *a = *b;
+    *b = tmp;
+}
+
+void print(char *s)
+{
+    while (*s)
+        putchar(*s++);
+}
+
+int *next(int *p)
+{
+    return p + 1;
+}
+
+int length(char *s)
+{
+    char *start = s;
+    while (*s)
+        s++;
+    return s - start;
+}
+
+int main(void)
+{
+    int x = 3;
+    int y = 4;
+    int *p = &x;
+    int **pp = &p;
+    int *null = 0;
+    long long big = 5;
+    long long *bp = &big;
+    char c = 'a';
+    char *cp = &c;
+    int result = 0;
+
+    swap(&x, &y);
+    if (x == 4 && y == 3) result += 1;
+
+    **pp = 10;
+    if (x == 10) result += 2;
+
+    *p += 5;
+    (*p)++;
+    if (x == 16) result += 4;
+
+    *bp *= 1000000000;
+    if (big == 5000000000LL) result += 8;
+
+    ++*cp;
+    if (c == 'b') result += 16;
+
+    if (next(p) - p == 1 && (char *)next(p) - (char *)p == 4) result += 32;
+    if (p < next(p) && next(p) > p && p != next(p)) result += 64;
+    if (!null && null == 0 && p) result += 128;
+
+    *&counter = length("pointer");
+    if (counter == 7) result += 256;
+
+    print("pointers\n");
+    bp++;
+    if ((char *)bp - (char *)&big == 8) result += 512;
+
+    return result - 1000;
+}
@@ -0,0 +1,10 @@
+int main()
+{
+    int zero = 0;
+    int a = 3 < 4 == 1;
+    int b = 5 >= 5 && 2 != 2 || 7 <= -1;
+    /* The right operands would divide by zero if they were evaluated. */
+    int c = zero && 1 / zero;
+    int d = !zero || 1 / zero;
+    return a * 100 + b * 50 + c * 10 + d + (2 > 1) * 20;
+}
@@ -0,0 +1,135 @@
+int putchar(int c);
+
+struct point
+{
+    int x, y;
+};
+
+struct node;
+
+struct node
+{
+    int value;
+    struct node *next;
+};
+
+struct shape
+{
+    char kind;
+    struct point corners[2];
+    union
+    {
+        int radius;
+        char label[6];
+    };
+    long long area;
+};
+
+union word
+{
+    unsigned int value;
+    unsigned char bytes[4];
+};
+
+struct big
+{
+    int values[10];
+    char tail[3];
+};
+
+struct point origin;
+struct point unit = {1, 1};
+struct shape box = {'b', {{0, 0}, {4, 3}}, .area = 12};
+struct node third = {3, 0};
+struct node second = {2, &third};
+int *unit_y = &unit.y;
+struct range { int low, high; } digits = {0, 9}, *bounds = &digits;
+
+struct point add(struct point a, struct point b)
+{
+    struct point sum;
+    sum.x = a.x + b.x;
+    sum.y = a.y + b.y;
+    return sum;
+}
+
+struct big fill(int start)
+{
+    struct big result;
+    for (int i = 0; i < 10; i++)
+        result.values[i] = start + i;
+    result.tail[2] = 'z';
+    return result;
+}
+
+int total(struct big big)
+{
+    int sum = 0;
+    for (int i = 0; i < 10; i++)
+        sum += big.values[i];
+    return sum + big.tail[2];
+}
+
+int length(struct node *list)
+{
+    int count = 0;
+    for (; list; list = list->next)
+        count++;
+    return count;
+}
+
+void print(char *s)
+{
+    while (*s)
+        putchar(*s++);
+}
+
+int main(void)
+{
+    struct node first = {1, &second};
+    struct point p = {.y = 5, .x = 2};
+    struct point q = p;
+    struct point grid[2][2] = {1, 2, 3, 4, [1][1] = {7, 8}};
+    union word word;
+    struct shape *shape = &box;
+
+    if (sizeof(struct point) != 8 || sizeof(struct shape) != 36 || sizeof(union word) != 4 || sizeof(struct big) != 44)
+        return 1;
+    if (length(&first) != 3 || first.next->next->value != 3 || origin.x != 0 || *unit_y != 1)
+        return 2;
+    q.x++;
+    if (p.x != 2 || q.x != 3 || q.y != 5 || grid[0][1].y != 4 || grid[1][0].x != 0 || grid[1][1].y != 8)
+        return 3;
+
+    word.value = 0x01020304;
+    if (word.bytes[0] != 4 || word.bytes[3] != 1)
+        return 4;
+
+    shape->radius = 7;
+    shape->label[4] = 'x';
+    if (box.corners[1].x != 4 || shape->corners[1].y != 3 || box.area != 12 || box.radius != 7)
+        return 5;
+
+    struct point r = add(p, unit);
+    if (r.x != 3 || r.y != 6 || add(r, r).y != 12)
+        return 6;
+
+    struct big big = fill(10);
+    struct big copy;
+    copy = big;
+    if (total(copy) != 145 + 'z' || fill(1).values[9] != 10)
+        return 7;
+
+    struct { char name[8]; int id; } people[] = {"ann", 1, "bob", 2, {"cy", 3}};
+    print(people[1].name);
+    print(people[2].name);
+    putchar('\n');
+    if (sizeof people != 36 || people[2].id != 3)
+        return 8;
+
+    if (bounds->high - bounds->low != 9)
+        return 9;
+
+    p = q = unit;
+    return p.x + q.y + r.x + grid[1][1].x + box.kind;
+}
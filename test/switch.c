@@ -0,0 +1,40 @@
+int main()
+{
+    int total = 0;
+    for (int i = -2; i < 12; i = i + 1)
+    {
+        /* Dense cases: lowered to a jump table. */
+        switch (i)
+        {
+            case 0:
+                total = total + 1;
+            case 1:                 /* falls through from 0 */
+                total = total + 2;
+                break;
+            case 2:
+            case 3:
+                total = total + 4;
+                break;
+            case 5:
+                total = total + 8;
+                break;
+            default:
+                total = total + 100;
+        }
+    }
+    int x = 70000;
+    /* Sparse cases: lowered to a binary search. */
+    switch (x)
+    {
+        case -5: return 1;
+        case 10: return 2;
+        case 1000: return 3;
+        case 70000: total = total - 1000; break;
+        case 1 << 20: return 4;
+    }
+    switch (4000000000u > 0)
+    {
+        case 1: total = total + 1;
+    }
+    return total;
+}
@@ -0,0 +1,66 @@
+int putchar(int c);
+
+typedef int T;
+typedef unsigned char byte;
+typedef char *string;
+typedef struct point { int x, y; } point;
+typedef point segment[2];
+typedef enum { OFF, ON } state;
+typedef T T;
+typedef struct { int w, h; } size, *size_ptr;
+
+int scale = 3;
+
+T length(segment s)
+{
+    return (s[1].x - s[0].x) * (s[1].y - s[0].y);
+}
+
+void print(string s)
+{
+    while (*s)
+        putchar(*s++);
+}
+
+int multiply(int T, int x)
+{
+    // `T` is a parameter here, so this is a multiplication.
+    return T * x;
+}
+
+int main(void)
+{
+    T x = 2;
+    T * p = &x;
+    byte b = 255;
+    segment s = {{1, 1}, {4, 5}};
+    state on = ON;
+
+    if (sizeof(byte) != 1 || sizeof(segment) != 16 || sizeof(string) != 4 || (byte)256 != 0)
+        return 1;
+    b++;
+    if (b != 0 || *p != 2 || length(s) != 12 || on != 1)
+        return 2;
+
+    {
+        int T = 5;
+        int y = T * x;
+        if (y != 10)
+            return 3;
+        {
+            typedef long T;
+            T z = sizeof(T);
+            x = z;
+        }
+    }
+
+    T T = 6;
+    print("typedef\n");
+    struct point *q = &s[1];
+    point r = *q;
+    size area = {2, 3};
+    size_ptr a = &area;
+    if (a->w * a->h != 6)
+        return 4;
+    return T + x + r.x + multiply(scale, 2);
+}
@@ -0,0 +1,74 @@
+int putchar(int c);
+
+static unsigned char checksum;
+long long total = 3000000000LL;
+short counts;
+
+void print_unsigned(unsigned long long value)
+{
+    if (value >= 10)
+        print_unsigned(value / 10);
+    putchar('0' + (int)(value % 10));
+}
+
+void add(unsigned char byte)
+{
+    checksum += byte;
+    if (!byte)
+        return;
+}
+
+char narrow(int value)
+{
+    return value;
+}
+
+long long scale(long long value, int shift)
+{
+    return value << shift;
+}
+
+int main(void)
+{
+    char c = 200;
+    unsigned char uc = 200;
+    short s = 70000;
+    unsigned short us = -1;
+    _Bool b = 256;
+    unsigned u = -1;
+    long long big = 1LL << 40;
+    unsigned long long ubig = -1;
+    int result = 0;
+
+    if (c == -56) result += 1;
+    if (uc == 200) result += 2;
+    if (s == 4464) result += 4;
+    if (us == 65535) result += 8;
+    if (b == 1) result += 16;
+    if (u > 0) result += 32;
+    if (-1 > 0u) result += 1024;
+    if (big / 3 == 366503875925LL && big % 7 == 2) result += 64;
+    if (ubig / 10 == 1844674407370955161ULL && ubig > 0) result += 128;
+    if (scale(-3, 33) == -25769803776LL && (scale(1, 40) >> 38) == 4) result += 256;
+    if (narrow(300) == 44 && (signed char)255 == -1 && (unsigned short)-2 == 65534) result += 512;
+
+    uc++;
+    c += 100;
+    big = big * 3 - total;
+    add(uc);
+    add(0);
+    total = -total;
+
+    print_unsigned(ubig);
+    putchar('\n');
+    print_unsigned(big);
+    putchar('\n');
+    print_unsigned(total);
+    putchar('\n');
+    print_unsigned(c);
+    putchar('\n');
+    print_unsigned(checksum + (b ? 1 : 0));
+    putchar('\n');
+    (void)counts;
+    return result - 1900;
+}